@@ -36,7 +36,7 @@ where
         };
         BitSet {
             data: vec![uint_value; (size + 7) / (8 * size_of::<UINT>())],
-            size: size,
+            size,
         }
     }
 
@@ -59,6 +59,7 @@ where
     }
 
 
+    #[allow(clippy::needless_return)]
     pub fn contains(&self, n: usize) -> bool{
         if n>=self.size(){
             panic!("index out of bounds");
@@ -86,7 +87,7 @@ where
         let uint_index: usize = n/(8*size_of::<UINT>()) as usize;   // index in data
         let position_in_uint: usize = n - (8*size_of::<UINT>())*uint_index;
         let bit_mask: UINT = UINT::one() << (8*size_of::<UINT>() - 1 - position_in_uint);
-        self.data[uint_index] = bit_mask & !self.data[uint_index];
+        self.data[uint_index] = !bit_mask & self.data[uint_index];
     }
 
     pub fn set_value(&mut self, n: usize, value: bool){
@@ -260,6 +261,7 @@ where
     }
 
 
+    #[allow(clippy::needless_return)]
     pub fn is_disjoint(&self, other: &Self) -> bool{
         if self.size() != other.size() {
             panic!("operations on bitsets with different sizes are not allowed");
@@ -308,8 +310,7 @@ where
             let uint: UINT = self.data[i];
             let uint_str: String = format!("{uint:b}");
             // total starts with 0 bits
-            let mut total: String = std::iter::repeat("0")
-                .take(8-uint_str.len())
+            let mut total: String = std::iter::repeat_n("0", 8-uint_str.len())
                 .collect();
 
             total.push_str(&uint_str);
@@ -392,8 +393,8 @@ where
             if (self.working_uint & one_left) == one_left {
                 self.nbr_ones_in_uint -= 1;
 
-                let res: Option<usize> = Some(8*size_of::<UINT>()*self.uint_index + self.index_in_uint);
-                if res.unwrap() >= self.bitset.size() {
+                let res: usize = 8*size_of::<UINT>()*self.uint_index + self.index_in_uint;
+                if res >= self.bitset.size() {
                     return None;
                 }
 
@@ -404,7 +405,7 @@ where
                     self.working_uint = self.working_uint << 1; // skips to the next bit
                     self.index_in_uint += 1;
                 }
-                return res;
+                return Some(res);
             }            
             else {
                 self.working_uint = self.working_uint << 1;
@@ -499,8 +500,8 @@ where
             if (self.working_uint & one_left) == one_left {
                 self.nbr_ones_in_uint -= 1;
 
-                let res: Option<usize> = Some(8*size_of::<UINT>()*self.uint_index + self.index_in_uint);
-                if res.unwrap() >= self.bitset.size() {
+                let res: usize = 8*size_of::<UINT>()*self.uint_index + self.index_in_uint;
+                if res >= self.bitset.size() {
                     return None;
                 }
 
@@ -511,7 +512,7 @@ where
                     self.working_uint = self.working_uint << 1; // skips to the next bit
                     self.index_in_uint += 1;
                 }
-                return res;
+                return Some(res);
             }            
             else {
                 self.working_uint = self.working_uint << 1;
@@ -526,7 +527,7 @@ where
 
 #[cfg(test)]
 mod tests{
-    use crate::datastructures::bitset::{BitSet, BitSetIter, MutBitSetIter};
+    use crate::datastructures::bitset::{BitSet, MutBitSetIter};
 
     #[test]
     fn test1(){
@@ -540,7 +541,7 @@ mod tests{
             let rand_value: u8 = ((197 + i*157)%255) as u8 & 0b01101001;
             bitset1.data[i] = rand_value;
         }
-        bitset1.data[1] = bitset1.data[1] & 0b11000000;
+        bitset1.data[1] &= 0b11000000;
 
         
 
@@ -548,7 +549,7 @@ mod tests{
             let rand_value: u8 = ((100 + i*37)%255) as u8 & 0b11001011;
             bitset2.data[i] = rand_value;
         }
-        bitset2.data[1] = bitset2.data[1] & 0b11000000;
+        bitset2.data[1] &= 0b11000000;
 
 
         println!("size: {}", bitset1.size());
@@ -577,7 +578,7 @@ mod tests{
         let mut it: MutBitSetIter<u8> = (&mut bitset1).into_iter();
         for i in 0..5 {
             it.bitset.insert(i);
-            while let Some(symb) = it.next() {
+            for symb in it.by_ref() {
                 println!("value youpi: {}", symb);
             }
             it.reset();
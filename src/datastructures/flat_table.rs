@@ -1,7 +1,6 @@
 use std::{marker::PhantomData, ops::Index};
 use num::{Unsigned, PrimInt};
 use std::fmt::Debug;
-use std::mem::size_of;
 
 pub trait Indexing: From<usize> + Into<usize> + Copy {}
 
@@ -122,7 +121,7 @@ where
     type Output = [T];
 
     fn index(&self, index: Idxx) -> &[T] {
-        &self.table[usize::from(index)*self.width..usize::from(index)*(self.width+1)]
+        &self.table[usize::from(index)*self.width..(usize::from(index)+1)*self.width]
     }
 }
 
@@ -1,15 +1,21 @@
-use std::cell::OnceCell;
+pub mod k_sets;
+pub mod precedence;
+pub mod transforms;
+
+use std::cell::{OnceCell, RefCell};
 use std::borrow::Cow;
-use std::result;
+use std::collections::HashMap;
+use std::rc::Rc;
 use crate::datastructures::flat_table::{FlatTable, Indexing};
 
-use num::iter::Range;
 use thiserror::Error;
 use derive_more::{Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign};
 use delegate::delegate;
 
 use crate::datastructures::bitset::BitSet;
 use crate::UINT;
+use k_sets::KSets;
+use precedence::PrecedenceTable;
 
 
 // --------------------------------------------
@@ -23,15 +29,12 @@ impl Alphabet {
     pub fn new(mut chars: Vec<char>) -> Self {
         chars.sort();
         chars.dedup();
-        Alphabet { chars: chars }
+        Alphabet { chars }
     }
 
     pub fn id(&self, c: char) -> Option<usize> {
         let result: Result<usize, usize> = self.chars.binary_search(&c);
-        match result {
-            Ok(index) => Some(index),
-            Err(_) => None,
-        }
+        result.ok()
     }
 
     pub fn size(&self) -> usize {
@@ -56,7 +59,7 @@ impl Alphabet {
 
 const EXPECTED_RULE_SIZE: usize = 10;
 
-#[derive(Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign, PartialEq, Debug, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign, PartialEq, Debug, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct SymbolIdx(pub u16);
 
 impl From<usize> for SymbolIdx {
@@ -74,7 +77,7 @@ impl From<SymbolIdx> for usize {
 impl Indexing for SymbolIdx {}
 
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
 pub struct Symbol {
     pub id: SymbolIdx,
 }
@@ -106,6 +109,7 @@ impl TryFrom<OptionSymbol> for Symbol {
 
     type Error = ();
 
+    #[allow(clippy::needless_return)]
     fn try_from(value: OptionSymbol) -> Result<Self, Self::Error> {
         if value.is_none() {
             return Err(());
@@ -138,7 +142,7 @@ impl From<Symbol> for OptionSymbol {
 
 // --------------------------------------------
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: Symbol,
     pub lexeme: String,
@@ -179,7 +183,7 @@ impl SymbolSet {
     }
 
     pub fn size(&self) -> SymbolIdx {
-        self.representations.len().try_into().unwrap()
+        self.representations.len().into()
     }
 
     pub fn get_representation(&self, local_id: SymbolIdx) -> &String {
@@ -191,7 +195,7 @@ impl SymbolSet {
         self.representations
             .iter()
             .position(|s| s==representation)
-            .map(|x| SymbolIdx::try_from(x).unwrap())
+            .map(SymbolIdx::from)
 
     }
 }
@@ -209,6 +213,15 @@ pub enum CfgError {
     InvalidRuleReplacement{rule: CfgRule, symbol: Symbol},
 }
 
+/// why a rule is never used in the derivation of a word of terminals from START
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UselessRuleReason {
+    /// a symbol of the replacement derives no word of terminals
+    NonProductive,
+    /// the origin can't be reached from START with the productive rules
+    Unreachable,
+}
+
 // --------------------------------------------
 
 pub struct CfgSymbolSet {
@@ -274,14 +287,17 @@ impl CfgSymbolSet {
         (0..self.nbr_terminals().0 + self.nbr_non_terminals().0).map(|value| Symbol {id: SymbolIdx(value)})
     }
 
+    #[allow(clippy::needless_return)]
     pub fn is_terminal(&self, symbol: Symbol) -> bool {
         return symbol.id >= self.nbr_non_terminals()
     }
 
+    #[allow(clippy::needless_return)]
     pub fn is_non_terminal(&self, symbol: Symbol) -> bool {
         return symbol.id < self.nbr_non_terminals()
     }
     
+    #[allow(clippy::needless_return)]
     pub fn is_special(&self, symbol: Symbol) -> bool {
         return symbol == self.START() 
         || symbol == self.END()
@@ -293,6 +309,7 @@ impl CfgSymbolSet {
     /// If the symbol is a non-terminal or a terminal symbol, returns the 
     /// corresponding index in the corresponding SymbolSet
     /// Returns None if the symbol is a special symbol
+    #[allow(clippy::needless_return)]
     pub fn to_local_non_special(&self, symbol: Symbol) -> Option<SymbolIdx> {
         if self.is_special(symbol) {
             return None;
@@ -308,6 +325,7 @@ impl CfgSymbolSet {
 
     /// If the symbol is a non-terminal or a terminal symbol, returns the 
     /// corresponding index in the corresponding set of symbols, where special symbols are included
+    #[allow(clippy::needless_return)]
     pub fn to_local(&self, symbol: Symbol) -> SymbolIdx {
 
         if self.is_non_terminal(symbol) {
@@ -321,13 +339,19 @@ impl CfgSymbolSet {
     /// If the symbol is a non-terminal or a terminal symbol, returns the 
     /// corresponding index in the corresponding set of symbols, where special symbols are included
     /// as well as NOSYMBOL
+    /// NOSYMBOL has the index 0, so every other index is shifted by one
     pub fn to_local_nosymbol(&self, symbol: Symbol) -> SymbolIdx {
+        self.to_local(symbol) + SymbolIdx(1)
+    }
 
-        if self.is_non_terminal(symbol) {
-            return symbol.id;
+    /// inverse of `to_local_nosymbol` for terminal symbols
+    /// Returns None for the index of NOSYMBOL
+    pub fn terminal_from_local_nosymbol(&self, local_id: SymbolIdx) -> Option<Symbol> {
+        if local_id == SymbolIdx(0) {
+            None
         }
         else {
-            return symbol.id - self.nbr_non_terminals();
+            Some(Symbol { id: local_id - SymbolIdx(1) + self.nbr_non_terminals() })
         }
     }
 
@@ -400,7 +424,7 @@ impl CfgRule {
 }
 
 
-#[derive(Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign, PartialEq, Debug, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Add, Sub, Mul, Div, AddAssign, SubAssign, MulAssign, DivAssign, PartialEq, Debug, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct CfgRuleIdx(pub u16);
 
 impl From<usize> for CfgRuleIdx {
@@ -444,6 +468,9 @@ pub struct Cfg {
     symbol_set: CfgSymbolSet,
     rules: FlatTable<CfgRule, SymbolIdx, CfgRuleBySymbolIdx, CfgRuleIdx>,
 
+    // used by the LR generators to resolve shift/reduce conflicts
+    precedence_table: Option<PrecedenceTable>,
+
     // -------------- cached values
 
     // indexed by produced symbols
@@ -452,19 +479,30 @@ pub struct Cfg {
     // indexed by symbols
     are_symbols_nullable: OnceCell<BitSet<UINT>>,
 
-    // indexed by non-terminal symbols, bitset by terminal-symbols
+    // indexed by non-terminal symbols
+    are_symbols_productive: OnceCell<BitSet<UINT>>,
+
+    // indexed by symbols
+    are_symbols_reachable: OnceCell<BitSet<UINT>>,
+
+    // indexed by non-terminal symbols, bitset by optional terminal symbols (see to_local_nosymbol)
     first_sets: OnceCell<Vec<BitSet<UINT>>>,
 
+    // indexed by non-terminal symbols, bitset by optional terminal symbols (see to_local_nosymbol)
+    follow_sets: OnceCell<Vec<BitSet<UINT>>>,
+
+    // indexed by rules, bitset by optional terminal symbols (see to_local_nosymbol)
+    predict_sets: OnceCell<Vec<BitSet<UINT>>>,
+
+    // indexed by k, see k_sets
+    k_sets: RefCell<HashMap<usize, Rc<KSets>>>,
+
     /*
     get_NTsymbols_implied_by_rule
     get_NTsymbols_implied_by_symbol
     get_rules_indirectly_producing
-    get_terminating_symbols
     is_word_nullable
     is_rule_nullable
-    compute_
-    compute_follow_sets
-    compute_predict_sets
      */
 }
 
@@ -477,10 +515,10 @@ impl  Cfg {
         let nbr_non_terminals: SymbolIdx = symbol_set.nbr_non_terminals();
         let nbr_terminals: SymbolIdx = symbol_set.nbr_terminals();
 
-        rules.sort_by(|rule1, rule2| rule1.origin.id.cmp(&rule2.origin.id));
+        rules.sort_by_key(|rule1| rule1.origin.id);
 
         // checks that each rule is valid
-        for (rule_id, rule) in rules.iter().enumerate() {
+        for rule in rules.iter() {
             if rule.origin.id >= nbr_non_terminals {
                 return Err(CfgError::InvalidRuleOrigin { rule: rule.clone() });
             }
@@ -508,12 +546,24 @@ impl  Cfg {
             symbol_set,
             rules: FlatTable::new(rules, rule_origin_correspondance),
 
+            precedence_table: None,
+
             rules_producing_each_symbol: OnceCell::new(),
 
             are_symbols_nullable: OnceCell::new(),
 
+            are_symbols_productive: OnceCell::new(),
+
+            are_symbols_reachable: OnceCell::new(),
+
             first_sets: OnceCell::new(),
 
+            follow_sets: OnceCell::new(),
+
+            predict_sets: OnceCell::new(),
+
+            k_sets: RefCell::new(HashMap::new()),
+
         })
     }
 
@@ -523,6 +573,14 @@ impl  Cfg {
         &self.symbol_set
     }
 
+    pub fn get_precedence_table(&self) -> Option<&PrecedenceTable> {
+        self.precedence_table.as_ref()
+    }
+
+    pub fn set_precedence_table(&mut self, precedence_table: PrecedenceTable) {
+        self.precedence_table = Some(precedence_table);
+    }
+
     delegate! {
         to self.symbol_set {
             pub fn START(&self) -> Symbol;
@@ -540,6 +598,8 @@ impl  Cfg {
             pub fn is_special(&self, symbol: Symbol) -> bool;
             pub fn to_local_non_special(&self, symbol: Symbol) -> Option<SymbolIdx>;
             pub fn to_local(&self, symbol: Symbol) -> SymbolIdx;
+            pub fn to_local_nosymbol(&self, symbol: Symbol) -> SymbolIdx;
+            pub fn terminal_from_local_nosymbol(&self, local_id: SymbolIdx) -> Option<Symbol>;
             pub fn repr_symbol(&self, symbol: Symbol) -> &str;
         }
     }
@@ -583,6 +643,7 @@ impl  Cfg {
         s
     }
 
+    #[allow(clippy::needless_return)]
     pub fn nbr_rules(&self) -> CfgRuleIdx {
         return self.rules.size();
     }
@@ -602,7 +663,7 @@ impl  Cfg {
 
     pub fn get_rules_by_origin(&self, origin: Symbol) -> impl Iterator<Item = (CfgRuleIdx, &CfgRule)> {
         let rule_id: CfgRuleIdx = self.rules.rows[usize::from(origin.id)];
-        (&self.rules[origin.id])
+        self.rules[origin.id]
             .iter()
             .enumerate()
             .map(move |(id, rule)| (CfgRuleIdx::from(id) + rule_id, rule))
@@ -614,7 +675,7 @@ impl  Cfg {
         let rules_producing_each_symbol: &Vec<BitSet<UINT>> = 
             self.rules_producing_each_symbol.get_or_init(|| self.compute_rules_producing_each_symbol());
 
-        (&rules_producing_each_symbol[usize::from(produced_symbol.id)])
+        rules_producing_each_symbol[usize::from(produced_symbol.id)]
             .iter()
             .map(|rule_id| (CfgRuleIdx::from(rule_id), self.get_rule_by_id(CfgRuleIdx::from(rule_id))))
     }
@@ -655,6 +716,7 @@ impl  Cfg {
         self.get_rule_by_id(rule_id).replacement.iter().all(|&symbol| self.is_symbol_nullable(symbol))
     }
 
+    #[allow(clippy::len_zero)]
     fn compute_are_symbols_nullable(&self) -> BitSet<UINT> {
         // adapted from here:
         // https://cstheory.stackexchange.com/questions/2479/quickly-finding-empty-string-producing-nonterminals-in-a-cfg
@@ -708,13 +770,170 @@ impl  Cfg {
         are_nullable
     }
 
+    // -------------------------- productive/reachable symbols, useless rules
+
+    /// a symbol is productive if it derives a word of terminals, the terminals always are
+    pub fn is_symbol_productive(&self, symbol: Symbol) -> bool {
+        if self.is_terminal(symbol) {
+            true
+        }
+        else {
+            self.are_symbols_productive
+                .get_or_init(|| self.compute_are_symbols_productive())
+                .contains(usize::from(symbol.id))
+        }
+    }
+
+    /// a symbol is reachable if it appears in a sentential form derived from START, START always is
+    pub fn is_symbol_reachable(&self, symbol: Symbol) -> bool {
+        self.are_symbols_reachable
+            .get_or_init(|| self.compute_reachable_symbols(|_| true))
+            .contains(usize::from(symbol.id))
+    }
+
+    fn compute_are_symbols_productive(&self) -> BitSet<UINT> {
+        // same propagation as the nullable symbols, with the terminals considered productive
+
+        let mut are_productive: BitSet<UINT> = BitSet::new_filled(false, usize::from(self.nbr_non_terminals()));
+
+        // number of distinct non_terminals not marked as productive in the replacement of the index rule
+        let mut nbr_unproductive: Vec<SymbolIdx> = vec![SymbolIdx(0); usize::from(self.nbr_rules())];
+
+        // stack of non_terminals that have been marked productive but not yet processed
+        let mut unprocessed_productive_symbols: Vec<Symbol> = Vec::new();
+
+        for (rule_id, rule) in self.all_rules() {
+            let (_, _, dist_non_term) = self.count_symbols_in_rule(rule);
+            nbr_unproductive[usize::from(rule_id)] = dist_non_term;
+            if dist_non_term == SymbolIdx(0) && !are_productive.contains(usize::from(rule.origin.id)) {
+                are_productive.insert(usize::from(rule.origin.id));
+                unprocessed_productive_symbols.push(rule.origin);
+            }
+        }
+
+        while let Some(unprocessed_productive_symbol) = unprocessed_productive_symbols.pop() {
+            for (rule_id, rule) in self.get_rules_producing(unprocessed_productive_symbol) {
+                nbr_unproductive[usize::from(rule_id)] -= SymbolIdx(1);
+
+                if nbr_unproductive[usize::from(rule_id)] == SymbolIdx(0) && !are_productive.contains(usize::from(rule.origin.id)) {
+                    are_productive.insert(usize::from(rule.origin.id));
+                    unprocessed_productive_symbols.push(rule.origin);
+                }
+            }
+        }
+
+        are_productive
+    }
+
+    /// the symbols appearing in a sentential form derived from START with the rules kept by the filter
+    fn compute_reachable_symbols(&self, rule_filter: impl Fn(CfgRuleIdx) -> bool) -> BitSet<UINT> {
+        let mut are_reachable: BitSet<UINT> = BitSet::new_filled(false, usize::from(self.nbr_symbols()));
+        are_reachable.insert(usize::from(self.START().id));
+
+        let mut non_terminals_to_process: Vec<Symbol> = vec![self.START()];
+        while let Some(non_terminal) = non_terminals_to_process.pop() {
+            for (rule_id, rule) in self.get_rules_by_origin(non_terminal) {
+                if !rule_filter(rule_id) {
+                    continue;
+                }
+                for &symbol in &rule.replacement {
+                    if !are_reachable.contains(usize::from(symbol.id)) {
+                        are_reachable.insert(usize::from(symbol.id));
+                        if self.is_non_terminal(symbol) {
+                            non_terminals_to_process.push(symbol);
+                        }
+                    }
+                }
+            }
+        }
+
+        are_reachable
+    }
+
+    /// the rules never used in the derivation of a word of terminals from START
+    /// the reachability is checked once the non-productive rules are removed
+    pub fn get_useless_rules(&self) -> Vec<(CfgRuleIdx, UselessRuleReason)> {
+        let is_rule_productive = |rule_id: CfgRuleIdx|
+            self.get_rule_by_id(rule_id).replacement.iter().all(|&symbol| self.is_symbol_productive(symbol));
+        let are_reachable: BitSet<UINT> = self.compute_reachable_symbols(is_rule_productive);
+
+        self.all_rules()
+            .filter_map(|(rule_id, rule)| {
+                if !is_rule_productive(rule_id) {
+                    Some((rule_id, UselessRuleReason::NonProductive))
+                }
+                else if !are_reachable.contains(usize::from(rule.origin.id)) {
+                    Some((rule_id, UselessRuleReason::Unreachable))
+                }
+                else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// equivalent grammar without the useless rules, and without the symbols that only appear in them
+    /// the special symbols are kept, the other ones are renumbered in the same order, and the precedence table
+    /// isn't kept since the ids change
+    pub fn reduce(&self) -> Cfg {
+        let mut are_rules_useful: BitSet<UINT> = BitSet::new_filled(false, usize::from(self.nbr_rules()));
+        for (rule_id, _) in self.all_rules() {
+            are_rules_useful.insert(usize::from(rule_id));
+        }
+        for (rule_id, _) in self.get_useless_rules() {
+            are_rules_useful.remove(usize::from(rule_id));
+        }
+
+        let mut are_symbols_kept: BitSet<UINT> = BitSet::new_filled(false, usize::from(self.nbr_symbols()));
+        for symbol in [self.ERR_NON_TERM(), self.START(), self.ERR_TERM(), self.END()] {
+            are_symbols_kept.insert(usize::from(symbol.id));
+        }
+        for rule_id in &are_rules_useful {
+            let rule: &CfgRule = self.get_rule_by_id(CfgRuleIdx::from(rule_id));
+            for &symbol in std::iter::once(&rule.origin).chain(&rule.replacement) {
+                are_symbols_kept.insert(usize::from(symbol.id));
+            }
+        }
+
+        // the kept symbols keep their order, so the special symbols stay at their place
+        let mut new_symbols: Vec<Option<Symbol>> = vec![None; usize::from(self.nbr_symbols())];
+        for (new_id, old_id) in are_symbols_kept.iter().enumerate() {
+            new_symbols[old_id] = Some(Symbol { id: SymbolIdx::from(new_id) });
+        }
+        let representations = |symbols: &mut dyn Iterator<Item = Symbol>| -> Vec<String> {
+            symbols
+                .filter(|&symbol| !self.is_special(symbol) && are_symbols_kept.contains(usize::from(symbol.id)))
+                .map(|symbol| String::from(self.repr_symbol(symbol)))
+                .collect()
+        };
+        let symbol_set: CfgSymbolSet = CfgSymbolSet::new(
+            SymbolSet::new(representations(&mut self.all_terminals())),
+            SymbolSet::new(representations(&mut self.all_non_terminals())),
+        );
+
+        // unwrap is safe because every symbol of a useful rule is kept
+        let new_symbol = |symbol: Symbol| new_symbols[usize::from(symbol.id)].unwrap();
+        let rules: Vec<CfgRule> = are_rules_useful
+            .iter()
+            .map(|rule_id| {
+                let rule: &CfgRule = self.get_rule_by_id(CfgRuleIdx::from(rule_id));
+                CfgRule { origin: new_symbol(rule.origin), replacement: rule.replacement.iter().map(|&symbol| new_symbol(symbol)).collect() }
+            })
+            .collect();
+
+        // unwrap is safe because the rules only use the kept symbols
+        Cfg::new(symbol_set, rules).unwrap()
+    }
+
     // -------------------------- first sets
 
-    fn get_first_set(&self, symbol: Symbol) -> Cow<BitSet<UINT>> {
+    /// returns the first set of the symbol, as a bitset of optional terminal symbols
+    /// indexed by `to_local_nosymbol`: the index 0 (NOSYMBOL) is set iff the symbol is nullable
+    pub fn get_first_set(&self, symbol: Symbol) -> Cow<'_, BitSet<UINT>> {
         if self.is_terminal(symbol) {
             let mut first_set: BitSet<UINT> = BitSet::new_filled(false, usize::from(self.nbr_terminals())+1);
             // set containing only the terminal symbol itself
-            first_set.insert(usize::from(OptionSymbol::from(symbol).id));
+            first_set.insert(usize::from(self.to_local_nosymbol(symbol)));
             
             Cow::Owned(first_set)
         }
@@ -726,71 +945,253 @@ impl  Cfg {
         }
     }
 
-    fn compute_first_sets(&self) -> Vec<BitSet<UINT>> {
+    /// returns the first set of the word, with the same encoding as `get_first_set`:
+    /// NOSYMBOL is in the set iff the word is nullable (in particular if the word is empty)
+    pub fn get_first_set_of_word(&self, word: &[Symbol]) -> BitSet<UINT> {
+        let mut first_set: BitSet<UINT> = BitSet::new_filled(false, usize::from(self.nbr_terminals())+1);
+
+        for &symbol in word {
+            first_set.update_union(&self.get_first_set(symbol));
+            if !self.is_symbol_nullable(symbol) {
+                // the word isn't nullable, NOSYMBOL might have been added by a previous symbol
+                first_set.remove(0);
+                return first_set;
+            }
+        }
 
-        // first_sets: dict[Symbol, set[Symbol | NOSYMBOL]] = {symbol: set() for symbol in self.NTsymbols}
+        first_set.insert(0);
+        first_set
+    }
 
+    fn compute_first_sets(&self) -> Vec<BitSet<UINT>> {
 
         // for each non-terminal symbol, contains a bitset of optional terminal symbols
         // the terminal symbol case is trivial so not computed
-        let first_sets: Vec<BitSet<UINT>> = 
+        let mut first_sets: Vec<BitSet<UINT>> = 
         vec![BitSet::new_filled(false, usize::from(self.nbr_terminals())+1); usize::from(self.nbr_non_terminals())];
 
         // for each non-terminal symbol, maps to the set of non-terminal symbols that rely on it (inculde it)
-        let inclusions: Vec<BitSet<UINT>> = 
+        let mut inclusions: Vec<BitSet<UINT>> = 
         vec![BitSet::new_filled(false, usize::from(self.nbr_non_terminals())); usize::from(self.nbr_non_terminals())];
         // inclusions[a] = {b, c, d} <=> first(a) included in first(b), first(c), and first(d)
 
         // for each terminal symbol, maps to the set of non-terminal symbols that relies on it (inculde it)
-        let terminal_inclusions: Vec<BitSet<UINT>> = 
+        let mut terminal_inclusions: Vec<BitSet<UINT>> = 
         vec![BitSet::new_filled(false, usize::from(self.nbr_non_terminals())); usize::from(self.nbr_terminals())];
         // terminal_inclusions[a] = [b, c, d] <=> a included in first(b), first(c), and first(d)
 
         // initialize results that include firsts
-        for (rule_id, rule) in self.all_rules() {
-            for symbol in rule.replacement {
+        for (_, rule) in self.all_rules() {
+            for &symbol in &rule.replacement {
                 if self.is_terminal(symbol) {
-                    // the Tsymbol is included in the first set of the origin of the rule
+                    // the terminal symbol is included in the first set of the origin of the rule
                     terminal_inclusions[usize::from(self.to_local(symbol))].insert(
                         usize::from(self.to_local(rule.origin)));
                     break;
                 }
 
-                else:
-                    # the first set of the NTsymbol is included in the first set of the origin of the rule
-                    inclusions[symbol].append(rule.origin)
+                // the first set of the non-terminal symbol is included in the first set of the origin of the rule
+                inclusions[usize::from(self.to_local(symbol))].insert(usize::from(self.to_local(rule.origin)));
 
-                    if not self.is_symbol_nullable(symbol):  # we stay in the loop only if this NTsymbol is nullable
-                        break
+                if !self.is_symbol_nullable(symbol) {
+                    // we stay in the loop only if this non-terminal symbol is nullable
+                    break;
+                }
+            }
+        }
+
+        // propagation of each terminal symbol
+        let mut non_terminals_processed: BitSet<UINT> = BitSet::new_filled(false, usize::from(self.nbr_non_terminals()));
+        for terminal in self.all_terminals() {
+            let mut non_terminals_to_process: Vec<usize> = 
+                terminal_inclusions[usize::from(self.to_local(terminal))].iter().collect();
+            non_terminals_processed.clear();
+
+            while let Some(non_terminal_to_process) = non_terminals_to_process.pop() {
+
+                if non_terminals_processed.contains(non_terminal_to_process) {
+                    continue;
+                }
+
+                first_sets[non_terminal_to_process].insert(usize::from(self.to_local_nosymbol(terminal)));
+
+                for new_non_terminal_to_process in &inclusions[non_terminal_to_process] {
+                    if !non_terminals_processed.contains(new_non_terminal_to_process) {
+                        non_terminals_to_process.push(new_non_terminal_to_process);
+                    }
+                }
+
+                non_terminals_processed.insert(non_terminal_to_process);
+            }
+        }
+
+        for non_terminal in self.all_non_terminals() {
+            if self.is_symbol_nullable(non_terminal) {
+                first_sets[usize::from(self.to_local(non_terminal))].insert(0);
+            }
+        }
+
+        first_sets
+    }
+
+    // -------------------------- follow sets
+
+    /// returns the follow set of the non-terminal symbol, as a bitset of optional terminal symbols 
+    /// indexed by `to_local_nosymbol`, NOSYMBOL is never in it
+    /// END is in the follow set of START
+    pub fn get_follow_set(&self, non_terminal: Symbol) -> &BitSet<UINT> {
+        &self.follow_sets
+            .get_or_init(|| self.compute_follow_sets())[usize::from(self.to_local(non_terminal))]
+    }
+
+    fn compute_follow_sets(&self) -> Vec<BitSet<UINT>> {
+
+        let mut follow_sets: Vec<BitSet<UINT>> = 
+        vec![BitSet::new_filled(false, usize::from(self.nbr_terminals())+1); usize::from(self.nbr_non_terminals())];
+
+        follow_sets[usize::from(self.to_local(self.START()))].insert(usize::from(self.to_local_nosymbol(self.END())));
+
+        // for each non-terminal symbol, maps to the set of non-terminal symbols that rely on it (inculde it)
+        let mut inclusions: Vec<BitSet<UINT>> = 
+        vec![BitSet::new_filled(false, usize::from(self.nbr_non_terminals())); usize::from(self.nbr_non_terminals())];
+        // inclusions[a] = {b, c, d} <=> follow(a) included in follow(b), follow(c), and follow(d)
+
+        for (_, rule) in self.all_rules() {
+            for (position, &symbol) in rule.replacement.iter().enumerate() {
+                if self.is_terminal(symbol) {
+                    continue;
+                }
+
+                // the first set of what comes after the non-terminal symbol is included in its follow set
+                let first_set_after: BitSet<UINT> = self.get_first_set_of_word(&rule.replacement[position+1..]);
+                let follow_set: &mut BitSet<UINT> = &mut follow_sets[usize::from(self.to_local(symbol))];
+                follow_set.update_union(&first_set_after);
+                follow_set.remove(0);
+
+                if first_set_after.contains(0) && rule.origin != symbol {
+                    // what comes after is nullable: the follow set of the origin is included
+                    inclusions[usize::from(self.to_local(rule.origin))].insert(usize::from(self.to_local(symbol)));
+                }
+            }
+        }
+
+        // propagation along the inclusions until nothing changes
+        // stack of non-terminals whose follow set changed but hasn't been propagated yet
+        let mut non_terminals_to_process: Vec<usize> = (0..usize::from(self.nbr_non_terminals())).collect();
+        let mut is_waiting: BitSet<UINT> = BitSet::new_filled(true, usize::from(self.nbr_non_terminals()));
+
+        while let Some(non_terminal_to_process) = non_terminals_to_process.pop() {
+            is_waiting.remove(non_terminal_to_process);
+
+            for including_non_terminal in &inclusions[non_terminal_to_process] {
+                if follow_sets[non_terminal_to_process].is_subset(&follow_sets[including_non_terminal]) {
+                    continue;
+                }
+                let new_follow_set: BitSet<UINT> = 
+                    follow_sets[including_non_terminal].union(&follow_sets[non_terminal_to_process]);
+                follow_sets[including_non_terminal] = new_follow_set;
+
+                if !is_waiting.contains(including_non_terminal) {
+                    is_waiting.insert(including_non_terminal);
+                    non_terminals_to_process.push(including_non_terminal);
+                }
             }
         }
-        # propagation of each Tsymbol
-        for Tsymbol in self.Tsymbols:
-            NTsymbols_to_process: list[Symbol] = terminal_inclusions[Tsymbol].copy()
-            NTsymbols_processed: set[Symbol] = set()
 
-            while len(NTsymbols_to_process)>0:
-                NTsymbol_to_process = NTsymbols_to_process.pop()
+        follow_sets
+    }
 
-                if NTsymbol_to_process in NTsymbols_processed:
-                    continue
+    // -------------------------- predict sets
 
-                first_sets[NTsymbol_to_process].add(Tsymbol)
+    /// returns the predict set of the rule, as a bitset of optional terminal symbols 
+    /// indexed by `to_local_nosymbol`, NOSYMBOL is never in it
+    /// it is the set of terminals that can be read when the rule is used in a leftmost derivation
+    pub fn get_predict_set(&self, rule_id: CfgRuleIdx) -> &BitSet<UINT> {
+        &self.predict_sets
+            .get_or_init(|| self.compute_predict_sets())[usize::from(rule_id)]
+    }
 
-                for new_NTsymbol_to_process in inclusions[NTsymbol_to_process]:
-                    if not new_NTsymbol_to_process in NTsymbols_processed:
-                        NTsymbols_to_process.append(new_NTsymbol_to_process)
+    fn compute_predict_sets(&self) -> Vec<BitSet<UINT>> {
+        let mut predict_sets: Vec<BitSet<UINT>> = Vec::with_capacity(usize::from(self.nbr_rules()));
 
-                NTsymbols_processed.add(NTsymbol_to_process)
+        for (_, rule) in self.all_rules() {
+            let mut predict_set: BitSet<UINT> = self.get_first_set_of_word(&rule.replacement);
 
-        for NTsymbol in self.NTsymbols:
-            if self.is_symbol_nullable(NTsymbol):
-                first_sets[NTsymbol].add(NoSymbol)
+            if predict_set.contains(0) {
+                predict_set.remove(0);
+                predict_set.update_union(self.get_follow_set(rule.origin));
+            }
+            predict_sets.push(predict_set);
+        }
 
-        self.first_sets = first_sets
+        predict_sets
     }
 
+    // -------------------------- representations
 
+    /// represents a bitset of optional terminal symbols indexed by `to_local_nosymbol`
+    pub fn repr_optional_terminal_set(&self, optional_terminal_set: &BitSet<UINT>) -> String {
+        let mut s: String = String::from("{");
+
+        for local_id in optional_terminal_set {
+            match self.terminal_from_local_nosymbol(SymbolIdx::from(local_id)) {
+                None => s.push_str("NOSYMBOL"),
+                Some(terminal) => s.push_str(self.repr_symbol(terminal)),
+            }
+            s.push_str(", ");
+        }
+        if s.len() > 1 {
+            s.truncate(s.len() - 2);
+        }
+        s.push('}');
+        s
+    }
+
+    /// one line per non-terminal symbol: `Symbol: {first set}`
+    pub fn repr_first_sets(&self) -> String {
+        let mut s: String = String::new();
+        for non_terminal in self.all_non_terminals() {
+            s.push_str(&format!("{}: {}\n", 
+                self.repr_symbol(non_terminal), 
+                self.repr_optional_terminal_set(&self.get_first_set(non_terminal))));
+        }
+        s
+    }
+
+    /// one line per non-terminal symbol: `Symbol: {follow set}`
+    pub fn repr_follow_sets(&self) -> String {
+        let mut s: String = String::new();
+        for non_terminal in self.all_non_terminals() {
+            s.push_str(&format!("{}: {}\n", 
+                self.repr_symbol(non_terminal), 
+                self.repr_optional_terminal_set(self.get_follow_set(non_terminal))));
+        }
+        s
+    }
+
+    /// one line per useless rule: `rule: reason`
+    pub fn repr_useless_rules(&self) -> String {
+        let mut s: String = String::new();
+        for (rule_id, reason) in self.get_useless_rules() {
+            let reason: &str = match reason {
+                UselessRuleReason::NonProductive => "non-productive",
+                UselessRuleReason::Unreachable => "unreachable",
+            };
+            s.push_str(&format!("{}: {}\n", self.repr_rule(rule_id), reason));
+        }
+        s
+    }
+
+    /// one line per rule: `rule: {predict set}`
+    pub fn repr_predict_sets(&self) -> String {
+        let mut s: String = String::new();
+        for (rule_id, _) in self.all_rules() {
+            s.push_str(&format!("{}: {}\n", 
+                self.repr_rule(rule_id), 
+                self.repr_optional_terminal_set(self.get_predict_set(rule_id))));
+        }
+        s
+    }
 
 }
 
@@ -828,3 +1229,62 @@ impl  Cfg {
     self.predict_k_sets: dict[int, dict[CFRule: set[TupleSymbolicWord]]] = {}
      */
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::create_grammar;
+
+    fn create_grammar_with_useless_rules() -> Cfg {
+        create_grammar(
+            &["a", "b", "c", "d", "e"],
+            &["S", "B", "C", "D", "E"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["a", "S", "b"]),
+                ("S", &["e"]),
+                ("S", &["B", "E"]),
+                ("B", &["B", "c"]),
+                ("C", &["c", "D"]),
+                ("D", &["d"]),
+                ("E", &["e"]),
+            ],
+        )
+    }
+
+    #[test]
+    fn productive_and_reachable_symbols() {
+        let cfg = create_grammar_with_useless_rules();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+
+        assert!(["START", "S", "C", "D", "E", "a"].iter().all(|s| cfg.is_symbol_productive(S(s))));
+        assert!(!cfg.is_symbol_productive(S("B")));
+
+        assert!(["START", "S", "B", "E", "a", "c", "END"].iter().all(|s| cfg.is_symbol_reachable(S(s))));
+        assert!(["C", "D", "d"].iter().all(|s| !cfg.is_symbol_reachable(S(s))));
+
+        println!("{}", cfg.repr_useless_rules());
+        // E is only reachable through the non-productive B
+        assert_eq!(cfg.repr_useless_rules(), "\
+            S --> B E: non-productive\n\
+            B --> B c: non-productive\n\
+            C --> c D: unreachable\n\
+            D --> d: unreachable\n\
+            E --> e: unreachable\n");
+    }
+
+    #[test]
+    fn reduce() {
+        let cfg = create_grammar_with_useless_rules();
+        let reduced = cfg.reduce();
+
+        let rules: Vec<String> = reduced.all_rules().map(|(rule_id, _)| reduced.repr_rule(rule_id)).collect();
+        assert_eq!(rules, ["START --> S END", "S --> a S b", "S --> e"]);
+        assert_eq!(reduced.nbr_non_terminals(), SymbolIdx(3));
+        // ERR_TERM, END, a, b, e
+        assert_eq!(reduced.nbr_terminals(), SymbolIdx(5));
+        assert!(reduced.get_useless_rules().is_empty());
+        assert_eq!(reduced.repr_symbol(reduced.END()), "END");
+    }
+}
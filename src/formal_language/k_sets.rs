@@ -0,0 +1,427 @@
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
+use derive_more::{Add, Sub};
+
+use super::{Cfg, CfgRuleIdx, Symbol};
+use crate::datastructures::flat_table::Indexing;
+
+
+// --------------------------------------------
+
+#[derive(Add, Sub, PartialEq, Debug, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct TerminalWordIdx(pub u32);
+
+impl From<usize> for TerminalWordIdx {
+    fn from(value: usize) -> Self {
+        Self(u32::try_from(value).expect("ids of terminal words must be storable in the u32 type") )
+    }
+}
+
+impl From<TerminalWordIdx> for usize {
+    fn from(value: TerminalWordIdx) -> Self {
+        value.0 as usize
+    }
+}
+
+impl Indexing for TerminalWordIdx {}
+
+
+/// a set of interned words, the ids only make sense with the TerminalWordInterner that created them
+pub type TerminalWordSet = BTreeSet<TerminalWordIdx>;
+
+
+// --------------------------------------------
+
+/// interns the words of at most k terminal symbols, so that sets of words are sets of integers
+/// the empty word always has the id 0
+#[derive(Clone)]
+pub struct TerminalWordInterner {
+    k: usize,
+    words: Vec<Box<[Symbol]>>,
+    ids: HashMap<Box<[Symbol]>, TerminalWordIdx>,
+
+    // memoization of the k-concatenations: (word1, word2) -> first k symbols of word1.word2
+    concatenations: HashMap<(TerminalWordIdx, TerminalWordIdx), TerminalWordIdx>,
+}
+
+impl TerminalWordInterner {
+
+    pub fn new(k: usize) -> Self {
+        let mut interner = TerminalWordInterner {
+            k,
+            words: Vec::new(),
+            ids: HashMap::new(),
+            concatenations: HashMap::new(),
+        };
+        interner.intern(&[]);
+        interner
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn empty_word(&self) -> TerminalWordIdx {
+        TerminalWordIdx(0)
+    }
+
+    pub fn nbr_words(&self) -> usize {
+        self.words.len()
+    }
+
+    /// only the first k symbols of the word are kept
+    pub fn intern(&mut self, word: &[Symbol]) -> TerminalWordIdx {
+        let word: &[Symbol] = &word[..word.len().min(self.k)];
+
+        if let Some(&word_id) = self.ids.get(word) {
+            return word_id;
+        }
+
+        let word_id: TerminalWordIdx = TerminalWordIdx::from(self.words.len());
+        self.words.push(word.into());
+        self.ids.insert(word.into(), word_id);
+        word_id
+    }
+
+    /// same as intern, but doesn't create new ids
+    pub fn get_id(&self, word: &[Symbol]) -> Option<TerminalWordIdx> {
+        self.ids.get(&word[..word.len().min(self.k)]).copied()
+    }
+
+    pub fn get_word(&self, word_id: TerminalWordIdx) -> &[Symbol] {
+        &self.words[usize::from(word_id)]
+    }
+
+    pub fn is_complete(&self, word_id: TerminalWordIdx) -> bool {
+        // true iff the word has the maximum length k, meaning that concatenating it doesn't change it
+        self.get_word(word_id).len() == self.k
+    }
+
+    /// returns the id of the first k symbols of word1.word2
+    pub fn concatenate(&mut self, word1: TerminalWordIdx, word2: TerminalWordIdx) -> TerminalWordIdx {
+        if self.is_complete(word1) || word2 == self.empty_word() {
+            return word1;
+        }
+        if word1 == self.empty_word() {
+            return word2;
+        }
+        if let Some(&word_id) = self.concatenations.get(&(word1, word2)) {
+            return word_id;
+        }
+
+        let mut word: Vec<Symbol> = Vec::with_capacity(self.k);
+        word.extend_from_slice(self.get_word(word1));
+        word.extend_from_slice(self.get_word(word2));
+        let word_id: TerminalWordIdx = self.intern(&word);
+
+        self.concatenations.insert((word1, word2), word_id);
+        word_id
+    }
+
+    /// returns { first k symbols of w1.w2 | w1 in word_set1, w2 in word_set2 }
+    pub fn concatenate_sets(&mut self, word_set1: &TerminalWordSet, word_set2: &TerminalWordSet) -> TerminalWordSet {
+        let mut word_set: TerminalWordSet = TerminalWordSet::new();
+        for &word1 in word_set1 {
+            if self.is_complete(word1) {
+                word_set.insert(word1);
+                continue;
+            }
+            for &word2 in word_set2 {
+                word_set.insert(self.concatenate(word1, word2));
+            }
+        }
+        word_set
+    }
+
+    pub fn repr_word(&self, cfg: &Cfg, word_id: TerminalWordIdx) -> String {
+        let word: &[Symbol] = self.get_word(word_id);
+        if word.is_empty() {
+            return String::from("EPS");
+        }
+        word.iter()
+            .map(|&symbol| cfg.repr_symbol(symbol))
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    pub fn repr_word_set(&self, cfg: &Cfg, word_set: &TerminalWordSet) -> String {
+        let words: Vec<String> = word_set.iter().map(|&word_id| self.repr_word(cfg, word_id)).collect();
+        format!("{{{}}}", words.join(", "))
+    }
+}
+
+
+// --------------------------------------------
+
+/// FIRST_k, FOLLOW_k and PREDICT_k sets of a grammar, for a given k
+/// the words that are shorter than k in a FOLLOW_k or PREDICT_k set can only be followed by the end of the input
+/// just like END is in the FOLLOW set of START, END^k is in the FOLLOW_k set of START
+pub struct KSets {
+    // shared with the users of KSets that need to intern new words,
+    // ids are never invalidated so the sets below stay valid
+    interner: RefCell<TerminalWordInterner>,
+
+    // indexed by symbols
+    first_k_sets: Vec<TerminalWordSet>,
+
+    // indexed by non-terminal symbols
+    follow_k_sets: Vec<TerminalWordSet>,
+
+    // indexed by rules
+    predict_k_sets: Vec<TerminalWordSet>,
+}
+
+impl KSets {
+
+    pub fn k(&self) -> usize {
+        self.interner.borrow().k()
+    }
+
+    /// copy of the interned words, the interner itself stays borrowed only inside the methods of KSets
+    pub fn interner(&self) -> TerminalWordInterner {
+        self.interner.borrow().clone()
+    }
+
+    pub fn get_word(&self, word_id: TerminalWordIdx) -> Vec<Symbol> {
+        self.interner.borrow().get_word(word_id).to_vec()
+    }
+
+    pub fn first_k_set(&self, symbol: Symbol) -> &TerminalWordSet {
+        &self.first_k_sets[usize::from(symbol.id)]
+    }
+
+    pub fn follow_k_set(&self, non_terminal: Symbol) -> &TerminalWordSet {
+        &self.follow_k_sets[usize::from(non_terminal.id)]
+    }
+
+    pub fn predict_k_set(&self, rule_id: CfgRuleIdx) -> &TerminalWordSet {
+        &self.predict_k_sets[usize::from(rule_id)]
+    }
+
+    pub fn intern(&self, word: &[Symbol]) -> TerminalWordIdx {
+        self.interner.borrow_mut().intern(word)
+    }
+
+    pub fn concatenate_sets(&self, word_set1: &TerminalWordSet, word_set2: &TerminalWordSet) -> TerminalWordSet {
+        self.interner.borrow_mut().concatenate_sets(word_set1, word_set2)
+    }
+
+    /// FIRST_k set of any word of symbols
+    pub fn first_k_set_of_word(&self, word: &[Symbol]) -> TerminalWordSet {
+        first_k_set_of_word(&mut self.interner.borrow_mut(), &self.first_k_sets, word)
+    }
+
+    pub fn repr_word_set(&self, cfg: &Cfg, word_set: &TerminalWordSet) -> String {
+        self.interner.borrow().repr_word_set(cfg, word_set)
+    }
+
+    /// one line per non-terminal symbol: `Symbol: {first_k set}`
+    pub fn repr_first_k_sets(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for non_terminal in cfg.all_non_terminals() {
+            s.push_str(&format!("{}: {}\n",
+                cfg.repr_symbol(non_terminal),
+                self.repr_word_set(cfg, self.first_k_set(non_terminal))));
+        }
+        s
+    }
+
+    /// one line per non-terminal symbol: `Symbol: {follow_k set}`
+    pub fn repr_follow_k_sets(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for non_terminal in cfg.all_non_terminals() {
+            s.push_str(&format!("{}: {}\n",
+                cfg.repr_symbol(non_terminal),
+                self.repr_word_set(cfg, self.follow_k_set(non_terminal))));
+        }
+        s
+    }
+
+    /// one line per rule: `rule: {predict_k set}`
+    pub fn repr_predict_k_sets(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for (rule_id, _) in cfg.all_rules() {
+            s.push_str(&format!("{}: {}\n",
+                cfg.repr_rule(rule_id),
+                self.repr_word_set(cfg, self.predict_k_set(rule_id))));
+        }
+        s
+    }
+
+    fn compute(cfg: &Cfg, k: usize) -> KSets {
+        let mut interner: TerminalWordInterner = TerminalWordInterner::new(k);
+
+        let first_k_sets: Vec<TerminalWordSet> = Self::compute_first_k_sets(cfg, &mut interner);
+        let follow_k_sets: Vec<TerminalWordSet> = Self::compute_follow_k_sets(cfg, &mut interner, &first_k_sets);
+
+        let mut predict_k_sets: Vec<TerminalWordSet> = Vec::with_capacity(usize::from(cfg.nbr_rules()));
+        for (_, rule) in cfg.all_rules() {
+            let first_k_set: TerminalWordSet = first_k_set_of_word(&mut interner, &first_k_sets, &rule.replacement);
+            predict_k_sets.push(
+                interner.concatenate_sets(&first_k_set, &follow_k_sets[usize::from(rule.origin.id)]));
+        }
+
+        KSets {
+            interner: RefCell::new(interner),
+            first_k_sets,
+            follow_k_sets,
+            predict_k_sets,
+        }
+    }
+
+    fn compute_first_k_sets(cfg: &Cfg, interner: &mut TerminalWordInterner) -> Vec<TerminalWordSet> {
+
+        let mut first_k_sets: Vec<TerminalWordSet> = vec![TerminalWordSet::new(); usize::from(cfg.nbr_symbols())];
+        for terminal in cfg.all_terminals() {
+            first_k_sets[usize::from(terminal.id)].insert(interner.intern(&[terminal]));
+        }
+
+        // fixed-point iteration: the sets only grow and are bounded
+        let mut changed: bool = true;
+        while changed {
+            changed = false;
+
+            for (_, rule) in cfg.all_rules() {
+                let first_k_set: TerminalWordSet = first_k_set_of_word(interner, &first_k_sets, &rule.replacement);
+
+                let origin_first_k_set: &mut TerminalWordSet = &mut first_k_sets[usize::from(rule.origin.id)];
+                for word_id in first_k_set {
+                    changed |= origin_first_k_set.insert(word_id);
+                }
+            }
+        }
+
+        first_k_sets
+    }
+
+    fn compute_follow_k_sets(cfg: &Cfg, interner: &mut TerminalWordInterner, first_k_sets: &[TerminalWordSet])
+    -> Vec<TerminalWordSet> {
+
+        let mut follow_k_sets: Vec<TerminalWordSet> = vec![TerminalWordSet::new(); usize::from(cfg.nbr_non_terminals())];
+        let end_word: Vec<Symbol> = vec![cfg.END(); interner.k()];
+        follow_k_sets[usize::from(cfg.START().id)].insert(interner.intern(&end_word));
+
+        // the FIRST_k sets of the suffixes of each rule don't change during the iteration
+        // suffix_first_k_sets[rule_id][i] = FIRST_k(replacement[i+1..])
+        let mut suffix_first_k_sets: Vec<Vec<TerminalWordSet>> = Vec::with_capacity(usize::from(cfg.nbr_rules()));
+        for (_, rule) in cfg.all_rules() {
+            suffix_first_k_sets.push(
+                (0..rule.replacement_size())
+                    .map(|i| first_k_set_of_word(interner, first_k_sets, &rule.replacement[i+1..]))
+                    .collect()
+            );
+        }
+
+        let mut changed: bool = true;
+        while changed {
+            changed = false;
+
+            for (rule_id, rule) in cfg.all_rules() {
+                for (position, &symbol) in rule.replacement.iter().enumerate() {
+                    if cfg.is_terminal(symbol) {
+                        continue;
+                    }
+
+                    let follow_k_set: TerminalWordSet = interner.concatenate_sets(
+                        &suffix_first_k_sets[usize::from(rule_id)][position],
+                        &follow_k_sets[usize::from(rule.origin.id)]);
+
+                    let symbol_follow_k_set: &mut TerminalWordSet = &mut follow_k_sets[usize::from(symbol.id)];
+                    for word_id in follow_k_set {
+                        changed |= symbol_follow_k_set.insert(word_id);
+                    }
+                }
+            }
+        }
+
+        follow_k_sets
+    }
+}
+
+
+fn first_k_set_of_word(interner: &mut TerminalWordInterner, first_k_sets: &[TerminalWordSet], word: &[Symbol])
+-> TerminalWordSet {
+
+    let mut first_k_set: TerminalWordSet = TerminalWordSet::from([interner.empty_word()]);
+
+    for &symbol in word {
+        first_k_set = interner.concatenate_sets(&first_k_set, &first_k_sets[usize::from(symbol.id)]);
+
+        if first_k_set.iter().all(|&word_id| interner.is_complete(word_id)) {
+            // the next symbols can't change the set anymore
+            break;
+        }
+    }
+
+    first_k_set
+}
+
+
+// --------------------------------------------
+
+impl Cfg {
+
+    /// returns the FIRST_k, FOLLOW_k and PREDICT_k sets of the grammar, computed once for each k
+    pub fn get_k_sets(&self, k: usize) -> Rc<KSets> {
+        if let Some(k_sets) = self.k_sets.borrow().get(&k) {
+            return Rc::clone(k_sets);
+        }
+
+        let k_sets: Rc<KSets> = Rc::new(KSets::compute(self, k));
+        self.k_sets.borrow_mut().insert(k, Rc::clone(&k_sets));
+        k_sets
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::grammars::regex_grammar::create_regex_grammar;
+    use super::*;
+
+    #[test]
+    fn k_sets_match_first_follow_predict_for_k_1() {
+        let cfg = create_regex_grammar();
+        let k_sets = cfg.get_k_sets(1);
+
+        for non_terminal in cfg.all_non_terminals() {
+            let first_set = cfg.get_first_set(non_terminal);
+            let first_k_set = k_sets.first_k_set(non_terminal);
+            assert_eq!(first_set.len(), first_k_set.len());
+            for &word_id in first_k_set {
+                match k_sets.get_word(word_id)[..] {
+                    [] => assert!(first_set.contains(0)),
+                    [terminal] => assert!(first_set.contains(usize::from(cfg.to_local_nosymbol(terminal)))),
+                    _ => panic!("words should be of length at most 1"),
+                }
+            }
+        }
+
+        for (rule_id, _) in cfg.all_rules() {
+            assert_eq!(cfg.get_predict_set(rule_id).len(), k_sets.predict_k_set(rule_id).len());
+        }
+    }
+
+    #[test]
+    fn k_sets_k_2() {
+        let cfg = create_regex_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let k_sets = cfg.get_k_sets(2);
+
+        println!("{}", k_sets.repr_first_k_sets(&cfg));
+        println!("{}", k_sets.repr_follow_k_sets(&cfg));
+
+        assert_eq!(k_sets.repr_word_set(&cfg, k_sets.first_k_set(S("Interval"))), "{{ int, { ,}");
+        assert_eq!(k_sets.repr_word_set(&cfg, k_sets.first_k_set(S("Integer__Optional"))), "{EPS, int}");
+        assert_eq!(k_sets.repr_word_set(&cfg, k_sets.follow_k_set(S("START"))), "{END END}");
+        assert!(k_sets.follow_k_set(S("Expression")).contains(&k_sets.intern(&[S(")"), S("END")])));
+
+        // the cache gives back the same sets
+        assert!(Rc::ptr_eq(&k_sets, &cfg.get_k_sets(2)));
+
+        let first_k_set = k_sets.first_k_set_of_word(&[S("Hat__Optional"), S("Hat__Optional")]);
+        assert_eq!(k_sets.repr_word_set(&cfg, &first_k_set), "{EPS, ^, ^ ^}");
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use super::{Cfg, CfgRuleIdx, Symbol};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    // %left: a op b op c = (a op b) op c, reductions win over shifts
+    Left,
+    // %right: a op b op c = a op (b op c), shifts win over reductions
+    Right,
+    // %nonassoc: a op b op c is a syntax error
+    NonAssoc,
+}
+
+/// precedence of a terminal or of a rule, a higher level binds tighter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Precedence {
+    pub level: u16,
+    pub associativity: Associativity,
+}
+
+
+/// yacc-like precedence declarations, used by the LR generators to resolve shift/reduce conflicts
+/// each call to left, right or nonassoc declares a new level which binds tighter than the previous ones,
+/// just like each %left, %right or %nonassoc line in yacc
+#[derive(Debug, Clone, Default)]
+pub struct PrecedenceTable {
+    // indexed by terminal symbol
+    terminal_precedences: HashMap<Symbol, Precedence>,
+    // %prec: the terminal whose precedence is used by the rule
+    rule_precedence_terminals: HashMap<CfgRuleIdx, Symbol>,
+    nbr_levels: u16,
+}
+
+impl PrecedenceTable {
+
+    pub fn new() -> Self {
+        PrecedenceTable {
+            terminal_precedences: HashMap::new(),
+            rule_precedence_terminals: HashMap::new(),
+            nbr_levels: 0,
+        }
+    }
+
+    /// declares a new level with its terminals
+    pub fn add_level(&mut self, associativity: Associativity, terminals: &[Symbol]) -> &mut Self {
+        self.nbr_levels += 1;
+        let precedence: Precedence = Precedence { level: self.nbr_levels, associativity };
+        for &terminal in terminals {
+            self.terminal_precedences.insert(terminal, precedence);
+        }
+        self
+    }
+
+    /// %left terminals
+    pub fn left(&mut self, terminals: &[Symbol]) -> &mut Self {
+        self.add_level(Associativity::Left, terminals)
+    }
+
+    /// %right terminals
+    pub fn right(&mut self, terminals: &[Symbol]) -> &mut Self {
+        self.add_level(Associativity::Right, terminals)
+    }
+
+    /// %nonassoc terminals
+    pub fn nonassoc(&mut self, terminals: &[Symbol]) -> &mut Self {
+        self.add_level(Associativity::NonAssoc, terminals)
+    }
+
+    /// %prec terminal: the rule gets the precedence of the terminal, which doesn't need to appear in the grammar
+    pub fn set_rule_precedence(&mut self, rule_id: CfgRuleIdx, terminal: Symbol) -> &mut Self {
+        self.rule_precedence_terminals.insert(rule_id, terminal);
+        self
+    }
+
+    pub fn nbr_levels(&self) -> u16 {
+        self.nbr_levels
+    }
+
+    pub fn get_terminal_precedence(&self, terminal: Symbol) -> Option<Precedence> {
+        self.terminal_precedences.get(&terminal).copied()
+    }
+
+    /// the precedence given by %prec, or else the one of the last terminal of the replacement like in yacc: the
+    /// rule has no precedence if this terminal has none, even when an earlier terminal has one
+    pub fn get_rule_precedence(&self, cfg: &Cfg, rule_id: CfgRuleIdx) -> Option<Precedence> {
+        if let Some(&terminal) = self.rule_precedence_terminals.get(&rule_id) {
+            return self.get_terminal_precedence(terminal);
+        }
+        cfg.get_rule_by_id(rule_id)
+            .replacement
+            .iter()
+            .rev()
+            .find(|&&symbol| cfg.is_terminal(symbol))
+            .and_then(|&terminal| self.get_terminal_precedence(terminal))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::create_grammar;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+
+    #[test]
+    fn rule_precedence_of_the_last_terminal() {
+        // x is the last terminal of E -> E + x E and has no precedence, so the rule has none either
+        let mut cfg = create_grammar(
+            &["+", "x", "id"],
+            &["E"],
+            &[
+                ("START", &["E", "END"]),
+                ("E", &["E", "+", "x", "E"]),
+                ("E", &["id"]),
+            ],
+        );
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let mut precedence_table: PrecedenceTable = PrecedenceTable::new();
+        precedence_table.left(&[S("+")]);
+        // unwrap is safe because the grammar has this rule
+        let (rule_id, _) = cfg.get_rules_by_origin(S("E")).find(|(_, rule)| rule.replacement.len() == 4).unwrap();
+        assert_eq!(precedence_table.get_rule_precedence(&cfg, rule_id), None);
+
+        // %prec still gives it a precedence
+        let mut prec_table: PrecedenceTable = precedence_table.clone();
+        prec_table.set_rule_precedence(rule_id, S("+"));
+        assert_eq!(prec_table.get_rule_precedence(&cfg, rule_id).map(|precedence| precedence.level), Some(1));
+
+        // E + x E . + is reported like in yacc instead of being resolved by the precedence of +
+        cfg.set_precedence_table(precedence_table);
+        let table = generate_LALR1_table(&cfg);
+        println!("{}", table.repr_conflicts(&cfg));
+        assert!(table.has_conflicts());
+        assert!(table.resolved_conflicts().is_empty());
+    }
+}
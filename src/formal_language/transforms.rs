@@ -0,0 +1,330 @@
+pub mod CNF;
+pub mod epsilon_rules;
+pub mod left_factoring;
+pub mod left_recursion;
+pub mod unit_rules;
+
+use std::collections::HashSet;
+
+use super::*;
+use crate::parsing::parse_tree::*;
+
+
+/// symbol of a grammar being built from an original one: either a symbol of the original grammar or a
+/// non-terminal added during the construction
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum BuilderSymbol {
+    Original(Symbol),
+    // index in the added non-terminals
+    Fresh(usize),
+}
+
+
+/// builds a grammar from an original one, keeping its symbols and adding non-terminals after them
+/// each rule carries a provenance, which is given back in the order of the rules of the new grammar
+pub struct CfgBuilder<'a, P> {
+    original: &'a Cfg,
+    fresh_non_terminals: Vec<String>,
+    representations: HashSet<String>,
+    rules: Vec<(BuilderSymbol, Vec<BuilderSymbol>, P)>,
+}
+
+impl <'a, P> CfgBuilder<'a, P> {
+
+    pub fn new(original: &'a Cfg) -> Self {
+        CfgBuilder {
+            original,
+            fresh_non_terminals: Vec::new(),
+            representations: original.all_symbols().map(|symbol| String::from(original.repr_symbol(symbol))).collect(),
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn original(&self) -> &'a Cfg {
+        self.original
+    }
+
+    pub fn is_non_terminal(&self, symbol: BuilderSymbol) -> bool {
+        match symbol {
+            BuilderSymbol::Original(symbol) => self.original.is_non_terminal(symbol),
+            BuilderSymbol::Fresh(_) => true,
+        }
+    }
+
+    pub fn repr_symbol(&self, symbol: BuilderSymbol) -> &str {
+        match symbol {
+            BuilderSymbol::Original(symbol) => self.original.repr_symbol(symbol),
+            BuilderSymbol::Fresh(fresh_id) => &self.fresh_non_terminals[fresh_id],
+        }
+    }
+
+    /// adds a non-terminal with the representation, followed by an underscore and the smallest number from 2 that
+    /// makes it unique if it is already taken, so a numbered representation like A__Tail1 never becomes A__Tail12
+    pub fn fresh_non_terminal(&mut self, representation: &str) -> BuilderSymbol {
+        let mut unique_representation: String = String::from(representation);
+        let mut number: usize = 2;
+        while self.representations.contains(&unique_representation) {
+            unique_representation = format!("{representation}_{number}");
+            number += 1;
+        }
+        self.representations.insert(unique_representation.clone());
+        self.fresh_non_terminals.push(unique_representation);
+        BuilderSymbol::Fresh(self.fresh_non_terminals.len()-1)
+    }
+
+    pub fn add_rule(&mut self, origin: BuilderSymbol, replacement: Vec<BuilderSymbol>, provenance: P) {
+        self.rules.push((origin, replacement, provenance));
+    }
+
+    /// the new grammar, with the provenance of each of its rules
+    pub fn build(mut self) -> (Cfg, Vec<P>) {
+        let nbr_original_non_terminals: SymbolIdx = self.original.nbr_non_terminals();
+        let nbr_fresh_non_terminals: SymbolIdx = SymbolIdx::from(self.fresh_non_terminals.len());
+        let to_symbol = |symbol: BuilderSymbol| match symbol {
+            BuilderSymbol::Original(symbol) if self.original.is_terminal(symbol) =>
+                Symbol { id: symbol.id + nbr_fresh_non_terminals },
+            BuilderSymbol::Original(symbol) => symbol,
+            BuilderSymbol::Fresh(fresh_id) => Symbol { id: nbr_original_non_terminals + SymbolIdx::from(fresh_id) },
+        };
+
+        // same order as the one given by Cfg::new, so the provenances stay aligned with the rules
+        self.rules.sort_by_key(|(origin, _, _)| to_symbol(*origin).id);
+        let (rules, provenances): (Vec<CfgRule>, Vec<P>) = self.rules
+            .into_iter()
+            .map(|(origin, replacement, provenance)| (
+                CfgRule { origin: to_symbol(origin), replacement: replacement.into_iter().map(to_symbol).collect() },
+                provenance,
+            ))
+            .unzip();
+
+        let original_symbol_set: &CfgSymbolSet = self.original.symbol_set();
+        let representations = |symbol_set: &SymbolSet| -> Vec<String> {
+            (0..symbol_set.size().0).map(|local_id| symbol_set.get_representation(SymbolIdx(local_id)).clone()).collect()
+        };
+        let terminals: SymbolSet = SymbolSet::new(representations(original_symbol_set.get_terminals()));
+        let non_terminals: SymbolSet = SymbolSet::new(
+            [representations(original_symbol_set.get_non_terminals()), self.fresh_non_terminals].concat());
+
+        // unwrap is safe because every symbol comes from the original grammar or was added to it
+        (Cfg::new(CfgSymbolSet::new(terminals, non_terminals), rules).unwrap(), provenances)
+    }
+}
+
+
+/// the symbol of a grammar built by a CfgBuilder corresponding to a symbol of the original grammar
+/// the non-terminals keep their id, the terminals are shifted after the added non-terminals
+pub fn translate_symbol(original: &Cfg, built: &Cfg, symbol: Symbol) -> Symbol {
+    if original.is_non_terminal(symbol) {
+        symbol
+    }
+    else {
+        Symbol { id: symbol.id - original.nbr_non_terminals() + built.nbr_non_terminals() }
+    }
+}
+
+/// the symbol of the original grammar corresponding to a symbol of a grammar built by a CfgBuilder,
+/// None for the added non-terminals
+pub fn original_symbol(original: &Cfg, built: &Cfg, symbol: Symbol) -> Option<Symbol> {
+    if built.is_terminal(symbol) {
+        Some(Symbol { id: symbol.id - built.nbr_non_terminals() + original.nbr_non_terminals() })
+    }
+    else {
+        (symbol.id < original.nbr_non_terminals()).then_some(symbol)
+    }
+}
+
+// --------------------------------------------
+
+/// how a rule of a transformed grammar derives with the rules of the original grammar: the tree of original
+/// rules it stands for, whose leaves are the symbols of its replacement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivationFragment {
+    /// the next symbol of the replacement
+    Symbol,
+    /// nullable non-terminal erased from the replacement, it derives the empty word
+    Erased(Symbol),
+    /// rule of the original grammar, with the fragments of the symbols of its replacement
+    Rule{rule_id: CfgRuleIdx, children: Vec<DerivationFragment>},
+    /// the non-terminal derived by the base, then extended by the left-recursive rules given by the next symbol
+    /// of the replacement, which is a helper added by the elimination of the left recursion
+    Extended{base: Box<DerivationFragment>},
+}
+
+impl DerivationFragment {
+
+    /// an original rule whose replacement is kept
+    pub fn rule(original: &Cfg, rule_id: CfgRuleIdx) -> Self {
+        let size: usize = original.get_rule_by_id(rule_id).replacement_size();
+        DerivationFragment::Rule { rule_id, children: vec![DerivationFragment::Symbol; size] }
+    }
+
+    /// the original rules, from the root of the fragment, in pre-order
+    pub fn original_rules(&self) -> Vec<CfgRuleIdx> {
+        let mut rules: Vec<CfgRuleIdx> = Vec::new();
+        let mut to_visit: Vec<&DerivationFragment> = vec![self];
+        while let Some(fragment) = to_visit.pop() {
+            match fragment {
+                DerivationFragment::Symbol | DerivationFragment::Erased(_) => {}
+                DerivationFragment::Rule { rule_id, children } => {
+                    rules.push(*rule_id);
+                    to_visit.extend(children.iter().rev());
+                }
+                DerivationFragment::Extended { base } => to_visit.push(base),
+            }
+        }
+        rules
+    }
+
+    /// replaces the first symbol by the fragment deriving it, returns false if there is no symbol
+    pub fn substitute_first(&mut self, fragment: DerivationFragment) -> bool {
+        self.substitute_first_in(&mut Some(fragment))
+    }
+
+    fn substitute_first_in(&mut self, fragment: &mut Option<DerivationFragment>) -> bool {
+        match self {
+            DerivationFragment::Symbol => {
+                // unwrap is safe because the fragment is only taken once, before returning true
+                *self = fragment.take().unwrap();
+                true
+            }
+            DerivationFragment::Erased(_) => false,
+            DerivationFragment::Rule { children, .. } =>
+                children.iter_mut().any(|child| child.substitute_first_in(fragment)),
+            DerivationFragment::Extended { base } => base.substitute_first_in(fragment),
+        }
+    }
+}
+
+
+/// for each nullable non-terminal, a rule whose replacement only contains non-terminals of smaller
+/// derivation height, so following them always ends
+pub fn compute_epsilon_rules(cfg: &Cfg) -> Vec<Option<CfgRuleIdx>> {
+    let mut epsilon_rules: Vec<Option<CfgRuleIdx>> = vec![None; usize::from(cfg.nbr_non_terminals())];
+    loop {
+        let found: Vec<(Symbol, CfgRuleIdx)> = cfg
+            .all_rules()
+            .filter(|(_, rule)| {
+                epsilon_rules[usize::from(rule.origin.id)].is_none() && rule.replacement.iter().all(|&symbol|
+                    cfg.is_non_terminal(symbol) && epsilon_rules[usize::from(symbol.id)].is_some())
+            })
+            .map(|(rule_id, rule)| (rule.origin, rule_id))
+            .collect();
+        if found.is_empty() {
+            return epsilon_rules;
+        }
+        for (origin, rule_id) in found {
+            epsilon_rules[usize::from(origin.id)].get_or_insert(rule_id);
+        }
+    }
+}
+
+
+// --------------------------------------------
+
+// rules of each non-terminal of the original grammar while they are transformed, with the fragment each derives like
+type WorkingRules = Vec<Vec<(Vec<BuilderSymbol>, DerivationFragment)>>;
+
+/// the rules of the original grammar, each deriving like itself
+fn original_working_rules(original: &Cfg) -> WorkingRules {
+    let mut rules: WorkingRules = vec![Vec::new(); usize::from(original.nbr_non_terminals())];
+    for (rule_id, rule) in original.all_rules() {
+        let replacement: Vec<BuilderSymbol> = rule.replacement.iter().map(|&symbol| BuilderSymbol::Original(symbol)).collect();
+        rules[usize::from(rule.origin.id)].push((replacement, DerivationFragment::rule(original, rule_id)));
+    }
+    rules
+}
+
+fn add_working_rules<P>(builder: &mut CfgBuilder<'_, P>, rules: WorkingRules, provenance: impl Fn(DerivationFragment) -> P) {
+    for (index, non_terminal_rules) in rules.into_iter().enumerate() {
+        let origin: BuilderSymbol = BuilderSymbol::Original(Symbol { id: SymbolIdx::from(index) });
+        for (replacement, fragment) in non_terminal_rules {
+            builder.add_rule(origin, replacement, provenance(fragment));
+        }
+    }
+}
+
+/// rewrites the parse trees of a grammar built by a CfgBuilder into parse trees of the original grammar
+struct ParseTreeRewriter<'t> {
+    original: &'t Cfg,
+    built: &'t Cfg,
+    parse_tree: &'t ParseTree,
+    // indexed by the non-terminals of the original grammar, see compute_epsilon_rules
+    epsilon_rules: &'t [Option<CfgRuleIdx>],
+    rewritten: ParseTree,
+}
+
+impl <'t> ParseTreeRewriter<'t> {
+
+    fn new(original: &'t Cfg, built: &'t Cfg, parse_tree: &'t ParseTree, epsilon_rules: &'t [Option<CfgRuleIdx>]) -> Self {
+        ParseTreeRewriter { original, built, parse_tree, epsilon_rules, rewritten: ParseTree::new() }
+    }
+
+    /// the parse tree, when the rules of the built grammar all derive like the fragments
+    fn rewrite_with_fragments(mut self, fragments: &[DerivationFragment]) -> ParseTree {
+        if let Some(root_id) = self.parse_tree.root() {
+            let root_id: usize = self.rewrite_fragment_node(fragments, root_id);
+            self.rewritten.set_root(root_id);
+        }
+        self.rewritten
+    }
+
+    fn rewrite_fragment_node(&mut self, fragments: &[DerivationFragment], node_id: usize) -> usize {
+        let Some(rule_id) = self.parse_tree.rule_id(node_id) else {
+            return self.rewrite_leaf(node_id);
+        };
+        let mut children = self.parse_tree.children(node_id).iter().copied();
+        self.build_fragment(&fragments[usize::from(rule_id)], &mut |rewriter: &mut Self| {
+            // unwrap is safe because there is one child for each symbol of the replacement
+            let child_id: usize = children.next().unwrap();
+            rewriter.rewrite_fragment_node(fragments, child_id)
+        })
+    }
+
+    /// the nodes of the original rules of the fragment, its symbols are given in order by next_symbol
+    fn build_fragment(&mut self, fragment: &DerivationFragment, next_symbol: &mut dyn FnMut(&mut Self) -> usize) -> usize {
+        match fragment {
+            DerivationFragment::Symbol => next_symbol(self),
+            DerivationFragment::Erased(non_terminal) => self.build_epsilon_node(*non_terminal),
+            DerivationFragment::Rule { rule_id, children } => {
+                let nodes: Vec<usize> = children.iter().map(|child| self.build_fragment(child, next_symbol)).collect();
+                let origin: Symbol = self.original.get_rule_by_id(*rule_id).origin;
+                self.rewritten.add_parent_node(ParseTreeNodeKind::Inner { rule_id: *rule_id, origin }, nodes)
+            }
+            DerivationFragment::Extended { .. } => unreachable!("only the elimination of the left recursion extends non-terminals"),
+        }
+    }
+
+    /// the token gets back the terminal of the original grammar
+    fn rewrite_leaf(&mut self, node_id: usize) -> usize {
+        // unwrap is safe because the node is a leaf
+        let mut token: Token = self.parse_tree.token(node_id).unwrap().clone();
+        // unwrap is safe because the terminals all come from the original grammar
+        token.token_type = original_symbol(self.original, self.built, token.token_type).unwrap();
+        self.rewritten.add_node(ParseTreeNodeKind::Leaf { token }, None)
+    }
+
+    fn build_epsilon_node(&mut self, non_terminal: Symbol) -> usize {
+        // unwrap is safe because only nullable symbols are erased
+        let rule_id: CfgRuleIdx = self.epsilon_rules[usize::from(non_terminal.id)].unwrap();
+        let rule: &CfgRule = self.original.get_rule_by_id(rule_id);
+        let children: Vec<usize> = rule.replacement
+            .iter()
+            .map(|&symbol| self.build_epsilon_node(symbol))
+            .collect();
+        self.rewritten.add_parent_node(ParseTreeNodeKind::Inner { rule_id, origin: rule.origin }, children)
+    }
+}
+
+/// one line per rule of the built grammar, followed by the original rules it comes from if there are some
+fn repr_with_original_rules(built: &Cfg, original: &Cfg, original_rules: impl Fn(CfgRuleIdx) -> Vec<CfgRuleIdx>) -> String {
+    let mut s: String = String::new();
+    for (rule_id, _) in built.all_rules() {
+        s.push_str(&built.repr_rule(rule_id));
+        let original_rules: Vec<String> = original_rules(rule_id).into_iter().map(|rule_id| original.repr_rule(rule_id)).collect();
+        if !original_rules.is_empty() {
+            s.push_str(&format!("    ({})", original_rules.join(", ")));
+        }
+        s.push('\n');
+    }
+    s
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use super::epsilon_rules::remove_epsilon_rules;
+use super::unit_rules::remove_unit_rules;
+use super::*;
+
+
+/// where a rule of the grammar in Chomsky normal form comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CnfRuleProvenance {
+    /// the unit rules applied one after the other, then the rule giving the replacement, with its erased symbols
+    /// when the replacement is binarized, the rules of the helper non-terminals complete it
+    Derived(DerivationFragment),
+    /// rule of a non-terminal added by the conversion, its node is replaced by its children in the original tree
+    Helper,
+}
+
+
+/// grammar in Chomsky normal form: every rule is A -> B C or A -> a, with START -> ε if START is nullable,
+/// and START never appears in a replacement
+/// it is obtained by removing the ε-rules and the unit rules, isolating START, then binarizing the replacements
+pub struct ChomskyNormalForm {
+    cfg: Cfg,
+
+    // indexed by the rules of cfg
+    provenances: Vec<CnfRuleProvenance>,
+
+    // indexed by the non-terminals of the original grammar, first rule of the derivation chosen for the empty
+    // word, None if the non-terminal isn't nullable
+    epsilon_rules: Vec<Option<CfgRuleIdx>>,
+}
+
+impl ChomskyNormalForm {
+
+    pub fn new(original: &Cfg) -> Self {
+        let mut builder: CfgBuilder<'_, CnfRuleProvenance> = CfgBuilder::new(original);
+        let O = BuilderSymbol::Original;
+        let start: BuilderSymbol = O(original.START());
+        let start_index: usize = usize::from(original.START().id);
+
+        // the empty rule of START is set aside during the unit removal, so the non-terminals reaching START by unit
+        // rules don't get it
+        let mut rules: WorkingRules = remove_epsilon_rules(original, original_working_rules(original));
+        let (empty_start_rules, start_rules): (Vec<(Vec<BuilderSymbol>, DerivationFragment)>, Vec<_>) = std::mem::take(&mut rules[start_index])
+            .into_iter()
+            .partition(|(replacement, _)| replacement.is_empty());
+        rules[start_index] = start_rules;
+        let mut rules: WorkingRules = remove_unit_rules(rules);
+        rules[start_index].extend(empty_start_rules);
+
+        // START isolation: its occurrences in the replacements are replaced by a copy of it without the empty rule
+        let is_start_produced: bool = rules.iter().flatten().any(|(replacement, _)| replacement.contains(&start));
+        let inner_start: BuilderSymbol = if is_start_produced {builder.fresh_non_terminal("START__Inner")} else {start};
+        let rename = |symbol: BuilderSymbol| if symbol == start {inner_start} else {symbol};
+
+        let mut unit_free_rules: Vec<(BuilderSymbol, Vec<BuilderSymbol>, CnfRuleProvenance)> = Vec::new();
+        for (index, non_terminal_rules) in rules.into_iter().enumerate() {
+            let origin: BuilderSymbol = O(Symbol { id: SymbolIdx::from(index) });
+            for (replacement, fragment) in non_terminal_rules {
+                let replacement: Vec<BuilderSymbol> = replacement.into_iter().map(rename).collect();
+                if is_start_produced && origin == start && !replacement.is_empty() {
+                    unit_free_rules.push((inner_start, replacement.clone(), CnfRuleProvenance::Derived(fragment.clone())));
+                }
+                unit_free_rules.push((origin, replacement, CnfRuleProvenance::Derived(fragment)));
+            }
+        }
+
+        // binarization: the terminals of long replacements are replaced by helper non-terminals, and the
+        // replacements are split in a chain of helper non-terminals
+        let mut terminal_helpers: HashMap<Symbol, BuilderSymbol> = HashMap::new();
+        let mut nbr_binarization_helpers: HashMap<BuilderSymbol, usize> = HashMap::new();
+        for (origin, replacement, provenance) in unit_free_rules {
+            if replacement.len() < 2 {
+                builder.add_rule(origin, replacement, provenance);
+                continue;
+            }
+
+            let mut replacement_non_terminals: Vec<BuilderSymbol> = Vec::with_capacity(replacement.len());
+            for symbol in replacement {
+                match symbol {
+                    BuilderSymbol::Original(terminal) if original.is_terminal(terminal) => {
+                        let helper: BuilderSymbol = match terminal_helpers.get(&terminal) {
+                            Some(&helper) => helper,
+                            None => {
+                                let helper: BuilderSymbol = builder.fresh_non_terminal(&format!("{}__Term", original.repr_symbol(terminal)));
+                                builder.add_rule(helper, vec![symbol], CnfRuleProvenance::Helper);
+                                terminal_helpers.insert(terminal, helper);
+                                helper
+                            }
+                        };
+                        replacement_non_terminals.push(helper);
+                    }
+                    _ => replacement_non_terminals.push(symbol),
+                }
+            }
+
+            let mut current_origin: BuilderSymbol = origin;
+            let mut provenance: CnfRuleProvenance = provenance;
+            let size: usize = replacement_non_terminals.len();
+            for &symbol in &replacement_non_terminals[..size-2] {
+                let nbr_helpers: &mut usize = nbr_binarization_helpers.entry(origin).or_default();
+                *nbr_helpers += 1;
+                let representation: String = format!("{}__Bin{}", builder.repr_symbol(origin), nbr_helpers);
+                let helper: BuilderSymbol = builder.fresh_non_terminal(&representation);
+                builder.add_rule(current_origin, vec![symbol, helper], std::mem::replace(&mut provenance, CnfRuleProvenance::Helper));
+                current_origin = helper;
+            }
+            builder.add_rule(current_origin, replacement_non_terminals[size-2..].to_vec(), provenance);
+        }
+
+        let (cfg, provenances): (Cfg, Vec<CnfRuleProvenance>) = builder.build();
+        ChomskyNormalForm {
+            cfg,
+            provenances,
+            epsilon_rules: compute_epsilon_rules(original),
+        }
+    }
+
+    pub fn cfg(&self) -> &Cfg {
+        &self.cfg
+    }
+
+    pub fn get_provenance(&self, rule_id: CfgRuleIdx) -> &CnfRuleProvenance {
+        &self.provenances[usize::from(rule_id)]
+    }
+
+    /// the rules of the original grammar applied by the rule, unit rules first
+    pub fn original_rules(&self, rule_id: CfgRuleIdx) -> Vec<CfgRuleIdx> {
+        match self.get_provenance(rule_id) {
+            CnfRuleProvenance::Derived(fragment) => fragment.original_rules(),
+            CnfRuleProvenance::Helper => vec![],
+        }
+    }
+
+    /// the first rule of the derivation of the empty word chosen for a nullable non-terminal of the original grammar
+    pub fn epsilon_rule(&self, non_terminal: Symbol) -> Option<CfgRuleIdx> {
+        self.epsilon_rules[usize::from(non_terminal.id)]
+    }
+
+    /// one line per rule, followed by the original rules it comes from
+    pub fn repr(&self, original: &Cfg) -> String {
+        repr_with_original_rules(&self.cfg, original, |rule_id| self.original_rules(rule_id))
+    }
+
+    // -------------------------- parse trees
+
+    /// the parse tree of the original grammar corresponding to a parse tree of the grammar in Chomsky normal form,
+    /// the nodes of the helper non-terminals are replaced by their children and the erased symbols get back the
+    /// subtrees of their ε-rules
+    pub fn rewrite_parse_tree(&self, original: &Cfg, parse_tree: &ParseTree) -> ParseTree {
+        let mut rewriter: ParseTreeRewriter<'_> = ParseTreeRewriter::new(original, &self.cfg, parse_tree, &self.epsilon_rules);
+        if let Some(root_id) = parse_tree.root() {
+            let root_id: usize = self.rewrite_node(&mut rewriter, root_id);
+            rewriter.rewritten.set_root(root_id);
+        }
+        rewriter.rewritten
+    }
+
+    fn rewrite_node(&self, rewriter: &mut ParseTreeRewriter<'_>, node_id: usize) -> usize {
+        let parse_tree: &ParseTree = rewriter.parse_tree;
+        let Some(rule_id) = parse_tree.rule_id(node_id) else {
+            return rewriter.rewrite_leaf(node_id);
+        };
+
+        let CnfRuleProvenance::Derived(fragment) = self.get_provenance(rule_id) else {
+            panic!("the helpers of the conversion are rewritten with the rule they belong to");
+        };
+        let mut children = self.derived_children(parse_tree, node_id).into_iter();
+        rewriter.build_fragment(fragment, &mut |rewriter: &mut ParseTreeRewriter<'_>| {
+            // unwrap is safe because there is one child for each symbol of the replacement before binarization
+            let child_id: usize = children.next().unwrap();
+            self.rewrite_node(rewriter, child_id)
+        })
+    }
+
+    /// the children of the node, where the nodes of the helper non-terminals are replaced by their own children
+    fn derived_children(&self, parse_tree: &ParseTree, node_id: usize) -> Vec<usize> {
+        parse_tree.children(node_id)
+            .iter()
+            .flat_map(|&child_id| match parse_tree.rule_id(child_id) {
+                Some(rule_id) if *self.get_provenance(rule_id) == CnfRuleProvenance::Helper =>
+                    self.derived_children(parse_tree, child_id),
+                _ => vec![child_id],
+            })
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar};
+
+    fn assert_is_in_CNF(cfg: &Cfg) {
+        for (rule_id, rule) in cfg.all_rules() {
+            let is_valid: bool = match rule.replacement.as_slice() {
+                [] => rule.origin == cfg.START(),
+                [symbol] => cfg.is_terminal(*symbol),
+                [left, right] => [left, right].iter().all(|&&symbol| cfg.is_non_terminal(symbol) && symbol != cfg.START()),
+                _ => false,
+            };
+            assert!(is_valid, "{} isn't in Chomsky normal form", cfg.repr_rule(rule_id));
+        }
+    }
+
+    #[test]
+    fn expression_grammar() {
+        let original = create_expression_grammar();
+        let cnf = ChomskyNormalForm::new(&original);
+        println!("{}", cnf.repr(&original));
+        assert_is_in_CNF(cnf.cfg());
+
+        let repr: String = cnf.repr(&original);
+        // E -> T -> F -> id
+        assert!(repr.contains("E --> id    (E --> T, T --> F, F --> id)"));
+        // START --> E END is split with a helper for END
+        assert!(repr.contains("START --> E END__Term    (START --> E END)"));
+        assert!(repr.contains("E__Bin1 --> +__Term T"));
+        // START is never produced, so it doesn't need a copy
+        assert!(cnf.cfg().symbol_set().get_non_terminals().get_id("START__Inner").is_none());
+    }
+
+    #[test]
+    fn nullable_symbols_and_start_isolation() {
+        let original = create_grammar(
+            &["a", "b"],
+            &["S", "A"],
+            &[
+                ("START", &["S", "END"]),
+                ("START", &["b", "START"]),
+                ("S", &["A", "A", "A"]),
+                ("A", &["a"]),
+                ("A", &[]),
+            ],
+        );
+        let cnf = ChomskyNormalForm::new(&original);
+        println!("{}", cnf.repr(&original));
+        assert_is_in_CNF(cnf.cfg());
+
+        let repr: String = cnf.repr(&original);
+        assert!(repr.contains("START --> b__Term START__Inner    (START --> b START)"));
+        assert!(repr.contains("START__Inner --> b__Term START__Inner    (START --> b START)"));
+        // S --> A A A with two A erased
+        assert!(repr.contains("S --> a    (S --> A A A, A --> a)"));
+        // S is nullable, so START --> END is possible
+        assert!(repr.contains("START --> END    (START --> S END)"));
+        assert!(!repr.contains("A -->\n"));
+
+        let S = |s: &str| original.symbol_set().get_symbol_by_representation(s);
+        let epsilon_rule: CfgRuleIdx = cnf.epsilon_rule(S("S")).unwrap();
+        assert_eq!(original.repr_rule(epsilon_rule), "S --> A A A");
+        assert_eq!(cnf.epsilon_rule(S("START")), None);
+    }
+}
@@ -0,0 +1,193 @@
+use super::*;
+
+
+/// grammar without ε-rules, except for the rule START -> ε when START is nullable
+/// each rule gives one rule for each way of erasing the nullable symbols of its replacement, but the non-terminals
+/// only deriving the empty word are always erased and the rules left empty are dropped
+pub struct EpsilonRuleElimination {
+    cfg: Cfg,
+
+    // indexed by the rules of cfg, the original rule with the symbols it erases
+    provenances: Vec<DerivationFragment>,
+
+    // indexed by the non-terminals of the original grammar, used to rebuild the erased symbols
+    epsilon_rules: Vec<Option<CfgRuleIdx>>,
+}
+
+impl EpsilonRuleElimination {
+
+    pub fn new(original: &Cfg) -> Self {
+        let mut builder: CfgBuilder<'_, DerivationFragment> = CfgBuilder::new(original);
+        let rules: WorkingRules = remove_epsilon_rules(original, original_working_rules(original));
+        add_working_rules(&mut builder, rules, |fragment| fragment);
+
+        let (cfg, provenances): (Cfg, Vec<DerivationFragment>) = builder.build();
+        EpsilonRuleElimination {
+            cfg,
+            provenances,
+            epsilon_rules: compute_epsilon_rules(original),
+        }
+    }
+
+    pub fn cfg(&self) -> &Cfg {
+        &self.cfg
+    }
+
+    pub fn get_provenance(&self, rule_id: CfgRuleIdx) -> &DerivationFragment {
+        &self.provenances[usize::from(rule_id)]
+    }
+
+    /// the original rule of the rule, the rules deriving the erased symbols aren't listed
+    pub fn original_rules(&self, rule_id: CfgRuleIdx) -> Vec<CfgRuleIdx> {
+        self.get_provenance(rule_id).original_rules()
+    }
+
+    /// one line per rule, followed by the original rule it comes from
+    pub fn repr(&self, original: &Cfg) -> String {
+        repr_with_original_rules(&self.cfg, original, |rule_id| self.original_rules(rule_id))
+    }
+
+    // -------------------------- parse trees
+
+    /// the parse tree of the original grammar corresponding to a parse tree of the grammar without ε-rules, the
+    /// erased symbols get back the subtrees of their ε-rules
+    pub fn rewrite_parse_tree(&self, original: &Cfg, parse_tree: &ParseTree) -> ParseTree {
+        ParseTreeRewriter::new(original, &self.cfg, parse_tree, &self.epsilon_rules).rewrite_with_fragments(&self.provenances)
+    }
+}
+
+
+/// one rule for each way of erasing the nullable symbols of the replacement, START keeps its empty rule
+pub(super) fn remove_epsilon_rules(original: &Cfg, rules: WorkingRules) -> WorkingRules {
+    let derives_non_empty: Vec<bool> = compute_derives_non_empty(original);
+    let is_nullable = |symbol: BuilderSymbol| matches!(symbol, BuilderSymbol::Original(symbol) if original.is_symbol_nullable(symbol));
+    let is_always_erased = |symbol: BuilderSymbol| is_nullable(symbol) && match symbol {
+        BuilderSymbol::Original(symbol) => !derives_non_empty[usize::from(symbol.id)],
+        BuilderSymbol::Fresh(_) => false,
+    };
+
+    let mut non_empty_rules: WorkingRules = vec![Vec::new(); rules.len()];
+    for (index, non_terminal_rules) in rules.into_iter().enumerate() {
+        for (replacement, fragment) in non_terminal_rules {
+            let DerivationFragment::Rule { rule_id, .. } = fragment else {
+                unreachable!("the rules are still the original ones");
+            };
+            // the symbols that are erased in some of the rules only
+            let optional_positions: Vec<usize> = (0..replacement.len())
+                .filter(|&position| is_nullable(replacement[position]) && !is_always_erased(replacement[position]))
+                .collect();
+
+            for erasing_mask in 0..(1usize << optional_positions.len()) {
+                let is_erased = |position: usize| is_always_erased(replacement[position]) || optional_positions
+                    .iter()
+                    .enumerate()
+                    .any(|(bit, &optional_position)| optional_position == position && (erasing_mask >> bit) & 1 == 1);
+                let kept: Vec<BuilderSymbol> = (0..replacement.len())
+                    .filter(|&position| !is_erased(position))
+                    .map(|position| replacement[position])
+                    .collect();
+                if kept.is_empty() && index != usize::from(original.START().id) {
+                    continue;
+                }
+                let children: Vec<DerivationFragment> = (0..replacement.len())
+                    .map(|position| match replacement[position] {
+                        BuilderSymbol::Original(symbol) if is_erased(position) => DerivationFragment::Erased(symbol),
+                        _ => DerivationFragment::Symbol,
+                    })
+                    .collect();
+                non_empty_rules[index].push((kept, DerivationFragment::Rule { rule_id, children }));
+            }
+        }
+    }
+    non_empty_rules
+}
+
+/// for each non-terminal, whether it derives a word of at least one terminal
+fn compute_derives_non_empty(cfg: &Cfg) -> Vec<bool> {
+    let mut derives_non_empty: Vec<bool> = vec![false; usize::from(cfg.nbr_non_terminals())];
+    loop {
+        let found: Vec<Symbol> = cfg
+            .all_rules()
+            .filter(|(_, rule)| {
+                !derives_non_empty[usize::from(rule.origin.id)]
+                    && rule.replacement.iter().all(|&symbol| cfg.is_symbol_productive(symbol))
+                    && rule.replacement.iter().any(|&symbol| cfg.is_terminal(symbol) || derives_non_empty[usize::from(symbol.id)])
+            })
+            .map(|(_, rule)| rule.origin)
+            .collect();
+        if found.is_empty() {
+            return derives_non_empty;
+        }
+        for origin in found {
+            derives_non_empty[usize::from(origin.id)] = true;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_grammar, rewritten_and_expected_trees};
+
+    #[test]
+    fn nullable_symbols() {
+        let original = create_grammar(
+            &["a", "b", "c", "d"],
+            &["S", "A", "B", "E"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["d", "S", "b"]),
+                ("S", &["A", "c", "B"]),
+                ("A", &["a"]),
+                ("A", &[]),
+                ("B", &["b"]),
+                ("B", &["E"]),
+                ("E", &[]),
+            ],
+        );
+
+        let elimination = EpsilonRuleElimination::new(&original);
+        let cfg: &Cfg = elimination.cfg();
+        println!("{}", elimination.repr(&original));
+        for (_, rule) in cfg.all_rules() {
+            assert!(!rule.replacement.is_empty());
+        }
+        let repr: String = elimination.repr(&original);
+        assert!(repr.contains("S --> A c B    (S --> A c B)"));
+        assert!(repr.contains("S --> c    (S --> A c B)"));
+        assert!(repr.contains("B --> b    (B --> b)"));
+        // E only derives ε, so it is always erased
+        assert!(!repr.contains("B --> E"));
+
+        for input in [&["c"][..], &["a", "c"], &["c", "b"], &["d", "a", "c", "b"], &["d", "c", "b"]] {
+            let (rewritten, expected) = rewritten_and_expected_trees(&original, elimination.cfg(), |parse_tree| elimination.rewrite_parse_tree(&original, parse_tree), input);
+            assert_eq!(rewritten, expected);
+        }
+    }
+
+    #[test]
+    fn nullable_start() {
+        let original = create_grammar(
+            &["a"],
+            &["A"],
+            &[
+                ("START", &["A", "END"]),
+                ("A", &["a", "A"]),
+                ("A", &[]),
+            ],
+        );
+
+        let elimination = EpsilonRuleElimination::new(&original);
+        let repr: String = elimination.repr(&original);
+        assert!(repr.contains("START --> A END    (START --> A END)"));
+        assert!(repr.contains("START --> END    (START --> A END)"));
+        assert!(repr.contains("A --> a    (A --> a A)"));
+        assert!(!repr.contains("A -->    "));
+
+        for input in [&[][..], &["a"], &["a", "a", "a"]] {
+            let (rewritten, expected) = rewritten_and_expected_trees(&original, elimination.cfg(), |parse_tree| elimination.rewrite_parse_tree(&original, parse_tree), input);
+            assert_eq!(rewritten, expected);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+
+/// grammar where the rules of a non-terminal never start with the same symbol
+/// the rules A -> α β1 | α β2 sharing the longest prefix α become A -> α A__Tail1, A__Tail1 -> β1 | β2, and the
+/// tails are factored again, numbered after the non-terminal of the original grammar
+pub struct LeftFactoring {
+    cfg: Cfg,
+
+    // indexed by the rules of cfg, the rules of the original grammar whose replacement starts with the one of
+    // the rule, sorted
+    provenances: Vec<Vec<CfgRuleIdx>>,
+}
+
+// replacements still to factor, with the original rule they end
+type FactoredRules = Vec<(Vec<BuilderSymbol>, CfgRuleIdx)>;
+
+impl LeftFactoring {
+
+    pub fn new(original: &Cfg) -> Self {
+        let mut builder: CfgBuilder<'_, Vec<CfgRuleIdx>> = CfgBuilder::new(original);
+        let O = BuilderSymbol::Original;
+
+        for non_terminal in original.all_non_terminals() {
+            let rules: FactoredRules = original
+                .get_rules_by_origin(non_terminal)
+                .map(|(rule_id, rule)| (rule.replacement.iter().map(|&symbol| O(symbol)).collect(), rule_id))
+                .collect();
+
+            let mut nbr_tails: usize = 0;
+            let mut to_factor: VecDeque<(BuilderSymbol, FactoredRules)> = VecDeque::from([(O(non_terminal), rules)]);
+            while let Some((origin, rules)) = to_factor.pop_front() {
+                // the rules grouped by their first symbol, in the order of the rules
+                let mut groups: Vec<FactoredRules> = Vec::new();
+                for (replacement, rule_id) in rules {
+                    let group: Option<&mut Vec<_>> = groups
+                        .iter_mut()
+                        .find(|group| !replacement.is_empty() && group[0].0.first() == replacement.first());
+                    match group {
+                        Some(group) => group.push((replacement, rule_id)),
+                        None => groups.push(vec![(replacement, rule_id)]),
+                    }
+                }
+
+                for group in groups {
+                    if group.len() == 1 {
+                        // unwrap is safe because the group has one rule
+                        let (replacement, rule_id) = group.into_iter().next().unwrap();
+                        builder.add_rule(origin, replacement, vec![rule_id]);
+                        continue;
+                    }
+
+                    let prefix_size: usize = (1..)
+                        .take_while(|&size| group.iter().all(|(replacement, _)| {
+                            replacement.len() >= size && replacement[size-1] == group[0].0[size-1]
+                        }))
+                        .last()
+                        // unwrap is safe because the rules of the group share their first symbol
+                        .unwrap();
+
+                    nbr_tails += 1;
+                    let tail: BuilderSymbol = builder.fresh_non_terminal(&format!("{}__Tail{}", original.repr_symbol(non_terminal), nbr_tails));
+                    let mut rule_ids: Vec<CfgRuleIdx> = group.iter().map(|&(_, rule_id)| rule_id).collect();
+                    rule_ids.sort();
+                    let prefix: Vec<BuilderSymbol> = group[0].0[..prefix_size].iter().copied().chain([tail]).collect();
+                    builder.add_rule(origin, prefix, rule_ids);
+
+                    let suffixes: FactoredRules = group
+                        .into_iter()
+                        .map(|(replacement, rule_id)| (replacement[prefix_size..].to_vec(), rule_id))
+                        .collect();
+                    to_factor.push_back((tail, suffixes));
+                }
+            }
+        }
+
+        let (cfg, provenances): (Cfg, Vec<Vec<CfgRuleIdx>>) = builder.build();
+        LeftFactoring { cfg, provenances }
+    }
+
+    pub fn cfg(&self) -> &Cfg {
+        &self.cfg
+    }
+
+    /// the rules of the original grammar the rule is a prefix of, there is only one when the rule isn't factored
+    /// or ends the replacement of an original rule
+    pub fn original_rules(&self, rule_id: CfgRuleIdx) -> &[CfgRuleIdx] {
+        &self.provenances[usize::from(rule_id)]
+    }
+
+    /// one line per rule, followed by the original rules it comes from
+    pub fn repr(&self, original: &Cfg) -> String {
+        repr_with_original_rules(&self.cfg, original, |rule_id| self.original_rules(rule_id).to_vec())
+    }
+
+    // -------------------------- parse trees
+
+    /// the parse tree of the original grammar corresponding to a parse tree of the factored grammar, the nodes of
+    /// the tails are replaced by their children, and the tokens get back the terminals of the original grammar
+    pub fn rewrite_parse_tree(&self, original: &Cfg, parse_tree: &ParseTree) -> ParseTree {
+        let mut rewriter: ParseTreeRewriter<'_> = ParseTreeRewriter::new(original, &self.cfg, parse_tree, &[]);
+        if let Some(root_id) = parse_tree.root() {
+            let root_id: usize = self.rewrite_node(&mut rewriter, root_id);
+            rewriter.rewritten.set_root(root_id);
+        }
+        rewriter.rewritten
+    }
+
+    fn rewrite_node(&self, rewriter: &mut ParseTreeRewriter<'_>, node_id: usize) -> usize {
+        let parse_tree: &ParseTree = rewriter.parse_tree;
+        if parse_tree.is_leaf(node_id) {
+            return rewriter.rewrite_leaf(node_id);
+        }
+
+        // the tails are the last child of the rules factoring a prefix
+        let is_tail = |child_id: usize| original_symbol(rewriter.original, &self.cfg, parse_tree.symbol(child_id)).is_none();
+        let mut children_ids: Vec<usize> = Vec::new();
+        let mut current_id: usize = node_id;
+        loop {
+            let (&last_id, others) = match parse_tree.children(current_id).split_last() {
+                Some((last_id, others)) if is_tail(*last_id) => (last_id, others),
+                _ => break,
+            };
+            children_ids.extend(others);
+            current_id = last_id;
+        }
+        children_ids.extend(parse_tree.children(current_id));
+
+        let children: Vec<usize> = children_ids.into_iter().map(|child_id| self.rewrite_node(rewriter, child_id)).collect();
+        // unwrap is safe because the node is an inner node
+        let rule_id: CfgRuleIdx = self.original_rules(parse_tree.rule_id(current_id).unwrap())[0];
+        let origin: Symbol = rewriter.original.get_rule_by_id(rule_id).origin;
+        rewriter.rewritten.add_parent_node(ParseTreeNodeKind::Inner { rule_id, origin }, children)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar, rewritten_and_expected_trees};
+    use crate::parsing::LL_parsing::LL1_generator::generate_LL1_table;
+
+    #[test]
+    fn common_prefixes() {
+        let original = create_grammar(
+            &["a", "b", "c", "d", "e", "f"],
+            &["A"],
+            &[
+                ("START", &["A", "END"]),
+                ("A", &["a", "b", "c"]),
+                ("A", &["a", "b", "d"]),
+                ("A", &["f"]),
+                ("A", &["a", "e"]),
+                ("A", &["a", "b"]),
+            ],
+        );
+        assert!(generate_LL1_table(&original).is_err());
+
+        let factoring = LeftFactoring::new(&original);
+        println!("{}", factoring.repr(&original));
+        let repr: String = factoring.repr(&original);
+        assert!(repr.contains("A --> a A__Tail1    (A --> a b c, A --> a b d, A --> a e, A --> a b)"));
+        assert!(repr.contains("A --> f    (A --> f)"));
+        assert!(repr.contains("A__Tail1 --> b A__Tail2    (A --> a b c, A --> a b d, A --> a b)"));
+        assert!(repr.contains("A__Tail1 --> e    (A --> a e)"));
+        assert!(repr.contains("A__Tail2 --> c    (A --> a b c)"));
+        assert!(repr.contains("A__Tail2 -->    (A --> a b)"));
+
+        let cfg: &Cfg = factoring.cfg();
+        assert!(generate_LL1_table(cfg).is_ok());
+        for input in [&["a", "b", "c"][..], &["a", "b"], &["a", "e"], &["f"]] {
+            let (rewritten, expected) = rewritten_and_expected_trees(&original, cfg, |parse_tree| factoring.rewrite_parse_tree(&original, parse_tree), input);
+            assert_eq!(rewritten, expected);
+        }
+    }
+
+    #[test]
+    fn nothing_to_factor() {
+        let original = create_expression_grammar();
+        let factoring = LeftFactoring::new(&original);
+        let cfg: &Cfg = factoring.cfg();
+        assert_eq!(cfg.nbr_non_terminals(), original.nbr_non_terminals());
+        for (rule_id, _) in cfg.all_rules() {
+            assert_eq!(cfg.repr_rule(rule_id), original.repr_rule(rule_id));
+            assert_eq!(factoring.original_rules(rule_id), [rule_id]);
+        }
+    }
+
+    #[test]
+    fn taken_tail_name() {
+        // the name of the tail is already taken by a non-terminal of the original grammar
+        let original = create_grammar(
+            &["a", "b", "c"],
+            &["A", "A__Tail1"],
+            &[
+                ("START", &["A", "A__Tail1", "END"]),
+                ("A", &["a", "b"]),
+                ("A", &["a", "c"]),
+                ("A__Tail1", &["c"]),
+            ],
+        );
+        let factoring = LeftFactoring::new(&original);
+        let repr: String = factoring.repr(&original);
+        assert!(repr.contains("A --> a A__Tail1_2    (A --> a b, A --> a c)"));
+        assert!(repr.contains("A__Tail1_2 --> b    (A --> a b)"));
+        assert!(repr.contains("A__Tail1 --> c    (A__Tail1 --> c)"));
+    }
+}
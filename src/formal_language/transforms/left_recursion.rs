@@ -0,0 +1,400 @@
+use std::collections::HashSet;
+
+use super::epsilon_rules::remove_epsilon_rules;
+use super::unit_rules::remove_unit_rules;
+use super::*;
+
+
+/// where a rule of the grammar without left recursion comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeftRecursionRuleProvenance {
+    /// rule deriving like the fragment
+    Rule(DerivationFragment),
+    /// A__Extend -> α A__Extend: the fragment of the left-recursive rule A -> A α, its first symbol is the tree
+    /// of A built so far, and the last symbol of the replacement gives the next extensions
+    Extension(DerivationFragment),
+    /// A__Extend -> ε, the tree of A is complete
+    End,
+}
+
+
+/// grammar without left recursion, obtained with Paull's algorithm
+/// the rules A -> A α | β of a left-recursive non-terminal become A -> β A__Extend, A__Extend -> α A__Extend | ε,
+/// after the rules starting with a non-terminal that derives A on the left were substituted, and the ε-rules and
+/// the cycles are removed first when a nullable prefix or a cycle leads to the left recursion
+pub struct LeftRecursionElimination {
+    cfg: Cfg,
+
+    // indexed by the rules of cfg
+    provenances: Vec<LeftRecursionRuleProvenance>,
+
+    // indexed by the non-terminals of the original grammar, used to rebuild the erased symbols
+    epsilon_rules: Vec<Option<CfgRuleIdx>>,
+}
+
+impl LeftRecursionElimination {
+
+    pub fn new(original: &Cfg) -> Self {
+        let mut builder: CfgBuilder<'_, LeftRecursionRuleProvenance> = CfgBuilder::new(original);
+        let O = BuilderSymbol::Original;
+
+        let mut rules: WorkingRules = original_working_rules(original);
+
+        if needs_epsilon_and_cycle_removal(original) {
+            rules = remove_unit_rules(remove_epsilon_rules(original, rules));
+        }
+
+        for non_terminal in original.all_non_terminals() {
+            let index: usize = usize::from(non_terminal.id);
+
+            // substitutes the first symbol of the rules while it derives the non-terminal on the left, the
+            // previous non-terminals don't have left recursion anymore so this ends
+            loop {
+                let position: Option<usize> = rules[index].iter().position(|(replacement, _)| match replacement.first() {
+                    Some(&BuilderSymbol::Original(first)) =>
+                        first.id < non_terminal.id && left_corner_reaches(original, &rules, first, non_terminal),
+                    _ => false,
+                });
+                let Some(position) = position else {
+                    break;
+                };
+
+                let (replacement, fragment) = rules[index].remove(position);
+                let BuilderSymbol::Original(first) = replacement[0] else { unreachable!("the first symbol was just matched") };
+                let substituted: Vec<(Vec<BuilderSymbol>, DerivationFragment)> = rules[usize::from(first.id)]
+                    .iter()
+                    .map(|(first_replacement, first_fragment)| {
+                        let mut new_fragment: DerivationFragment = fragment.clone();
+                        new_fragment.substitute_first(first_fragment.clone());
+                        ([first_replacement.as_slice(), &replacement[1..]].concat(), new_fragment)
+                    })
+                    .collect();
+                rules[index].extend(substituted);
+            }
+
+            // direct left recursion
+            let (recursive, base): (Vec<_>, Vec<_>) = std::mem::take(&mut rules[index])
+                .into_iter()
+                .partition(|(replacement, _)| replacement.first() == Some(&O(non_terminal)));
+            if recursive.is_empty() {
+                rules[index] = base;
+                continue;
+            }
+
+            let helper: BuilderSymbol = builder.fresh_non_terminal(&format!("{}__Extend", original.repr_symbol(non_terminal)));
+            for (replacement, fragment) in recursive {
+                // A -> A doesn't change the tree
+                if replacement.len() == 1 {
+                    continue;
+                }
+                let extension: Vec<BuilderSymbol> = replacement[1..].iter().copied().chain([helper]).collect();
+                builder.add_rule(helper, extension, LeftRecursionRuleProvenance::Extension(fragment));
+            }
+            builder.add_rule(helper, Vec::new(), LeftRecursionRuleProvenance::End);
+
+            rules[index] = base
+                .into_iter()
+                .map(|(mut replacement, fragment)| {
+                    replacement.push(helper);
+                    (replacement, DerivationFragment::Extended { base: Box::new(fragment) })
+                })
+                .collect();
+        }
+
+        add_working_rules(&mut builder, rules, LeftRecursionRuleProvenance::Rule);
+
+        let (cfg, provenances): (Cfg, Vec<LeftRecursionRuleProvenance>) = builder.build();
+        LeftRecursionElimination {
+            cfg,
+            provenances,
+            epsilon_rules: compute_epsilon_rules(original),
+        }
+    }
+
+    pub fn cfg(&self) -> &Cfg {
+        &self.cfg
+    }
+
+    pub fn get_provenance(&self, rule_id: CfgRuleIdx) -> &LeftRecursionRuleProvenance {
+        &self.provenances[usize::from(rule_id)]
+    }
+
+    /// the rules of the original grammar applied by the rule, from the top of the tree
+    pub fn original_rules(&self, rule_id: CfgRuleIdx) -> Vec<CfgRuleIdx> {
+        match self.get_provenance(rule_id) {
+            LeftRecursionRuleProvenance::Rule(fragment) | LeftRecursionRuleProvenance::Extension(fragment) =>
+                fragment.original_rules(),
+            LeftRecursionRuleProvenance::End => vec![],
+        }
+    }
+
+    /// one line per rule, followed by the original rules it comes from
+    pub fn repr(&self, original: &Cfg) -> String {
+        repr_with_original_rules(&self.cfg, original, |rule_id| self.original_rules(rule_id))
+    }
+
+    // -------------------------- parse trees
+
+    /// the parse tree of the original grammar corresponding to a parse tree of the grammar without left recursion,
+    /// the tokens get back the terminals of the original grammar
+    pub fn rewrite_parse_tree(&self, original: &Cfg, parse_tree: &ParseTree) -> ParseTree {
+        let mut rewriter: ParseTreeRewriter<'_> = ParseTreeRewriter::new(original, &self.cfg, parse_tree, &self.epsilon_rules);
+        if let Some(root_id) = parse_tree.root() {
+            let root_id: usize = self.rewrite_node(&mut rewriter, root_id);
+            rewriter.rewritten.set_root(root_id);
+        }
+        rewriter.rewritten
+    }
+
+    fn rewrite_node(&self, rewriter: &mut ParseTreeRewriter<'_>, node_id: usize) -> usize {
+        let parse_tree: &ParseTree = rewriter.parse_tree;
+        let Some(rule_id) = parse_tree.rule_id(node_id) else {
+            return rewriter.rewrite_leaf(node_id);
+        };
+
+        let LeftRecursionRuleProvenance::Rule(fragment) = self.get_provenance(rule_id) else {
+            panic!("the helpers of the left recursion are rewritten with the non-terminal they extend");
+        };
+        let mut children = parse_tree.children(node_id).iter().copied();
+        self.build_fragment(rewriter, fragment, &mut None, &mut children)
+    }
+
+    /// the nodes of the original rules of the fragment, its first symbol is the built node if there is one,
+    /// the other symbols are the children rewritten in order
+    fn build_fragment(
+        &self,
+        rewriter: &mut ParseTreeRewriter<'_>,
+        fragment: &DerivationFragment,
+        built: &mut Option<usize>,
+        children: &mut impl Iterator<Item = usize>,
+    ) -> usize {
+        let parse_tree: &ParseTree = rewriter.parse_tree;
+        match fragment {
+            DerivationFragment::Symbol => match built.take() {
+                Some(node_id) => node_id,
+                // unwrap is safe because there is one child for each symbol of the replacement
+                None => self.rewrite_node(rewriter, children.next().unwrap()),
+            },
+            DerivationFragment::Erased(non_terminal) => rewriter.build_epsilon_node(*non_terminal),
+            DerivationFragment::Rule { rule_id, children: fragments } => {
+                let nodes: Vec<usize> = fragments
+                    .iter()
+                    .map(|fragment| self.build_fragment(rewriter, fragment, built, children))
+                    .collect();
+                let origin: Symbol = rewriter.original.get_rule_by_id(*rule_id).origin;
+                rewriter.rewritten.add_parent_node(ParseTreeNodeKind::Inner { rule_id: *rule_id, origin }, nodes)
+            }
+            DerivationFragment::Extended { base } => {
+                let mut node_id: usize = self.build_fragment(rewriter, base, built, children);
+                // unwrap is safe because the helper follows the symbols of the base
+                let mut extension_id: usize = children.next().unwrap();
+                // the rules of the chain of helpers are applied from the bottom of the original tree
+                loop {
+                    // unwrap is safe because the helpers only have inner nodes
+                    let extension_rule: CfgRuleIdx = parse_tree.rule_id(extension_id).unwrap();
+                    let LeftRecursionRuleProvenance::Extension(fragment) = self.get_provenance(extension_rule) else {
+                        return node_id;
+                    };
+                    // unwrap is safe because the replacement of an extension ends with the next helper
+                    let (&next_extension_id, extension_children) = parse_tree.children(extension_id).split_last().unwrap();
+                    let mut extension_children = extension_children.iter().copied();
+                    node_id = self.build_fragment(rewriter, fragment, &mut Some(node_id), &mut extension_children);
+                    extension_id = next_extension_id;
+                }
+            }
+        }
+    }
+}
+
+
+/// whether a non-terminal derives a word starting with itself, the nullable prefixes included
+pub fn is_left_recursive(cfg: &Cfg) -> bool {
+    let edges: Vec<Vec<(Symbol, bool)>> = left_corner_edges(cfg);
+    cfg.all_non_terminals().any(|non_terminal| reachable(&edges, non_terminal).contains(&non_terminal))
+}
+
+/// for each non-terminal, the non-terminals starting its replacements after a nullable prefix, with whether
+/// the prefix is empty
+fn left_corner_edges(cfg: &Cfg) -> Vec<Vec<(Symbol, bool)>> {
+    let mut edges: Vec<Vec<(Symbol, bool)>> = vec![Vec::new(); usize::from(cfg.nbr_non_terminals())];
+    for (_, rule) in cfg.all_rules() {
+        for (position, &symbol) in rule.replacement.iter().enumerate() {
+            if cfg.is_non_terminal(symbol) {
+                edges[usize::from(rule.origin.id)].push((symbol, position == 0));
+            }
+            if !cfg.is_symbol_nullable(symbol) {
+                break;
+            }
+        }
+    }
+    edges
+}
+
+/// the non-terminals reachable in at least one step by the edges
+fn reachable(edges: &[Vec<(Symbol, bool)>], from: Symbol) -> HashSet<Symbol> {
+    let mut reached: HashSet<Symbol> = HashSet::new();
+    let mut to_process: Vec<Symbol> = vec![from];
+    while let Some(current) = to_process.pop() {
+        for &(target, _) in &edges[usize::from(current.id)] {
+            if reached.insert(target) {
+                to_process.push(target);
+            }
+        }
+    }
+    reached
+}
+
+/// Paull's algorithm needs the left recursion to only go through the first symbols of the replacements,
+/// without cycles A =>+ A
+fn needs_epsilon_and_cycle_removal(cfg: &Cfg) -> bool {
+    let edges: Vec<Vec<(Symbol, bool)>> = left_corner_edges(cfg);
+    let hidden_left_recursion: bool = cfg.all_non_terminals().any(|non_terminal| {
+        edges[usize::from(non_terminal.id)]
+            .iter()
+            .any(|&(target, is_first)| !is_first && (target == non_terminal || reachable(&edges, target).contains(&non_terminal)))
+    });
+    let has_cycle: bool = cfg.all_rules().any(|(_, rule)| {
+        rule.replacement.iter().enumerate().any(|(position, &symbol)| {
+            cfg.is_non_terminal(symbol)
+            && rule.replacement.iter().enumerate().all(|(other, &other_symbol)| other == position || cfg.is_symbol_nullable(other_symbol))
+            && (symbol == rule.origin || derives_alone(cfg, symbol, rule.origin))
+        })
+    });
+    hidden_left_recursion || has_cycle
+}
+
+/// whether the non-terminal derives the target alone, with the other symbols derived to the empty word
+fn derives_alone(cfg: &Cfg, non_terminal: Symbol, target: Symbol) -> bool {
+    let mut reached: HashSet<Symbol> = HashSet::from([non_terminal]);
+    let mut to_process: Vec<Symbol> = vec![non_terminal];
+    while let Some(current) = to_process.pop() {
+        for (_, rule) in cfg.get_rules_by_origin(current) {
+            for (position, &symbol) in rule.replacement.iter().enumerate() {
+                let is_alone: bool = rule.replacement.iter().enumerate().all(|(other, &other_symbol)| other == position || cfg.is_symbol_nullable(other_symbol));
+                if cfg.is_non_terminal(symbol) && is_alone && reached.insert(symbol) {
+                    to_process.push(symbol);
+                }
+            }
+        }
+    }
+    reached.contains(&target)
+}
+
+/// whether the first symbols of the current rules lead from the non-terminal to the target
+fn left_corner_reaches(original: &Cfg, rules: &WorkingRules, from: Symbol, target: Symbol) -> bool {
+    let mut reached: HashSet<Symbol> = HashSet::from([from]);
+    let mut to_process: Vec<Symbol> = vec![from];
+    while let Some(current) = to_process.pop() {
+        if current == target {
+            return true;
+        }
+        for (replacement, _) in &rules[usize::from(current.id)] {
+            if let Some(&BuilderSymbol::Original(first)) = replacement.first()
+            && original.is_non_terminal(first)
+            && reached.insert(first) {
+                to_process.push(first);
+            }
+        }
+    }
+    false
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar, rewritten_and_expected_trees, tokens};
+    use crate::parsing::LL_parsing::LL1_generator::generate_LL1_table;
+    use crate::parsing::LL_parsing::LL1_parser::LL1Parser;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_parser::LR1Parser;
+    use crate::parsing::BuildingParser;
+
+    #[test]
+    fn expression_grammar() {
+        let original = create_expression_grammar();
+        assert!(is_left_recursive(&original));
+
+        let elimination = LeftRecursionElimination::new(&original);
+        println!("{}", elimination.repr(&original));
+        let cfg: &Cfg = elimination.cfg();
+        assert!(!is_left_recursive(cfg));
+
+        let repr: String = elimination.repr(&original);
+        assert!(repr.contains("E --> T E__Extend    (E --> T)"));
+        assert!(repr.contains("E__Extend --> + T E__Extend    (E --> E + T)"));
+        assert!(repr.contains("E__Extend -->\n"));
+        assert!(repr.contains("F --> ( E )    (F --> ( E ))"));
+
+        // the grammar becomes LL(1), and its trees are rewritten to the ones of the LR(1) parser
+        let table = generate_LL1_table(cfg).unwrap();
+        let input: &[&str] = &["id", "+", "id", "*", "(", "id", "+", "id", ")", "+", "id"];
+        let parse_tree = LL1Parser::new(cfg, &table).parse_from_iter(tokens(cfg, input).into_iter()).unwrap();
+        let rewritten: ParseTree = elimination.rewrite_parse_tree(&original, &parse_tree);
+        println!("{}", rewritten.repr(&original));
+
+        let lr_table = generate_LALR1_table(&original);
+        let expected = LR1Parser::new(&original, &lr_table).parse_from_iter(tokens(&original, input).into_iter()).unwrap();
+        assert_eq!(rewritten.repr(&original), expected.repr(&original));
+        assert_eq!(rewritten.token(rewritten.pre_order(rewritten.root().unwrap()).last().unwrap()).unwrap().token_type, original.END());
+    }
+
+    #[test]
+    fn indirect_left_recursion() {
+        // S -> A a -> S c a
+        let original = create_grammar(
+            &["a", "b", "c", "d"],
+            &["S", "A"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["A", "a"]),
+                ("S", &["b"]),
+                ("A", &["S", "c"]),
+                ("A", &["d"]),
+            ],
+        );
+        let elimination = LeftRecursionElimination::new(&original);
+        println!("{}", elimination.repr(&original));
+        assert!(!is_left_recursive(elimination.cfg()));
+
+        let repr: String = elimination.repr(&original);
+        assert!(repr.contains("A --> b c A__Extend    (A --> S c, S --> b)"));
+        assert!(repr.contains("A__Extend --> a c A__Extend    (A --> S c, S --> A a)"));
+
+        for input in [&["b"][..], &["d", "a"], &["b", "c", "a", "c", "a"], &["d", "a", "c", "a"]] {
+            let (rewritten, expected) = rewritten_and_expected_trees(&original, elimination.cfg(), |parse_tree| elimination.rewrite_parse_tree(&original, parse_tree), input);
+            assert_eq!(rewritten, expected);
+        }
+    }
+
+    #[test]
+    fn hidden_left_recursion_and_cycle() {
+        // B -> N B x with N nullable, and the cycle C -> D -> C
+        let original = create_grammar(
+            &["n", "x", "y", "z"],
+            &["B", "N", "C", "D"],
+            &[
+                ("START", &["B", "END"]),
+                ("B", &["N", "B", "x"]),
+                ("B", &["C"]),
+                ("N", &["n"]),
+                ("N", &[]),
+                ("C", &["D"]),
+                ("C", &["y"]),
+                ("D", &["C"]),
+                ("D", &["C", "z"]),
+            ],
+        );
+        assert!(is_left_recursive(&original));
+        let elimination = LeftRecursionElimination::new(&original);
+        println!("{}", elimination.repr(&original));
+        assert!(!is_left_recursive(elimination.cfg()));
+        // the ε-rules are removed before the left recursion
+        assert!(elimination.repr(&original).contains("B__Extend --> x B__Extend    (B --> N B x)"));
+
+        for input in [&["y"][..], &["y", "x", "x"], &["n", "y", "z", "x"], &["n", "y", "z", "z", "x", "x"]] {
+            let (rewritten, expected) = rewritten_and_expected_trees(&original, elimination.cfg(), |parse_tree| elimination.rewrite_parse_tree(&original, parse_tree), input);
+            assert_eq!(rewritten, expected);
+        }
+    }
+}
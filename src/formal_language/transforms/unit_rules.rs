@@ -0,0 +1,150 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::*;
+
+
+/// grammar without unit rules A -> B
+/// each non-terminal gets the other rules of the non-terminals it reaches by unit rules, so the cycles of unit
+/// rules disappear too
+pub struct UnitRuleElimination {
+    cfg: Cfg,
+
+    // indexed by the rules of cfg, the chain of unit rules ending with the rule of the reached non-terminal
+    provenances: Vec<DerivationFragment>,
+}
+
+impl UnitRuleElimination {
+
+    pub fn new(original: &Cfg) -> Self {
+        let mut builder: CfgBuilder<'_, DerivationFragment> = CfgBuilder::new(original);
+        let rules: WorkingRules = remove_unit_rules(original_working_rules(original));
+        add_working_rules(&mut builder, rules, |fragment| fragment);
+
+        let (cfg, provenances): (Cfg, Vec<DerivationFragment>) = builder.build();
+        UnitRuleElimination { cfg, provenances }
+    }
+
+    pub fn cfg(&self) -> &Cfg {
+        &self.cfg
+    }
+
+    pub fn get_provenance(&self, rule_id: CfgRuleIdx) -> &DerivationFragment {
+        &self.provenances[usize::from(rule_id)]
+    }
+
+    /// the unit rules applied by the rule, from the top of the tree, then the rule of the reached non-terminal
+    pub fn original_rules(&self, rule_id: CfgRuleIdx) -> Vec<CfgRuleIdx> {
+        self.get_provenance(rule_id).original_rules()
+    }
+
+    /// one line per rule, followed by the original rules it comes from
+    pub fn repr(&self, original: &Cfg) -> String {
+        repr_with_original_rules(&self.cfg, original, |rule_id| self.original_rules(rule_id))
+    }
+
+    // -------------------------- parse trees
+
+    /// the parse tree of the original grammar corresponding to a parse tree of the grammar without unit rules,
+    /// the nodes of the unit rules are added back
+    pub fn rewrite_parse_tree(&self, original: &Cfg, parse_tree: &ParseTree) -> ParseTree {
+        ParseTreeRewriter::new(original, &self.cfg, parse_tree, &[]).rewrite_with_fragments(&self.provenances)
+    }
+}
+
+
+/// each non-terminal gets the other rules of the non-terminals it reaches by unit rules, with the shortest
+/// chain of unit rules
+pub(super) fn remove_unit_rules(rules: WorkingRules) -> WorkingRules {
+    let unit_target = |replacement: &[BuilderSymbol]| match *replacement {
+        [BuilderSymbol::Original(symbol)] if symbol.id < SymbolIdx::from(rules.len()) => Some(symbol),
+        _ => None,
+    };
+
+    let mut unit_free_rules: WorkingRules = vec![Vec::new(); rules.len()];
+    for (index, unit_free_non_terminal_rules) in unit_free_rules.iter_mut().enumerate() {
+        let non_terminal: Symbol = Symbol { id: SymbolIdx::from(index) };
+        let mut unit_chains: HashMap<Symbol, DerivationFragment> = HashMap::from([(non_terminal, DerivationFragment::Symbol)]);
+        let mut reached: Vec<Symbol> = Vec::new();
+        let mut to_process: VecDeque<Symbol> = VecDeque::from([non_terminal]);
+        while let Some(current) = to_process.pop_front() {
+            reached.push(current);
+            for (replacement, fragment) in &rules[usize::from(current.id)] {
+                let Some(target) = unit_target(replacement) else {
+                    continue;
+                };
+                if unit_chains.contains_key(&target) {
+                    continue;
+                }
+                let mut unit_chain: DerivationFragment = unit_chains[&current].clone();
+                unit_chain.substitute_first(fragment.clone());
+                unit_chains.insert(target, unit_chain);
+                to_process.push_back(target);
+            }
+        }
+
+        let mut added_replacements: HashSet<Vec<BuilderSymbol>> = HashSet::new();
+        for current in reached {
+            for (replacement, fragment) in &rules[usize::from(current.id)] {
+                // the same rule can be obtained in several ways, only the first one is kept
+                if unit_target(replacement).is_some() || !added_replacements.insert(replacement.clone()) {
+                    continue;
+                }
+                let mut unit_chain: DerivationFragment = unit_chains[&current].clone();
+                unit_chain.substitute_first(fragment.clone());
+                unit_free_non_terminal_rules.push((replacement.clone(), unit_chain));
+            }
+        }
+    }
+    unit_free_rules
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar, rewritten_and_expected_trees};
+
+    #[test]
+    fn expression_grammar() {
+        let original = create_expression_grammar();
+        let elimination = UnitRuleElimination::new(&original);
+        let cfg: &Cfg = elimination.cfg();
+        println!("{}", elimination.repr(&original));
+        for (_, rule) in cfg.all_rules() {
+            assert!(!matches!(rule.replacement[..], [symbol] if cfg.is_non_terminal(symbol)));
+        }
+        let repr: String = elimination.repr(&original);
+        assert!(repr.contains("E --> id    (E --> T, T --> F, F --> id)"));
+        assert!(repr.contains("E --> T * F    (E --> T, T --> T * F)"));
+        assert!(repr.contains("T --> ( E )    (T --> F, F --> ( E ))"));
+
+        for input in [&["id"][..], &["id", "+", "id", "*", "id"], &["(", "id", "+", "id", ")", "*", "id"]] {
+            let (rewritten, expected) = rewritten_and_expected_trees(&original, cfg, |parse_tree| elimination.rewrite_parse_tree(&original, parse_tree), input);
+            assert_eq!(rewritten, expected);
+        }
+    }
+
+    #[test]
+    fn unit_cycle() {
+        let original = create_grammar(
+            &["a", "b"],
+            &["A", "B"],
+            &[
+                ("START", &["A", "END"]),
+                ("A", &["B"]),
+                ("A", &["a"]),
+                ("B", &["A"]),
+                ("B", &["b"]),
+            ],
+        );
+
+        let elimination = UnitRuleElimination::new(&original);
+        let repr: String = elimination.repr(&original);
+        println!("{}", repr);
+        assert!(repr.contains("A --> a    (A --> a)"));
+        assert!(repr.contains("A --> b    (A --> B, B --> b)"));
+        assert!(repr.contains("B --> a    (B --> A, A --> a)"));
+        assert!(repr.contains("B --> b    (B --> b)"));
+        assert_eq!(elimination.cfg().nbr_rules().0, 5);
+    }
+}
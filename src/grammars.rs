@@ -1,4 +1,6 @@
 
-mod regex_grammar;
-mod math_grammar;
-mod bnf_grammar;
\ No newline at end of file
+pub mod regex_grammar;
+pub mod math_grammar;
+mod bnf_grammar;
+#[cfg(test)]
+pub mod test_grammars;
@@ -0,0 +1,119 @@
+use crate::formal_language::*;
+use crate::formal_language::precedence::PrecedenceTable;
+
+
+/// ambiguous grammar of arithmetic expressions, made deterministic for the LR generators by its precedence table
+pub fn create_math_grammar() -> Cfg {
+
+    let terminals: SymbolSet = SymbolSet::new(vec![
+        String::from("+"),
+        String::from("-"),
+        String::from("*"),
+        String::from("/"),
+        String::from("^"),
+
+        String::from("("),
+        String::from(")"),
+
+        String::from("number"),
+        String::from("identifier"),
+
+        String::from("UMINUS"),     // never read, only gives its precedence to the unary minus
+    ]);
+
+    let non_terminals: SymbolSet = SymbolSet::new(vec![
+        String::from("Expression"),
+    ]);
+
+    let cfg_symbol_set = CfgSymbolSet::new(terminals, non_terminals);
+
+    // S for Symbol
+    let S = |s: &str| cfg_symbol_set.get_symbol_by_representation(s);
+
+    let rules:Vec<CfgRule> = vec![
+        CfgRule { origin: S("START"), replacement: vec![S("Expression"), S("END")]},
+
+        CfgRule { origin: S("Expression"), replacement: vec![S("Expression"), S("+"), S("Expression")]},
+        CfgRule { origin: S("Expression"), replacement: vec![S("Expression"), S("-"), S("Expression")]},
+        CfgRule { origin: S("Expression"), replacement: vec![S("Expression"), S("*"), S("Expression")]},
+        CfgRule { origin: S("Expression"), replacement: vec![S("Expression"), S("/"), S("Expression")]},
+        CfgRule { origin: S("Expression"), replacement: vec![S("Expression"), S("^"), S("Expression")]},
+        CfgRule { origin: S("Expression"), replacement: vec![S("-"), S("Expression")]},
+
+        CfgRule { origin: S("Expression"), replacement: vec![S("("), S("Expression"), S(")")]},
+        CfgRule { origin: S("Expression"), replacement: vec![S("number")]},
+        CfgRule { origin: S("Expression"), replacement: vec![S("identifier")]},
+    ];
+
+    let mut precedence_table: PrecedenceTable = PrecedenceTable::new();
+    precedence_table
+        .left(&[S("+"), S("-")])
+        .left(&[S("*"), S("/")])
+        .right(&[S("UMINUS")])
+        .right(&[S("^")]);
+
+    let unary_minus_replacement: Vec<Symbol> = vec![S("-"), S("Expression")];
+    let (expression, uminus) = (S("Expression"), S("UMINUS"));
+
+    let mut cfg = Cfg::new(cfg_symbol_set, rules).unwrap();
+
+    // %prec UMINUS for the unary minus
+    let (unary_minus_rule_id, _) = cfg
+        .get_rules_by_origin(expression)
+        .find(|(_, rule)| rule.replacement == unary_minus_replacement)
+        .unwrap();
+    precedence_table.set_rule_precedence(unary_minus_rule_id, uminus);
+
+    cfg.set_precedence_table(precedence_table);
+    cfg
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::create_math_grammar;
+    use crate::grammars::test_grammars::tokens;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_generators::PrecedenceResolution;
+    use crate::parsing::LR_parsing::LR1_parser::LR1Parser;
+    use crate::parsing::BuildingParser;
+
+    #[test]
+    fn conflicts_resolved_by_precedence() {
+        let cfg = create_math_grammar();
+        let table = generate_LALR1_table(&cfg);
+        println!("{}", table.repr_resolved_conflicts(&cfg));
+
+        assert!(!table.has_conflicts(), "{}", table.repr_conflicts(&cfg));
+        // 5 binary operators and the unary minus, each against the 5 binary operators
+        assert_eq!(table.resolved_conflicts().len(), 30);
+        assert!(table.resolved_conflicts().iter().all(|conflict| conflict.resolution != PrecedenceResolution::Error));
+    }
+
+    #[test]
+    fn parse_with_precedence() {
+        let cfg = create_math_grammar();
+        let table = generate_LALR1_table(&cfg);
+        let parser = LR1Parser::new(&cfg, &table);
+
+        // - 1 - 2 * 3 ^ 4 ^ 5 = ((-1) - (2 * (3 ^ (4 ^ 5))))
+        let parse_tree = parser.parse_from_iter(tokens(&cfg,
+            &["-", "number", "-", "number", "*", "number", "^", "number", "^", "number"]).into_iter()).unwrap();
+        let repr: String = parse_tree.repr(&cfg);
+        println!("{repr}");
+        let rules: Vec<&str> = repr.lines().map(str::trim).filter(|line| line.contains("-->")).collect();
+        assert_eq!(rules, vec![
+            "START --> Expression END",
+            "Expression --> Expression - Expression",
+            "Expression --> - Expression",
+            "Expression --> number",
+            "Expression --> Expression * Expression",
+            "Expression --> number",
+            "Expression --> Expression ^ Expression",
+            "Expression --> number",
+            "Expression --> Expression ^ Expression",
+            "Expression --> number",
+            "Expression --> number",
+        ]);
+    }
+}
@@ -1,5 +1,3 @@
-use std::borrow::Cow;
-
 use crate::formal_language::*;
 
 
@@ -19,7 +17,7 @@ pub fn create_regex_grammar() -> Cfg {
         Vec::from_iter(other_chars.chars()),].concat();
 
 
-    let alphabet: Alphabet = Alphabet::new(all_chars);
+    let _alphabet: Alphabet = Alphabet::new(all_chars);
 
     let terminals: SymbolSet = SymbolSet::new(vec![
         String::from("+"),
@@ -180,6 +178,8 @@ pub fn create_regex_grammar() -> Cfg {
 mod tests{
 
     use super::create_regex_grammar;
+    use crate::datastructures::bitset::BitSet;
+    use crate::UINT;
 
     #[test]
     fn test1(){
@@ -196,13 +196,13 @@ mod tests{
         }
 
 
-        for (rule_id, rule) in cfg.all_rules() {
+        for (rule_id, _rule) in cfg.all_rules() {
             println!("{}", cfg.repr_rule(rule_id));
         }
 
         for symbol in cfg.all_symbols() {
             println!("symbol : {}", cfg.repr_symbol(symbol));
-            for (rule_id, rule) in cfg.get_rules_producing(symbol) {
+            for (rule_id, _rule) in cfg.get_rules_producing(symbol) {
                 println!("    {:?}", cfg.repr_rule(rule_id));
             }
         }
@@ -211,4 +211,70 @@ mod tests{
             println!("symbol : {}, {}", cfg.repr_symbol(symbol), cfg.is_symbol_nullable(symbol));
         }
     }
+
+    #[test]
+    fn first_sets(){
+        let cfg = create_regex_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let first_contains = |first_set: &BitSet<UINT>, terminal: &str| 
+            first_set.contains(usize::from(cfg.to_local_nosymbol(S(terminal))));
+
+        let first_expression = cfg.get_first_set(S("Expression"));
+        for terminal in ["char", ".", "^", "$", "[", "("] {
+            assert!(first_contains(&first_expression, terminal), "{terminal} should be in FIRST(Expression)");
+        }
+        assert!(!first_contains(&first_expression, "|"));
+        assert!(!first_expression.contains(0));
+        assert_eq!(first_expression.len(), 6);
+
+        let first_modifier_optional = cfg.get_first_set(S("Modifier__Optional"));
+        for terminal in ["*", "+", "?", "+?", "*?", "{"] {
+            assert!(first_contains(&first_modifier_optional, terminal));
+        }
+        assert!(first_modifier_optional.contains(0));
+
+        // Modifier__Optional is nullable, so FIRST(Modifier__Optional Hat__Optional) also contains "^"
+        let first_word = cfg.get_first_set_of_word(&[S("Modifier__Optional"), S("Hat__Optional")]);
+        assert!(first_contains(&first_word, "^"));
+        assert!(first_word.contains(0));
+
+        let first_word = cfg.get_first_set_of_word(&[S("Modifier__Optional"), S("List"), S("Hat__Optional")]);
+        assert!(first_contains(&first_word, "["));
+        assert!(!first_contains(&first_word, "^"));
+        assert!(!first_word.contains(0));
+
+        let first_terminal = cfg.get_first_set(S("int"));
+        assert!(first_contains(&first_terminal, "int"));
+        assert_eq!(first_terminal.len(), 1);
+
+        assert!(cfg.get_first_set_of_word(&[]).contains(0));
+    }
+
+    #[test]
+    fn follow_and_predict_sets(){
+        let cfg = create_regex_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+
+        println!("{}", cfg.repr_follow_sets());
+        println!("{}", cfg.repr_predict_sets());
+
+        assert_eq!(cfg.repr_optional_terminal_set(cfg.get_follow_set(S("Sequence"))), "{END, ), |}");
+        assert_eq!(cfg.repr_optional_terminal_set(cfg.get_follow_set(S("Expression"))), "{END, )}");
+        assert_eq!(cfg.repr_optional_terminal_set(cfg.get_follow_set(S("START"))), "{END}");
+
+        let union_extend_rules: Vec<_> = cfg.get_rules_by_origin(S("Union__Extend")).map(|(rule_id, _)| rule_id).collect();
+        assert_eq!(cfg.repr_optional_terminal_set(cfg.get_predict_set(union_extend_rules[0])), "{|}");
+        assert_eq!(cfg.repr_optional_terminal_set(cfg.get_predict_set(union_extend_rules[1])), "{END, )}");
+
+        // the grammar is LL(1): the predict sets of rules with the same origin are disjoint
+        for non_terminal in cfg.all_non_terminals() {
+            let rules: Vec<_> = cfg.get_rules_by_origin(non_terminal).map(|(rule_id, _)| rule_id).collect();
+            for (i, &rule1) in rules.iter().enumerate() {
+                for &rule2 in &rules[i+1..] {
+                    assert!(cfg.get_predict_set(rule1).is_disjoint(cfg.get_predict_set(rule2)),
+                        "{} and {} conflict", cfg.repr_rule(rule1), cfg.repr_rule(rule2));
+                }
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,141 @@
+use crate::formal_language::*;
+use crate::parsing::general_parsing::Earley_parser::EarleyParser;
+use crate::parsing::parse_tree::ParseTree;
+
+
+/// builds a small grammar from representations, START and END can be used in the rules
+pub fn create_grammar(terminals: &[&str], non_terminals: &[&str], rules: &[(&str, &[&str])]) -> Cfg {
+
+    let terminals: SymbolSet = SymbolSet::new(terminals.iter().map(|&s| String::from(s)).collect());
+    let non_terminals: SymbolSet = SymbolSet::new(non_terminals.iter().map(|&s| String::from(s)).collect());
+
+    let cfg_symbol_set = CfgSymbolSet::new(terminals, non_terminals);
+
+    let S = |s: &str| cfg_symbol_set.get_symbol_by_representation(s);
+
+    let rules: Vec<CfgRule> = rules
+        .iter()
+        .map(|(origin, replacement)| CfgRule {
+            origin: S(origin),
+            replacement: replacement.iter().map(|&s| S(s)).collect(),
+        })
+        .collect();
+
+    Cfg::new(cfg_symbol_set, rules).unwrap()
+}
+
+/// one token per representation, on the same line with a column per token
+pub fn tokens(cfg: &Cfg, token_types: &[&str]) -> Vec<Token> {
+    token_types
+        .iter()
+        .enumerate()
+        .map(|(column, &token_type)| Token {
+            token_type: cfg.symbol_set().get_symbol_by_representation(token_type),
+            lexeme: String::from(token_type),
+            line: 0,
+            column,
+        })
+        .collect()
+}
+
+/// the parse tree of a transformed grammar given back in the original grammar by rewrite, and the parse tree of
+/// the original grammar, both found by the Earley parser and printed with the rules of the original grammar
+pub fn rewritten_and_expected_trees(original: &Cfg, transformed: &Cfg, rewrite: impl Fn(&ParseTree) -> ParseTree, input: &[&str])
+-> (String, String) {
+    let parse_tree: ParseTree = EarleyParser::new(transformed)
+        .parse_from_iter(tokens(transformed, input).into_iter())
+        .unwrap()
+        .to_parse_tree()
+        .unwrap();
+    let expected: ParseTree = EarleyParser::new(original)
+        .parse_from_iter(tokens(original, input).into_iter())
+        .unwrap()
+        .to_parse_tree()
+        .unwrap();
+    (rewrite(&parse_tree).repr(original), expected.repr(original))
+}
+
+/// E -> E + T | T, T -> T * F | F, F -> ( E ) | id
+pub fn create_expression_grammar() -> Cfg {
+    create_grammar(
+        &["+", "*", "(", ")", "id"],
+        &["E", "T", "F"],
+        &[
+            ("START", &["E", "END"]),
+            ("E", &["E", "+", "T"]),
+            ("E", &["T"]),
+            ("T", &["T", "*", "F"]),
+            ("T", &["F"]),
+            ("F", &["(", "E", ")"]),
+            ("F", &["id"]),
+        ],
+    )
+}
+
+/// S -> a A a | b A b a, A -> b | EPS is LL(2) but not strong LL(2)
+pub fn create_LL2_grammar() -> Cfg {
+    create_grammar(
+        &["a", "b"],
+        &["S", "A"],
+        &[
+            ("START", &["S", "END"]),
+            ("S", &["a", "A", "a"]),
+            ("S", &["b", "A", "b", "a"]),
+            ("A", &["b"]),
+            ("A", &[]),
+        ],
+    )
+}
+
+/// E -> E + E | id is ambiguous, id + id + id has two parse trees
+pub fn create_ambiguous_sum_grammar() -> Cfg {
+    create_grammar(
+        &["+", "id"],
+        &["E"],
+        &[
+            ("START", &["E", "END"]),
+            ("E", &["E", "+", "E"]),
+            ("E", &["id"]),
+        ],
+    )
+}
+
+/// S -> A A A, A -> a | EPS: the a can be derived by any of the 3 A
+pub fn create_nullable_grammar() -> Cfg {
+    create_grammar(
+        &["a"],
+        &["S", "A"],
+        &[
+            ("START", &["S", "END"]),
+            ("S", &["A", "A", "A"]),
+            ("A", &["a"]),
+            ("A", &[]),
+        ],
+    )
+}
+
+/// S -> if cond then S | if cond then S else S | instr, the else can belong to several if
+pub fn create_dangling_else_grammar() -> Cfg {
+    create_grammar(
+        &["if", "then", "else", "cond", "instr"],
+        &["S"],
+        &[
+            ("START", &["S", "END"]),
+            ("S", &["if", "cond", "then", "S"]),
+            ("S", &["if", "cond", "then", "S", "else", "S"]),
+            ("S", &["instr"]),
+        ],
+    )
+}
+
+/// START -> a a END | EPS: the empty input is accepted without reading END
+pub fn create_nullable_start_grammar() -> Cfg {
+    create_grammar(
+        &["a"],
+        &[],
+        &[
+            ("START", &["a", "a", "END"]),
+            ("START", &[]),
+        ],
+    )
+}
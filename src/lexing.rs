@@ -1,5 +1,8 @@
 
+// the lexers aren't used by the parsers yet
+#[allow(dead_code)]
 mod dfa_lexer;
 mod stack_lexer;
-mod machine;
+pub mod machine;
+#[allow(dead_code)]
 mod finite_automaton;
@@ -128,6 +128,7 @@ where
     'dfa: 'dfa_lexer,
     'alp: 'dfa,
 {
+    #[allow(clippy::len_zero)]
     fn finish(&mut self) {
         // adds a token with the current lexeme
         // (this is usefull because it might have return a different token type if more chars were added to the lexeme) 
@@ -145,9 +146,9 @@ where
             // safe unwrap because final_state_id comes from dfa_runner.get_state() 
 
             let token_type_found: Symbol = match final_state_return_value {
-                ReturnValue::Accepted => self.dfa_lexer.error_symbol.clone(),
-                ReturnValue::NotAccepted => self.dfa_lexer.error_symbol.clone(),
-                ReturnValue::Value(output_value) => output_value.clone(),
+                ReturnValue::Accepted => self.dfa_lexer.error_symbol,
+                ReturnValue::NotAccepted => self.dfa_lexer.error_symbol,
+                ReturnValue::Value(output_value) => *output_value,
             };
 
             self.tokens.push(Token{
@@ -188,9 +189,9 @@ where
                     .return_value;
 
             let token_type_found: Symbol = match final_state_return_value {
-                ReturnValue::Accepted => self.dfa_lexer.error_symbol.clone(),
-                ReturnValue::NotAccepted => self.dfa_lexer.error_symbol.clone(),
-                ReturnValue::Value(output_value) => output_value.clone(),
+                ReturnValue::Accepted => self.dfa_lexer.error_symbol,
+                ReturnValue::NotAccepted => self.dfa_lexer.error_symbol,
+                ReturnValue::Value(output_value) => *output_value,
             };
 
             self.tokens.push(Token{
@@ -212,7 +213,7 @@ where
                 // we add an error token with this char only as the lexeme
 
                 self.tokens.push(Token{
-                    token_type: self.dfa_lexer.error_symbol.clone(),
+                    token_type: self.dfa_lexer.error_symbol,
                     lexeme: last_symbol.to_string(),
                     line: next_line,
                     column: next_column, 
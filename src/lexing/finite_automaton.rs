@@ -1,7 +1,6 @@
 pub mod dfa;
 pub mod nfa;
 
-use strum_macros::Display;
 
 use crate::datastructures::bitset::BitSet;
 
@@ -34,7 +33,7 @@ pub struct StateTransition {
 }
 
 #[derive(Debug, Clone)]
-struct StateTransitionSet
+pub struct StateTransitionSet
 {
     origin_state_id: usize,
     char_read: char,    // EPS is allowed
@@ -4,7 +4,7 @@ use super::super::machine::{Machine, RunInfo};
 use crate::{datastructures::option_uint::OptionUint, lexing::machine::MachineError};
 use crate::formal_language::Alphabet;
 
-use super::{ReturnValue, FiniteAutomatonState, StateTransition, SINT};
+use super::{FiniteAutomatonState, StateTransition, SINT};
 
 
 
@@ -78,13 +78,14 @@ impl <'alp, RETURN: Clone, DATA> Dfa<'alp, RETURN, DATA>
     }
 
     pub fn is_char_valid(&self, c: char) -> bool {
-        !self.char_id(c).is_none()
+        self.char_id(c).is_some()
     }
 
     pub fn is_state_id_valid(&self, state_id: usize) -> bool {
         state_id<self.nbr_states()
     }
 
+    #[allow(clippy::len_zero)]
     pub fn from_table(table: Vec<Vec<OptionUint<SINT>>>, states: Vec<FiniteAutomatonState<RETURN, DATA>>,
     alphabet: &'alp Alphabet) -> Result<Self, DfaError> {
 
@@ -129,13 +130,13 @@ impl <'alp, RETURN: Clone, DATA> Dfa<'alp, RETURN, DATA>
 
         // empty initial table
         let mut table: Vec<Vec<OptionUint<SINT>>> = 
-        vec![vec![OptionUint::from(None);nbr_chars.into()]; nbr_states.into()];
+        vec![vec![OptionUint::from(None);nbr_chars]; nbr_states];
 
         // checks each transition and adds an element to the table
         for transition in transitions {
             // checks char
             let opt_cher_id: Option<usize> = alphabet.id(transition.char_read);
-            if let None = opt_cher_id {
+            if opt_cher_id.is_none() {
                 return Err(DfaError::InvalidTransitionChar {transition});
             }
             let char_id: usize = opt_cher_id.unwrap();
@@ -217,7 +218,7 @@ where
 {
     pub fn new(dfa: &'dfa Dfa<'alp, RETURN, DATA>) -> Self {
         DfaRunner {
-            dfa: dfa,
+            dfa,
             current_state_id: 0,
             run_info: RunInfo::Ready,
         }
@@ -1,7 +1,7 @@
 use thiserror::Error;
 
-use super::{ReturnValue, FiniteAutomatonState, StateTransition, StateTransitionSet, SINT, UINT, EPS};
-use super::super::machine::{Machine, MachineError, RunInfo};
+use super::{FiniteAutomatonState, StateTransitionSet, SINT, UINT, EPS};
+use super::super::machine::MachineError;
 use crate::{datastructures::bitset::BitSet, formal_language::Alphabet};
 
 
@@ -85,13 +85,14 @@ impl <'alp, RETURN: Clone, DATA> Nfa<'alp, RETURN, DATA>
 
     pub fn is_char_valid(&self, c: char) -> bool {
         // returns false for EPS
-        !self.alphabet.id(c).is_none()
+        self.alphabet.id(c).is_some()
     }
 
     pub fn is_state_id_valid(&self, state_id: usize) -> bool {
         state_id<self.nbr_states()
     }
 
+    #[allow(clippy::len_zero)]
     pub fn from_table(table: Vec<Vec<BitSet<UINT>>>, states: Vec<FiniteAutomatonState<RETURN, DATA>>,
         alphabet: &'alp Alphabet) -> Result<Self, NfaError> {
 
@@ -142,11 +143,9 @@ impl <'alp, RETURN: Clone, DATA> Nfa<'alp, RETURN, DATA>
         for transition in transitions {
             // checks char
             let opt_cher_id: Option<usize> = alphabet.id(transition.char_read);
-            if let None = opt_cher_id {
+            if opt_cher_id.is_none() {
                 return Err(NfaError::InvalidTransitionChar {transition});
             }
-            let char_id: usize = opt_cher_id.unwrap();
-
             let char_id: usize = {
                 if transition.char_read == EPS {nbr_chars}
                 else if let Some(char_id) = alphabet.id(transition.char_read) {char_id}
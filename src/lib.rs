@@ -0,0 +1,13 @@
+// grammar notation: START, END, LR1_parser, ... are kept as in the litterature
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod grammars;
+pub mod parsing;
+pub mod lexing;
+pub mod datastructures;
+pub mod formal_language;
+
+
+pub type UINT = u8; // used for Bitsets, might slightly affect performance and memory usage?
@@ -1,14 +1,3 @@
-mod grammars;
-mod parsing;
-mod lexing;
-mod datastructures;
-mod formal_language;
-
-use strum::{EnumCount, IntoEnumIterator};
-use strum_macros::{EnumCount as EnumCountMacro, EnumIter};
-
-type UINT = u8; // used for Bitsets, might slightly affect performance and memory usage?
-
 // use std::cell::OnceCell;
 
 // struct Graph {
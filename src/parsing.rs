@@ -1,3 +1,108 @@
+pub mod LL_parsing;
+pub mod LR_parsing;
+pub mod general_parsing;
+pub mod parse_tree;
+pub mod semantic_actions;
+pub mod sppf;
+pub mod counterexamples;
 
-mod LL_parsing;
-mod LR_parsing;
\ No newline at end of file
+use std::error::Error;
+
+use thiserror::Error;
+
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use parse_tree::*;
+use semantic_actions::*;
+
+
+/// error of the parsers reading a token stream
+#[derive(Error, Debug)]
+pub enum ParserError {
+    #[error("Unexpected token \"{}\" at line {}, column {}", token.lexeme, token.line, token.column)]
+    UnexpectedToken{token: Token, expected: Vec<Symbol>},
+
+    #[error("The token \"{}\" at line {}, column {} has a type which isn't a terminal symbol", token.lexeme, token.line, token.column)]
+    InvalidTokenType{token: Token},
+
+    /// the parsers which don't read the input from left to right only find out at the end
+    #[error("The tokens up to \"{}\" at line {}, column {} aren't a word of the language", token.lexeme, token.line, token.column)]
+    NotInLanguage{token: Token},
+
+    #[error("{} rules have no semantic action and no non-terminal to take the value of", rules.len())]
+    MissingSemanticActions{rules: Vec<CfgRuleIdx>},
+}
+
+impl ParserError {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        match self {
+            ParserError::UnexpectedToken { token, expected } => {
+                let expected: Vec<&str> = expected.iter().map(|&symbol| cfg.repr_symbol(symbol)).collect();
+                format!("{}: found {}, expected one of {{{}}}", self, cfg.repr_symbol(token.token_type), expected.join(", "))
+            }
+            ParserError::InvalidTokenType { .. } | ParserError::NotInLanguage { .. } => self.to_string(),
+            ParserError::MissingSemanticActions { rules } => {
+                let rules: Vec<String> = rules.iter().map(|&rule_id| cfg.repr_rule(rule_id)).collect();
+                format!("{}: {}", self, rules.join(", "))
+            }
+        }
+    }
+}
+
+impl From<ParserError> for MachineError<ParserError> {
+    fn from(value: ParserError) -> Self {
+        MachineError::Other { other_err: value }
+    }
+}
+
+/// feeds the whole token stream to the runner, then END tokens placed after the last token until the runner is
+/// finished: none if the stream ends with END, one for most parsers and up to k with a lookahead of k tokens
+pub fn run_until_end<M, S, E, IT>(cfg: &Cfg, runner: &mut M, token_stream: IT) -> Result<(), MachineError<E>>
+where
+    M: Machine<Token, S, E>,
+    E: Error,
+    MachineError<E>: From<E>,
+    IT: Iterator<Item = Token>,
+{
+    let mut next_position: (usize, usize) = (0, 0);
+
+    for token in token_stream {
+        next_position = token.next_position();
+        runner.update(&token)?;
+    }
+
+    while !runner.is_finished() {
+        runner.update(&Token {
+            token_type: cfg.END(),
+            lexeme: String::new(),
+            line: next_position.0,
+            column: next_position.1,
+        })?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------
+
+/// deterministic parsers giving the tokens and the rules they recognize to a ParseBuilder
+pub trait BuildingParser {
+
+    /// parses the whole token stream (see run_until_end), giving the tokens and the rules to the builder
+    fn build_from_iter<B: ParseBuilder, IT: Iterator<Item = Token>>(&self, token_stream: IT, builder: B)
+    -> Result<B, MachineError<ParserError>>;
+
+    /// parses the whole token stream, END tokens are added if the stream doesn't end with one
+    fn parse_from_iter<IT: Iterator<Item = Token>>(&self, token_stream: IT)
+    -> Result<ParseTree, MachineError<ParserError>> {
+        self.build_from_iter(token_stream, ParseTree::new())
+    }
+
+    /// parses the whole token stream and gives the value computed by the semantic actions for its rule of START
+    /// fails before reading any token if some rules can't get a value
+    fn parse_with_actions<V, IT: Iterator<Item = Token>>(&self, token_stream: IT, actions: &SemanticActions<V>)
+    -> Result<V, MachineError<ParserError>> {
+        let evaluator: SemanticEvaluator<'_, V> = self.build_from_iter(token_stream, SemanticEvaluator::new(actions)?)?;
+        // unwrap is safe because the input was accepted, so the rule of START was reduced
+        Ok(evaluator.into_value().unwrap())
+    }
+}
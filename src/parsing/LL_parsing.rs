@@ -1,5 +1,85 @@
+pub mod LL1_parser;
+pub mod LL1_generator;
+pub mod LLk_parser;
+pub mod LLk_generator;
 
-mod LL1_parser;
-mod LL1_generator;
-mod LLk_parser;
-mod LLk_generator;
\ No newline at end of file
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use crate::parsing::parse_tree::*;
+use crate::parsing::ParserError;
+
+
+/// rules being derived by the stack of an LL runner, with the values of the symbols of their replacement derived so
+/// far: the rules are given to the builder once their whole replacement is derived
+struct DerivationFrames<B: ParseBuilder> {
+    // the last one is the rule of the symbol on top of the stack
+    frames: Vec<(CfgRuleIdx, Vec<B::Value>)>,
+    builder: B,
+}
+
+impl <B: ParseBuilder> DerivationFrames<B> {
+
+    fn new(builder: B) -> Self {
+        DerivationFrames { frames: Vec::new(), builder }
+    }
+
+    fn clear(&mut self) {
+        self.frames.clear();
+        self.builder.clear();
+    }
+
+    fn builder(&self) -> &B {
+        &self.builder
+    }
+
+    fn into_builder(self) -> B {
+        self.builder
+    }
+
+    /// starts the derivation of the replacement of the rule, an empty replacement is derived right away
+    fn expand(&mut self, cfg: &Cfg, rule_id: CfgRuleIdx) {
+        let rule: &CfgRule = cfg.get_rule_by_id(rule_id);
+        if rule.replacement.is_empty() {
+            let value: B::Value = self.builder.reduce(rule_id, rule.origin, Vec::new());
+            self.add_value(cfg, value);
+        }
+        else {
+            self.frames.push((rule_id, Vec::with_capacity(rule.replacement_size())));
+        }
+    }
+
+    /// the token matches the next symbol of the current rule
+    fn shift(&mut self, cfg: &Cfg, token: &Token) {
+        let value: B::Value = self.builder.shift(token);
+        self.add_value(cfg, value);
+    }
+
+    /// gives the value of a derived symbol to its rule, and reduces the rules whose replacement is then derived
+    fn add_value(&mut self, cfg: &Cfg, value: B::Value) {
+        let mut value: B::Value = value;
+        loop {
+            let Some((rule_id, children)) = self.frames.last_mut() else {
+                self.builder.accept(value);
+                return;
+            };
+            children.push(value);
+
+            let rule: &CfgRule = cfg.get_rule_by_id(*rule_id);
+            if children.len() < rule.replacement_size() {
+                return;
+            }
+            // unwrap is safe because the frame was just found
+            let (rule_id, children) = self.frames.pop().unwrap();
+            value = self.builder.reduce(rule_id, rule.origin, children);
+        }
+    }
+}
+
+/// once the stack of an LL runner is empty, START is derived and only END can be read
+/// the stack can be emptied by the expansions before END is matched when START has a rule without END
+fn check_end_of_input(cfg: &Cfg, token: &Token) -> Result<(), MachineError<ParserError>> {
+    if token.token_type == cfg.END() {
+        return Ok(());
+    }
+    Err(ParserError::UnexpectedToken { token: token.clone(), expected: vec![cfg.END()] }.into())
+}
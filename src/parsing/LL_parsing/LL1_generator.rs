@@ -0,0 +1,216 @@
+use thiserror::Error;
+
+use crate::datastructures::flat_table::RectFlatTable;
+use crate::formal_language::*;
+use crate::parsing::counterexamples::CounterexampleGenerator;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LL1ConflictKind {
+    // the lookahead is in the first set of both replacements
+    FirstFirst,
+    // at least one of the replacements is nullable and predicts the lookahead through the follow set of the origin
+    FirstFollow,
+}
+
+#[derive(Debug, Clone)]
+pub struct LL1Conflict {
+    pub non_terminal: Symbol,
+    pub lookahead: Symbol,
+    pub rule1: CfgRuleIdx,
+    pub rule2: CfgRuleIdx,
+    pub kind: LL1ConflictKind,
+}
+
+impl LL1Conflict {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        format!("{:?} conflict on {} with lookahead {}: {} | {}",
+            self.kind,
+            cfg.repr_symbol(self.non_terminal),
+            cfg.repr_symbol(self.lookahead),
+            cfg.repr_rule(self.rule1),
+            cfg.repr_rule(self.rule2))
+    }
+}
+
+
+#[derive(Error, Debug)]
+pub enum LL1GeneratorError {
+    #[error("The grammar is not LL(1), {} conflicts found", conflicts.len())]
+    NotLL1{conflicts: Vec<LL1Conflict>},
+}
+
+impl LL1GeneratorError {
+    /// each conflict followed by a counterexample
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        match self {
+            LL1GeneratorError::NotLL1 { conflicts } => {
+                let generator: CounterexampleGenerator = CounterexampleGenerator::new(cfg);
+                let mut s: String = format!("{self}\n");
+                for conflict in conflicts {
+                    s.push_str(&conflict.repr(cfg));
+                    s.push('\n');
+                    match generator.ll1_counterexample(conflict) {
+                        Some(counterexample) => s.push_str(&counterexample.repr(cfg)),
+                        None => s.push_str("no derivation reaches the conflict with this lookahead\n"),
+                    }
+                }
+                s
+            }
+        }
+    }
+}
+
+// --------------------------------------------
+
+/// predict table of an LL(1) grammar
+pub struct LL1Table {
+    // table[non_terminal][local terminal] = rule to apply when non_terminal is on top of the stack
+    // and the terminal is read
+    table: RectFlatTable<Option<CfgRuleIdx>, u16>,
+    nbr_non_terminals: SymbolIdx,
+}
+
+impl LL1Table {
+
+    pub fn get_rule(&self, non_terminal: Symbol, terminal: Symbol) -> Option<CfgRuleIdx> {
+        self.table[non_terminal.id.0][usize::from(terminal.id - self.nbr_non_terminals)]
+    }
+
+    /// the terminals for which a rule can be applied when non_terminal is on top of the stack
+    pub fn expected_terminals(&self, non_terminal: Symbol) -> impl Iterator<Item = Symbol> + '_ {
+        self.table[non_terminal.id.0]
+            .iter()
+            .enumerate()
+            .filter(|(_, rule_id)| rule_id.is_some())
+            .map(|(local_id, _)| Symbol { id: SymbolIdx::from(local_id) + self.nbr_non_terminals })
+    }
+
+    /// one line per (non-terminal, terminal) entry that isn't empty
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for non_terminal in cfg.all_non_terminals() {
+            for terminal in self.expected_terminals(non_terminal) {
+                s.push_str(&format!("{}, {}: {}\n",
+                    cfg.repr_symbol(non_terminal),
+                    cfg.repr_symbol(terminal),
+                    cfg.repr_rule(self.get_rule(non_terminal, terminal).unwrap())));
+            }
+        }
+        s
+    }
+}
+
+
+/// builds the predict table of the grammar from its PREDICT sets
+/// returns every conflict found if the grammar is not LL(1)
+pub fn generate_LL1_table(cfg: &Cfg) -> Result<LL1Table, LL1GeneratorError> {
+
+    let height: usize = usize::from(cfg.nbr_non_terminals());
+    let width: usize = usize::from(cfg.nbr_terminals());
+
+    let mut table: Vec<Option<CfgRuleIdx>> = vec![None; height*width];
+    let mut conflicts: Vec<LL1Conflict> = Vec::new();
+
+    for (rule_id, rule) in cfg.all_rules() {
+        for local_id in cfg.get_predict_set(rule_id) {
+            // unwrap is safe because NOSYMBOL is never in a predict set
+            let lookahead: Symbol = cfg.terminal_from_local_nosymbol(SymbolIdx::from(local_id)).unwrap();
+            let cell: &mut Option<CfgRuleIdx> =
+                &mut table[usize::from(rule.origin.id)*width + usize::from(cfg.to_local(lookahead))];
+
+            match *cell {
+                None => *cell = Some(rule_id),
+                Some(other_rule_id) => {
+                    let is_in_first = |rule_id: CfgRuleIdx|
+                        cfg.get_first_set_of_word(&cfg.get_rule_by_id(rule_id).replacement).contains(local_id);
+
+                    let kind: LL1ConflictKind = if is_in_first(other_rule_id) && is_in_first(rule_id) {
+                        LL1ConflictKind::FirstFirst
+                    }
+                    else {
+                        LL1ConflictKind::FirstFollow
+                    };
+
+                    conflicts.push(LL1Conflict {
+                        non_terminal: rule.origin,
+                        lookahead,
+                        rule1: other_rule_id,
+                        rule2: rule_id,
+                        kind,
+                    });
+                }
+            }
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(LL1GeneratorError::NotLL1 { conflicts });
+    }
+
+    Ok(LL1Table {
+        table: RectFlatTable::new(table, u16::try_from(height).unwrap(), width),
+        nbr_non_terminals: cfg.nbr_non_terminals(),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::regex_grammar::create_regex_grammar;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar};
+
+    #[test]
+    fn regex_grammar_is_LL1() {
+        let cfg = create_regex_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+
+        let table = generate_LL1_table(&cfg).unwrap();
+        println!("{}", table.repr(&cfg));
+
+        let rule_id = table.get_rule(S("Union__Extend"), S(")")).unwrap();
+        assert_eq!(cfg.repr_rule(rule_id), "Union__Extend -->");
+        let rule_id = table.get_rule(S("Item"), S("[")).unwrap();
+        assert_eq!(cfg.repr_rule(rule_id), "Item --> List");
+        assert_eq!(table.get_rule(S("Item"), S("]")), None);
+        // "^" and the 14 terminals that can start a ListSequence
+        assert_eq!(table.expected_terminals(S("Hat__Optional")).count(), 15);
+    }
+
+    #[test]
+    fn left_recursion_conflicts() {
+        let cfg = create_expression_grammar();
+
+        let Err(LL1GeneratorError::NotLL1 { conflicts }) = generate_LL1_table(&cfg) else {
+            panic!("the expression grammar is left-recursive");
+        };
+        for conflict in &conflicts {
+            println!("{}", conflict.repr(&cfg));
+            assert_eq!(conflict.kind, LL1ConflictKind::FirstFirst);
+        }
+        // E and T conflict on ( and id
+        assert_eq!(conflicts.len(), 4);
+    }
+
+    #[test]
+    fn first_follow_conflict() {
+        // A -> a | EPS followed by a
+        let cfg = create_grammar(
+            &["a"],
+            &["A"],
+            &[
+                ("START", &["A", "a", "END"]),
+                ("A", &["a"]),
+                ("A", &[]),
+            ],
+        );
+
+        let Err(LL1GeneratorError::NotLL1 { conflicts }) = generate_LL1_table(&cfg) else {
+            panic!("the grammar is not LL(1)");
+        };
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, LL1ConflictKind::FirstFollow);
+        assert_eq!(conflicts[0].repr(&cfg), "FirstFollow conflict on A with lookahead a: A --> a | A -->");
+    }
+}
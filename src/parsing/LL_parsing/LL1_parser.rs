@@ -0,0 +1,235 @@
+use super::LL1_generator::LL1Table;
+use super::{check_end_of_input, DerivationFrames};
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use crate::parsing::parse_tree::*;
+use crate::parsing::{run_until_end, BuildingParser, ParserError};
+
+
+// --------------------------------------------
+
+pub struct LL1Parser<'a> {
+    cfg: &'a Cfg,
+    table: &'a LL1Table,
+}
+
+impl <'a> LL1Parser<'a> {
+
+    pub fn new(cfg: &'a Cfg, table: &'a LL1Table) -> Self {
+        LL1Parser { cfg, table }
+    }
+
+    pub fn get_cfg(&self) -> &'a Cfg {
+        self.cfg
+    }
+}
+
+impl <'a> BuildingParser for LL1Parser<'a> {
+
+    fn build_from_iter<B: ParseBuilder, IT: Iterator<Item = Token>>(&self, token_stream: IT, builder: B)
+    -> Result<B, MachineError<ParserError>> {
+        let mut runner: LL1ParserRunner<'_, 'a, B> = LL1ParserRunner::with_builder(self, builder);
+        run_until_end(self.cfg, &mut runner, token_stream)?;
+        Ok(runner.frames.into_builder())
+    }
+}
+
+
+/// predictive stack parser, reads one token at each update
+/// the rules are given to the builder once their whole replacement is derived, so get_state gives the subtrees
+/// (or the values) of the rules completed so far
+pub struct LL1ParserRunner<'parser, 'a, B = ParseTree>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    parser: &'parser LL1Parser<'a>,
+
+    // symbols to derive
+    stack: Vec<Symbol>,
+    // rules being derived, with the builder they are given to
+    frames: DerivationFrames<B>,
+
+    run_info: RunInfo,
+}
+
+impl <'parser, 'a> LL1ParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    pub fn new(parser: &'parser LL1Parser<'a>) -> Self {
+        LL1ParserRunner::with_builder(parser, ParseTree::new())
+    }
+}
+
+impl <'parser, 'a, B> LL1ParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    pub fn with_builder(parser: &'parser LL1Parser<'a>, builder: B) -> Self {
+        LL1ParserRunner {
+            parser,
+            stack: vec![parser.cfg.START()],
+            frames: DerivationFrames::new(builder),
+            run_info: RunInfo::Ready,
+        }
+    }
+
+    fn unexpected_token(&self, token: &Token, expected: Vec<Symbol>) -> MachineError<ParserError> {
+        MachineError::Other { other_err: ParserError::UnexpectedToken { token: token.clone(), expected } }
+    }
+
+    /// replaces the non-terminal on top of the stack by the replacement of the rule
+    fn expand(&mut self, rule_id: CfgRuleIdx) {
+        let rule: &CfgRule = self.parser.cfg.get_rule_by_id(rule_id);
+        self.stack.pop();
+        self.stack.extend(rule.replacement.iter().rev());
+
+        self.frames.expand(self.parser.cfg, rule_id);
+    }
+}
+
+impl <'parser, 'a, B> Machine<Token, B, ParserError> for LL1ParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    fn clear(&mut self) {
+        self.stack = vec![self.parser.cfg.START()];
+        self.frames.clear();
+        self.run_info = RunInfo::Ready;
+    }
+
+    fn get_run_info(&self) -> &RunInfo {
+        &self.run_info
+    }
+
+    fn update(&mut self, token: &Token) -> Result<(), MachineError<ParserError>> {
+        if self.is_finished() {
+            return Err(MachineError::Finished);
+        }
+
+        let cfg: &Cfg = self.parser.cfg;
+        if token.token_type.id >= cfg.nbr_symbols() || !cfg.is_terminal(token.token_type) {
+            return Err(ParserError::InvalidTokenType { token: token.clone() }.into());
+        }
+
+        self.run_info = RunInfo::Running;
+
+        // expands the non-terminals on top of the stack until a terminal can be matched with the token
+        loop {
+            let Some(&symbol) = self.stack.last() else {
+                check_end_of_input(cfg, token)?;
+                self.run_info = RunInfo::Finished;
+                return Ok(());
+            };
+
+            if cfg.is_terminal(symbol) {
+                if symbol != token.token_type {
+                    return Err(self.unexpected_token(token, vec![symbol]));
+                }
+
+                self.stack.pop();
+                self.frames.shift(cfg, token);
+
+                if self.stack.is_empty() {
+                    self.run_info = RunInfo::Finished;
+                }
+                return Ok(());
+            }
+
+            let Some(rule_id) = self.parser.table.get_rule(symbol, token.token_type) else {
+                return Err(self.unexpected_token(token, self.parser.table.expected_terminals(symbol).collect()));
+            };
+
+            self.expand(rule_id);
+        }
+    }
+
+    fn get_state(&self) -> &B {
+        self.frames.builder()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::regex_grammar::create_regex_grammar;
+    use crate::grammars::test_grammars::{create_nullable_start_grammar, tokens};
+    use crate::parsing::LL_parsing::LL1_generator::generate_LL1_table;
+
+    #[test]
+    fn parse_regex() {
+        let cfg = create_regex_grammar();
+        let table = generate_LL1_table(&cfg).unwrap();
+        let parser = LL1Parser::new(&cfg, &table);
+
+        // (a|b)*[^[:digit:]x]
+        let parse_tree = parser.parse_from_iter(tokens(&cfg,
+            &["(", "char", "|", "char", ")", "*", "[", "^", "[:digit:]", "list_char", "]"]).into_iter()).unwrap();
+        println!("{}", parse_tree.repr(&cfg));
+
+        let root = parse_tree.get_node(parse_tree.root().unwrap());
+        let ParseTreeNodeKind::Inner { rule_id, .. } = root.kind else { panic!("the root should be an inner node") };
+        assert_eq!(cfg.repr_rule(rule_id), "START --> Expression END");
+
+        let nbr_leaves: usize = (0..parse_tree.nbr_nodes())
+            .filter(|&node_id| matches!(parse_tree.get_node(node_id).kind, ParseTreeNodeKind::Leaf { .. }))
+            .count();
+        assert_eq!(nbr_leaves, 12);
+    }
+
+    #[test]
+    fn syntax_error() {
+        let cfg = create_regex_grammar();
+        let table = generate_LL1_table(&cfg).unwrap();
+        let parser = LL1Parser::new(&cfg, &table);
+
+        let result = parser.parse_from_iter(tokens(&cfg, &["char", "|", ")"]).into_iter());
+        let Err(MachineError::Other { other_err: err @ ParserError::UnexpectedToken { .. } }) = result else {
+            panic!("the parse should fail on )");
+        };
+        println!("{}", err.repr(&cfg));
+        let ParserError::UnexpectedToken { token, expected } = err else { unreachable!() };
+        assert_eq!(token.column, 2);
+        assert!(expected.contains(&cfg.symbol_set().get_symbol_by_representation("char")));
+        assert!(!expected.contains(&cfg.END()));
+    }
+
+    #[test]
+    fn token_by_token() {
+        let cfg = create_regex_grammar();
+        let table = generate_LL1_table(&cfg).unwrap();
+        let parser = LL1Parser::new(&cfg, &table);
+        let mut runner = LL1ParserRunner::new(&parser);
+
+        assert!(runner.is_ready());
+        for token in tokens(&cfg, &["char", "+?", "END"]) {
+            assert!(!runner.is_finished());
+            runner.update(&token).unwrap();
+        }
+        assert!(runner.is_finished());
+        assert!(matches!(runner.update(&tokens(&cfg, &["char"])[0]), Err(MachineError::Finished)));
+
+        runner.clear();
+        assert!(runner.is_ready());
+        runner.update(&tokens(&cfg, &["END"])[0]).unwrap();
+        assert!(runner.is_finished());
+        assert_eq!(runner.get_state().nbr_nodes(), 2);
+    }
+
+    #[test]
+    fn epsilon_rule_of_START() {
+        let cfg = create_nullable_start_grammar();
+        let table = generate_LL1_table(&cfg).unwrap();
+        let parser = LL1Parser::new(&cfg, &table);
+
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &[]).into_iter()).unwrap();
+        assert_eq!(parse_tree.repr(&cfg), "START -->\n");
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &["a", "a"]).into_iter()).unwrap();
+        assert!(parse_tree.repr(&cfg).starts_with("START --> a a END\n"));
+        assert!(parser.parse_from_iter(tokens(&cfg, &["a"]).into_iter()).is_err());
+    }
+}
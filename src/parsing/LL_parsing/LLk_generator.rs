@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::formal_language::k_sets::*;
+use crate::formal_language::*;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LLkMode {
+    // the rule only depends on the non-terminal and the lookahead, using the FOLLOW_k sets
+    Strong,
+    // the rule also depends on the local follow set, the words that can follow this occurrence of the non-terminal
+    Full,
+}
+
+
+/// a non-terminal with the words of k terminals that can follow it
+#[derive(Debug, Clone)]
+pub struct LLkContext {
+    pub non_terminal: Symbol,
+    // FOLLOW_k(non_terminal) in strong mode, the local follow set in full mode
+    pub follow_set: TerminalWordSet,
+}
+
+#[derive(Debug, Clone)]
+pub struct LLkConflict {
+    pub non_terminal: Symbol,
+    pub follow_set: Vec<Vec<Symbol>>,
+    pub lookahead: Vec<Symbol>,
+    pub rule1: CfgRuleIdx,
+    pub rule2: CfgRuleIdx,
+}
+
+impl LLkConflict {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let repr_word = |word: &[Symbol]| -> String {
+            word.iter().map(|&symbol| cfg.repr_symbol(symbol)).collect::<Vec<&str>>().join(" ")
+        };
+        let follow_set: Vec<String> = self.follow_set.iter().map(|word| repr_word(word)).collect();
+        format!("conflict on {} followed by {{{}}} with lookahead \"{}\": {} | {}",
+            cfg.repr_symbol(self.non_terminal),
+            follow_set.join(", "),
+            repr_word(&self.lookahead),
+            cfg.repr_rule(self.rule1),
+            cfg.repr_rule(self.rule2))
+    }
+}
+
+
+#[derive(Error, Debug)]
+pub enum LLkGeneratorError {
+    #[error("The grammar is not {mode:?} LL({k}), {} conflicts found", conflicts.len())]
+    NotLLk{k: usize, mode: LLkMode, conflicts: Vec<LLkConflict>},
+    #[error("LL(0) tables have no lookahead")]
+    NoLookahead,
+}
+
+impl LLkGeneratorError {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        match self {
+            LLkGeneratorError::NotLLk { conflicts, .. } => {
+                let mut s: String = format!("{self}\n");
+                for conflict in conflicts {
+                    s.push_str(&conflict.repr(cfg));
+                    s.push('\n');
+                }
+                s
+            }
+            LLkGeneratorError::NoLookahead => format!("{self}\n"),
+        }
+    }
+}
+
+// --------------------------------------------
+
+/// predict table of an LL(k) grammar, indexed by contexts (non-terminal, follow set) and lookahead words
+/// the lookahead words have exactly k terminals, the input being padded with END
+pub struct LLkTable {
+    k: usize,
+    mode: LLkMode,
+    // the lookahead words of the predictions
+    interner: TerminalWordInterner,
+
+    contexts: Vec<LLkContext>,
+    // predictions[context][lookahead] = rule to apply
+    predictions: Vec<HashMap<TerminalWordIdx, CfgRuleIdx>>,
+    // successor_contexts[context][rule] = the context of each symbol of the replacement, None for terminals
+    successor_contexts: Vec<HashMap<CfgRuleIdx, Vec<Option<usize>>>>,
+    start_context: usize,
+}
+
+impl LLkTable {
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn mode(&self) -> LLkMode {
+        self.mode
+    }
+
+    pub fn nbr_contexts(&self) -> usize {
+        self.contexts.len()
+    }
+
+    pub fn get_context(&self, context_id: usize) -> &LLkContext {
+        &self.contexts[context_id]
+    }
+
+    /// the context of START, followed by END^k
+    pub fn start_context(&self) -> usize {
+        self.start_context
+    }
+
+    pub fn get_rule(&self, context_id: usize, lookahead: &[Symbol]) -> Option<CfgRuleIdx> {
+        self.interner
+            .get_id(lookahead)
+            .and_then(|word_id| self.predictions[context_id].get(&word_id).copied())
+    }
+
+    /// the contexts of the symbols of the replacement of a rule applied in the context
+    pub fn get_successor_contexts(&self, context_id: usize, rule_id: CfgRuleIdx) -> &[Option<usize>] {
+        &self.successor_contexts[context_id][&rule_id]
+    }
+
+    /// the lookahead words for which a rule can be applied in the context
+    pub fn expected_lookaheads(&self, context_id: usize) -> impl Iterator<Item = &[Symbol]> + '_ {
+        self.predictions[context_id].keys().map(|&word_id| self.interner.get_word(word_id))
+    }
+
+    /// one line per (context, lookahead) entry
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for (context_id, context) in self.contexts.iter().enumerate() {
+            s.push_str(&format!("{} {}:\n", cfg.repr_symbol(context.non_terminal),
+                self.interner.repr_word_set(cfg, &context.follow_set)));
+            let predictions: BTreeMap<String, CfgRuleIdx> = self.predictions[context_id]
+                .iter()
+                .map(|(&word_id, &rule_id)| (self.interner.repr_word(cfg, word_id), rule_id))
+                .collect();
+            for (lookahead, rule_id) in predictions {
+                s.push_str(&format!("    {}: {}\n", lookahead, cfg.repr_rule(rule_id)));
+            }
+        }
+        s
+    }
+}
+
+
+/// builds the predict table of the grammar from its PREDICT_k sets (strong mode)
+/// or from the local follow sets of each occurrence of the non-terminals (full mode)
+/// returns every conflict found if the grammar is not (strong) LL(k)
+pub fn generate_LLk_table(cfg: &Cfg, k: usize, mode: LLkMode) -> Result<LLkTable, LLkGeneratorError> {
+    if k == 0 {
+        return Err(LLkGeneratorError::NoLookahead);
+    }
+
+    let k_sets: Rc<KSets> = cfg.get_k_sets(k);
+    let end_word: TerminalWordIdx = k_sets.intern(&vec![cfg.END(); k]);
+
+    let mut contexts: Vec<LLkContext> = Vec::new();
+    let mut context_ids: HashMap<(Symbol, TerminalWordSet), usize> = HashMap::new();
+    let start_context: usize = match mode {
+        LLkMode::Strong => {
+            // one context per non-terminal, with the same id
+            for non_terminal in cfg.all_non_terminals() {
+                contexts.push(LLkContext { non_terminal, follow_set: k_sets.follow_k_set(non_terminal).clone() });
+            }
+            usize::from(cfg.START().id)
+        }
+        LLkMode::Full => {
+            let follow_set: TerminalWordSet = TerminalWordSet::from([end_word]);
+            context_ids.insert((cfg.START(), follow_set.clone()), 0);
+            contexts.push(LLkContext { non_terminal: cfg.START(), follow_set });
+            0
+        }
+    };
+
+    let mut predictions: Vec<HashMap<TerminalWordIdx, CfgRuleIdx>> = Vec::new();
+    let mut successor_contexts: Vec<HashMap<CfgRuleIdx, Vec<Option<usize>>>> = Vec::new();
+    let mut conflicts: Vec<LLkConflict> = Vec::new();
+
+    // contexts are processed in the order of creation, new ones are only created in full mode
+    let mut context_id: usize = 0;
+    while context_id < contexts.len() {
+        let non_terminal: Symbol = contexts[context_id].non_terminal;
+        let follow_set: TerminalWordSet = contexts[context_id].follow_set.clone();
+
+        let mut context_predictions: HashMap<TerminalWordIdx, CfgRuleIdx> = HashMap::new();
+        let mut context_successors: HashMap<CfgRuleIdx, Vec<Option<usize>>> = HashMap::new();
+
+        for (rule_id, rule) in cfg.get_rules_by_origin(non_terminal) {
+            let predict_set: TerminalWordSet = match mode {
+                LLkMode::Strong => k_sets.predict_k_set(rule_id).clone(),
+                LLkMode::Full => k_sets.concatenate_sets(&k_sets.first_k_set_of_word(&rule.replacement), &follow_set),
+            };
+
+            for word_id in predict_set {
+                if let Some(&other_rule_id) = context_predictions.get(&word_id) {
+                    conflicts.push(LLkConflict {
+                        non_terminal,
+                        follow_set: follow_set.iter().map(|&word_id| k_sets.get_word(word_id)).collect(),
+                        lookahead: k_sets.get_word(word_id),
+                        rule1: other_rule_id,
+                        rule2: rule_id,
+                    });
+                    continue;
+                }
+                context_predictions.insert(word_id, rule_id);
+            }
+
+            let mut rule_successors: Vec<Option<usize>> = Vec::with_capacity(rule.replacement_size());
+            for (position, &symbol) in rule.replacement.iter().enumerate() {
+                if cfg.is_terminal(symbol) {
+                    rule_successors.push(None);
+                    continue;
+                }
+                match mode {
+                    LLkMode::Strong => rule_successors.push(Some(usize::from(symbol.id))),
+                    LLkMode::Full => {
+                        let local_follow_set: TerminalWordSet = k_sets.concatenate_sets(
+                            &k_sets.first_k_set_of_word(&rule.replacement[position+1..]), &follow_set);
+                        let successor_id: usize = *context_ids
+                            .entry((symbol, local_follow_set.clone()))
+                            .or_insert_with(|| {
+                                contexts.push(LLkContext { non_terminal: symbol, follow_set: local_follow_set });
+                                contexts.len()-1
+                            });
+                        rule_successors.push(Some(successor_id));
+                    }
+                }
+            }
+            context_successors.insert(rule_id, rule_successors);
+        }
+
+        predictions.push(context_predictions);
+        successor_contexts.push(context_successors);
+        context_id += 1;
+    }
+
+    if !conflicts.is_empty() {
+        return Err(LLkGeneratorError::NotLLk { k, mode, conflicts });
+    }
+
+    Ok(LLkTable {
+        k,
+        mode,
+        interner: k_sets.interner(),
+        contexts,
+        predictions,
+        successor_contexts,
+        start_context,
+    })
+}
+
+/// the table for the smallest k <= max_k such that the grammar is (strong) LL(k)
+/// returns the conflicts for max_k if there is none
+pub fn generate_LLk_table_auto(cfg: &Cfg, max_k: usize, mode: LLkMode) -> Result<LLkTable, LLkGeneratorError> {
+    if max_k == 0 {
+        return Err(LLkGeneratorError::NoLookahead);
+    }
+
+    for k in 1..max_k {
+        if let Ok(table) = generate_LLk_table(cfg, k, mode) {
+            return Ok(table);
+        }
+    }
+    generate_LLk_table(cfg, max_k, mode)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::regex_grammar::create_regex_grammar;
+    use crate::grammars::test_grammars::create_LL2_grammar;
+
+    #[test]
+    fn strong_and_full_LL2() {
+        let cfg = create_LL2_grammar();
+
+        let Err(err @ LLkGeneratorError::NotLLk { .. }) = generate_LLk_table(&cfg, 2, LLkMode::Strong) else {
+            panic!("the grammar is not strong LL(2)");
+        };
+        println!("{}", err.repr(&cfg));
+        let LLkGeneratorError::NotLLk { conflicts, .. } = &err else { unreachable!() };
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].repr(&cfg),
+            "conflict on A followed by {b a, a END} with lookahead \"b a\": A --> b | A -->");
+
+        let table = generate_LLk_table(&cfg, 2, LLkMode::Full).unwrap();
+        println!("{}", table.repr(&cfg));
+        // START, S, and A followed by a or by b a
+        assert_eq!(table.nbr_contexts(), 4);
+    }
+
+    #[test]
+    fn auto_mode() {
+        let cfg = create_regex_grammar();
+        let table = generate_LLk_table_auto(&cfg, 3, LLkMode::Strong).unwrap();
+        assert_eq!(table.k(), 1);
+
+        let cfg = create_LL2_grammar();
+        let table = generate_LLk_table_auto(&cfg, 3, LLkMode::Full).unwrap();
+        assert_eq!(table.k(), 2);
+        let Err(LLkGeneratorError::NotLLk { k, .. }) = generate_LLk_table_auto(&cfg, 3, LLkMode::Strong) else {
+            panic!("the grammar is strong LL(3)");
+        };
+        assert_eq!(k, 3);
+
+        assert!(matches!(generate_LLk_table(&cfg, 0, LLkMode::Full), Err(LLkGeneratorError::NoLookahead)));
+        assert!(matches!(generate_LLk_table_auto(&cfg, 0, LLkMode::Full), Err(LLkGeneratorError::NoLookahead)));
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::VecDeque;
+
+use super::LLk_generator::LLkTable;
+use super::{check_end_of_input, DerivationFrames};
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use crate::parsing::parse_tree::*;
+use crate::parsing::{run_until_end, BuildingParser, ParserError};
+
+
+pub struct LLkParser<'a> {
+    cfg: &'a Cfg,
+    table: &'a LLkTable,
+}
+
+impl <'a> LLkParser<'a> {
+
+    pub fn new(cfg: &'a Cfg, table: &'a LLkTable) -> Self {
+        LLkParser { cfg, table }
+    }
+
+    pub fn get_cfg(&self) -> &'a Cfg {
+        self.cfg
+    }
+}
+
+impl <'a> BuildingParser for LLkParser<'a> {
+
+    fn build_from_iter<B: ParseBuilder, IT: Iterator<Item = Token>>(&self, token_stream: IT, builder: B)
+    -> Result<B, MachineError<ParserError>> {
+        let mut runner: LLkParserRunner<'_, 'a, B> = LLkParserRunner::with_builder(self, builder);
+        run_until_end(self.cfg, &mut runner, token_stream)?;
+        Ok(runner.frames.into_builder())
+    }
+}
+
+
+/// predictive stack parser, reads one token at each update into a window of k tokens,
+/// and expands or matches the top of the stack once the window is full
+/// the rules are given to the builder once their whole replacement is matched, so get_state gives the subtrees
+/// (or the values) of the rules completed so far
+pub struct LLkParserRunner<'parser, 'a, B = ParseTree>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    parser: &'parser LLkParser<'a>,
+
+    // symbols to derive, with the context of the table for non-terminals
+    stack: Vec<(Symbol, Option<usize>)>,
+    // rules being derived, with the builder they are given to
+    frames: DerivationFrames<B>,
+    // the tokens read but not matched yet
+    lookahead_window: VecDeque<Token>,
+
+    run_info: RunInfo,
+}
+
+impl <'parser, 'a> LLkParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    pub fn new(parser: &'parser LLkParser<'a>) -> Self {
+        LLkParserRunner::with_builder(parser, ParseTree::new())
+    }
+}
+
+impl <'parser, 'a, B> LLkParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    pub fn with_builder(parser: &'parser LLkParser<'a>, builder: B) -> Self {
+        LLkParserRunner {
+            parser,
+            stack: vec![(parser.cfg.START(), Some(parser.table.start_context()))],
+            frames: DerivationFrames::new(builder),
+            lookahead_window: VecDeque::with_capacity(parser.table.k()),
+            run_info: RunInfo::Ready,
+        }
+    }
+
+    /// replaces the non-terminal on top of the stack by the replacement of the rule, with their contexts
+    fn expand(&mut self, context_id: usize, rule_id: CfgRuleIdx) {
+        let rule: &CfgRule = self.parser.cfg.get_rule_by_id(rule_id);
+        let successor_contexts: &[Option<usize>] = self.parser.table.get_successor_contexts(context_id, rule_id);
+        self.stack.pop();
+        self.stack.extend(rule.replacement.iter().copied().zip(successor_contexts.iter().copied()).rev());
+
+        self.frames.expand(self.parser.cfg, rule_id);
+    }
+
+    /// the first token of the window that isn't the prefix of a lookahead expected in the context,
+    /// with the terminals that could have been at its place
+    fn unexpected_lookahead(&self, context_id: usize) -> MachineError<ParserError> {
+        let window: Vec<Symbol> = self.lookahead_window.iter().map(|token| token.token_type).collect();
+        let common_prefix_size = |lookahead: &[Symbol]|
+            lookahead.iter().zip(&window).take_while(|(symbol1, symbol2)| symbol1 == symbol2).count();
+
+        let error_position: usize = self.parser.table
+            .expected_lookaheads(context_id)
+            .map(common_prefix_size)
+            .max()
+            .unwrap_or(0);
+        let mut expected: Vec<Symbol> = self.parser.table
+            .expected_lookaheads(context_id)
+            .filter(|lookahead| common_prefix_size(lookahead) == error_position)
+            .map(|lookahead| lookahead[error_position])
+            .collect();
+        expected.sort();
+        expected.dedup();
+
+        MachineError::Other { other_err: ParserError::UnexpectedToken {
+            token: self.lookahead_window[error_position].clone(),
+            expected,
+        }}
+    }
+}
+
+impl <'parser, 'a, B> Machine<Token, B, ParserError> for LLkParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    fn clear(&mut self) {
+        self.stack = vec![(self.parser.cfg.START(), Some(self.parser.table.start_context()))];
+        self.frames.clear();
+        self.lookahead_window.clear();
+        self.run_info = RunInfo::Ready;
+    }
+
+    fn get_run_info(&self) -> &RunInfo {
+        &self.run_info
+    }
+
+    fn update(&mut self, token: &Token) -> Result<(), MachineError<ParserError>> {
+        if self.is_finished() {
+            return Err(MachineError::Finished);
+        }
+
+        let cfg: &Cfg = self.parser.cfg;
+        if token.token_type.id >= cfg.nbr_symbols() || !cfg.is_terminal(token.token_type) {
+            return Err(ParserError::InvalidTokenType { token: token.clone() }.into());
+        }
+
+        self.run_info = RunInfo::Running;
+
+        self.lookahead_window.push_back(token.clone());
+        if self.lookahead_window.len() < self.parser.table.k() {
+            return Ok(());
+        }
+
+        let lookahead: Vec<Symbol> = self.lookahead_window.iter().map(|token| token.token_type).collect();
+
+        // expands the non-terminals on top of the stack until a terminal can be matched with the first token
+        loop {
+            let Some(&(symbol, context)) = self.stack.last() else {
+                check_end_of_input(cfg, &self.lookahead_window[0])?;
+                self.lookahead_window.clear();
+                self.run_info = RunInfo::Finished;
+                return Ok(());
+            };
+
+            if cfg.is_terminal(symbol) {
+                if symbol != lookahead[0] {
+                    return Err(MachineError::Other { other_err: ParserError::UnexpectedToken {
+                        token: self.lookahead_window[0].clone(),
+                        expected: vec![symbol],
+                    }});
+                }
+
+                self.stack.pop();
+                // unwrap is safe because the window is full
+                let matched_token: Token = self.lookahead_window.pop_front().unwrap();
+                self.frames.shift(cfg, &matched_token);
+
+                if self.stack.is_empty() {
+                    self.lookahead_window.clear();
+                    self.run_info = RunInfo::Finished;
+                }
+                return Ok(());
+            }
+
+            // unwrap is safe because non-terminals are always pushed with their context
+            let context_id: usize = context.unwrap();
+            let Some(rule_id) = self.parser.table.get_rule(context_id, &lookahead) else {
+                return Err(self.unexpected_lookahead(context_id));
+            };
+
+            self.expand(context_id, rule_id);
+        }
+    }
+
+    fn get_state(&self) -> &B {
+        self.frames.builder()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::regex_grammar::create_regex_grammar;
+    use crate::grammars::test_grammars::{create_LL2_grammar, create_nullable_start_grammar, tokens};
+    use crate::parsing::LL_parsing::LL1_generator::generate_LL1_table;
+    use crate::parsing::LL_parsing::LL1_parser::LL1Parser;
+    use crate::parsing::LL_parsing::LLk_generator::{generate_LLk_table, LLkMode};
+
+    #[test]
+    fn parse_LL2() {
+        let cfg = create_LL2_grammar();
+        let table = generate_LLk_table(&cfg, 2, LLkMode::Full).unwrap();
+        let parser = LLkParser::new(&cfg, &table);
+
+        // A -> b after a, A -> EPS after b
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &["a", "b", "a"]).into_iter()).unwrap();
+        assert!(parse_tree.repr(&cfg).contains("A --> b\n"));
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &["b", "b", "a"]).into_iter()).unwrap();
+        println!("{}", parse_tree.repr(&cfg));
+        assert!(parse_tree.repr(&cfg).contains("A -->\n"));
+
+        let result = parser.parse_from_iter(tokens(&cfg, &["b", "a", "a"]).into_iter());
+        let Err(MachineError::Other { other_err: err @ ParserError::UnexpectedToken { .. } }) = result else {
+            panic!("the parse should fail on the first a");
+        };
+        assert_eq!(err.repr(&cfg), "Unexpected token \"a\" at line 0, column 1: found a, expected one of {b}");
+    }
+
+    #[test]
+    fn epsilon_rule_of_START() {
+        let cfg = create_nullable_start_grammar();
+        let table = generate_LLk_table(&cfg, 2, LLkMode::Full).unwrap();
+        let parser = LLkParser::new(&cfg, &table);
+
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &[]).into_iter()).unwrap();
+        assert_eq!(parse_tree.repr(&cfg), "START -->\n");
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &["a", "a"]).into_iter()).unwrap();
+        assert!(parse_tree.repr(&cfg).starts_with("START --> a a END\n"));
+        assert!(parser.parse_from_iter(tokens(&cfg, &["a"]).into_iter()).is_err());
+    }
+
+    #[test]
+    fn same_tree_as_LL1() {
+        let cfg = create_regex_grammar();
+        let input: Vec<Token> = tokens(&cfg, &["(", "char", "|", "char", ")", "*", "[", "^", "[:digit:]", "list_char", "]"]);
+
+        let ll1_table = generate_LL1_table(&cfg).unwrap();
+        let expected: String = LL1Parser::new(&cfg, &ll1_table).parse_from_iter(input.clone().into_iter()).unwrap().repr(&cfg);
+
+        for (k, mode) in [(1, LLkMode::Strong), (2, LLkMode::Strong), (2, LLkMode::Full)] {
+            let table = generate_LLk_table(&cfg, k, mode).unwrap();
+            let parse_tree = LLkParser::new(&cfg, &table).parse_from_iter(input.clone().into_iter()).unwrap();
+            assert_eq!(parse_tree.repr(&cfg), expected);
+        }
+    }
+}
@@ -1,5 +1,5 @@
+pub mod LR1_generators;
 
-mod LR1_generators;
-
-mod LR1_parser;
-mod LRk_parser;
\ No newline at end of file
+pub mod LR1_parser;
+pub mod LRk_parser;
+pub mod GLR_parser;
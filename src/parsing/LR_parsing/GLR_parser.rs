@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+
+use super::LR1_generators::{LR1Action, LR1Table};
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use crate::parsing::{run_until_end, ParserError};
+use crate::parsing::sppf::*;
+
+
+/// Tomita parser: follows every action of the table, including the ones in conflict, by sharing the stacks
+/// in a graph-structured stack
+/// any LR table can be used, the LR(0) and LALR(1) ones being the usual choices
+pub struct GLRParser<'a> {
+    cfg: &'a Cfg,
+    table: &'a LR1Table,
+}
+
+impl <'a> GLRParser<'a> {
+
+    pub fn new(cfg: &'a Cfg, table: &'a LR1Table) -> Self {
+        GLRParser { cfg, table }
+    }
+
+    pub fn get_cfg(&self) -> &'a Cfg {
+        self.cfg
+    }
+
+    pub fn get_table(&self) -> &'a LR1Table {
+        self.table
+    }
+
+    /// parses the whole token stream, an END token is added if the stream doesn't end with one
+    /// the forest contains every parse of the tokens
+    pub fn parse_from_iter<IT: Iterator<Item = Token>>(&self, token_stream: IT)
+    -> Result<Sppf, MachineError<ParserError>> {
+        let mut runner: GLRParserRunner<'_, 'a> = GLRParserRunner::new(self);
+        run_until_end(self.cfg, &mut runner, token_stream)?;
+        Ok(runner.sppf)
+    }
+}
+
+
+/// reduction of a rule from a stack top, restricted to the paths going through an edge when one is given
+struct PendingReduction {
+    node_id: usize,
+    rule_id: CfgRuleIdx,
+    through: Option<(usize, usize)>,
+}
+
+
+/// node of the graph-structured stack: a state of the automaton entered after reading level tokens
+/// each edge goes to a node below, with the node of the forest of the symbol read between them
+#[derive(Debug, Clone)]
+pub struct GssNode {
+    pub state_id: usize,
+    pub level: usize,
+    pub edges: Vec<(usize, usize)>,
+}
+
+
+/// reads one token at each update: does every reduction possible for it on all the stacks, then shifts it
+/// the forest is built along, its root is only set when the input is accepted
+pub struct GLRParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    parser: &'parser GLRParser<'a>,
+
+    gss: Vec<GssNode>,
+    // state -> node of the stack tops at the current level
+    frontier: HashMap<usize, usize>,
+
+    sppf: Sppf,
+
+    run_info: RunInfo,
+}
+
+impl <'parser, 'a> GLRParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    pub fn new(parser: &'parser GLRParser<'a>) -> Self {
+        GLRParserRunner {
+            parser,
+            gss: vec![GssNode { state_id: 0, level: 0, edges: Vec::new() }],
+            frontier: HashMap::from([(0, 0)]),
+            sppf: Sppf::new(Vec::new()),
+            run_info: RunInfo::Ready,
+        }
+    }
+
+    pub fn get_gss_node(&self, node_id: usize) -> &GssNode {
+        &self.gss[node_id]
+    }
+
+    /// the nodes of the stack tops, sorted by state
+    pub fn get_stack_tops(&self) -> Vec<usize> {
+        let mut stack_tops: Vec<(usize, usize)> = self.frontier.iter().map(|(&state_id, &node_id)| (state_id, node_id)).collect();
+        stack_tops.sort();
+        stack_tops.into_iter().map(|(_, node_id)| node_id).collect()
+    }
+
+    fn level(&self) -> usize {
+        self.sppf.tokens().len()
+    }
+
+    /// a rule of START has been reduced over every token read
+    fn is_accepted(&self) -> bool {
+        self.sppf.root().is_some_and(|root_id| self.sppf.get_node(root_id).end == self.level())
+    }
+
+    fn can_shift(&self, terminal: Symbol) -> bool {
+        self.frontier.keys().any(|&state_id| self.parser.table
+            .get_all_actions(state_id, terminal)
+            .iter()
+            .any(|action| matches!(action, LR1Action::Shift(_))))
+    }
+
+    fn unexpected_token(&self, token: &Token) -> MachineError<ParserError> {
+        let mut expected: Vec<Symbol> = self.frontier
+            .keys()
+            .flat_map(|&state_id| self.parser.table.expected_terminals(state_id))
+            .collect();
+        expected.sort();
+        expected.dedup();
+        MachineError::Other { other_err: ParserError::UnexpectedToken { token: token.clone(), expected } }
+    }
+
+    /// every path of the given length going down from the node, as the node reached and the forest nodes
+    /// of the edges from the bottom to the top
+    /// if an edge is given, only the paths going through it are kept
+    fn paths(&self, node_id: usize, length: usize, through: Option<(usize, usize)>) -> Vec<(usize, Vec<usize>)> {
+        // (current node, forest nodes from the top, has gone through the edge)
+        let mut paths: Vec<(usize, Vec<usize>, bool)> = vec![(node_id, Vec::new(), through.is_none())];
+        for _ in 0..length {
+            paths = paths
+                .into_iter()
+                .flat_map(|(current_id, children, has_gone_through)| {
+                    self.gss[current_id].edges.iter().map(move |&(target_id, sppf_node_id)| {
+                        let mut children: Vec<usize> = children.clone();
+                        children.push(sppf_node_id);
+                        (target_id, children, has_gone_through || through == Some((current_id, target_id)))
+                    })
+                })
+                .collect();
+        }
+        paths
+            .into_iter()
+            .filter(|(_, _, has_gone_through)| *has_gone_through)
+            .map(|(target_id, mut children, _)| {
+                children.reverse();
+                (target_id, children)
+            })
+            .collect()
+    }
+
+    /// the rules that can be reduced in the state with the lookahead
+    fn reductions(&self, state_id: usize, lookahead: Symbol) -> Vec<CfgRuleIdx> {
+        self.parser.table
+            .get_all_actions(state_id, lookahead)
+            .into_iter()
+            .filter_map(|action| match action {
+                LR1Action::Reduce(rule_id) | LR1Action::Accept(rule_id) => Some(rule_id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// does every reduction possible for the lookahead on the stack tops
+    /// when an edge is added to a stack top whose reductions were already done, the reductions going through
+    /// the new edge are done for all the processed stack tops
+    fn reduce_all(&mut self, lookahead: Symbol) {
+        let cfg: &Cfg = self.parser.cfg;
+        let level: usize = self.level();
+
+        let mut to_process: Vec<usize> = self.get_stack_tops();
+        let mut processed: Vec<usize> = Vec::new();
+        let mut pending: Vec<PendingReduction> = Vec::new();
+
+        loop {
+            if pending.is_empty() {
+                let Some(node_id) = to_process.pop() else {
+                    break;
+                };
+                processed.push(node_id);
+                for rule_id in self.reductions(self.gss[node_id].state_id, lookahead) {
+                    pending.push(PendingReduction { node_id, rule_id, through: None });
+                }
+                continue;
+            }
+
+            // unwrap is safe because pending isn't empty
+            let PendingReduction { node_id, rule_id, through } = pending.pop().unwrap();
+            let rule: &CfgRule = cfg.get_rule_by_id(rule_id);
+            // the reductions of empty rules don't go through any edge
+            if through.is_some() && rule.replacement_size() == 0 {
+                continue;
+            }
+
+            for (bottom_id, children) in self.paths(node_id, rule.replacement_size(), through) {
+                let bottom_level: usize = self.gss[bottom_id].level;
+                let sppf_node_id: usize = self.sppf.add_node(rule.origin, bottom_level, level);
+                self.sppf.add_family(sppf_node_id, rule_id, children);
+
+                if rule.origin == cfg.START() {
+                    if bottom_level == 0 {
+                        self.sppf.set_root(sppf_node_id);
+                    }
+                    continue;
+                }
+
+                // unwrap is safe because reductions are only possible in states where the origin was expected
+                let target_state: usize = self.parser.table.get_goto(self.gss[bottom_id].state_id, rule.origin).unwrap();
+                match self.frontier.get(&target_state) {
+                    None => {
+                        self.gss.push(GssNode { state_id: target_state, level, edges: vec![(bottom_id, sppf_node_id)] });
+                        let target_id: usize = self.gss.len()-1;
+                        self.frontier.insert(target_state, target_id);
+                        to_process.push(target_id);
+                    }
+                    Some(&target_id) => {
+                        if self.gss[target_id].edges.iter().any(|&(edge_target_id, _)| edge_target_id == bottom_id) {
+                            continue;
+                        }
+                        self.gss[target_id].edges.push((bottom_id, sppf_node_id));
+                        if processed.contains(&target_id) {
+                            for &processed_id in &processed {
+                                for rule_id in self.reductions(self.gss[processed_id].state_id, lookahead) {
+                                    pending.push(PendingReduction { node_id: processed_id, rule_id, through: Some((target_id, bottom_id)) });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// moves every stack top that can shift the token to the next level
+    fn shift_all(&mut self, token: &Token) -> HashMap<usize, usize> {
+        let level: usize = self.level();
+        let sppf_node_id: usize = self.sppf.add_token(token.clone());
+
+        let mut new_frontier: HashMap<usize, usize> = HashMap::new();
+        for node_id in self.get_stack_tops() {
+            for action in self.parser.table.get_all_actions(self.gss[node_id].state_id, token.token_type) {
+                let LR1Action::Shift(target_state) = action else {
+                    continue;
+                };
+                match new_frontier.get(&target_state) {
+                    None => {
+                        self.gss.push(GssNode { state_id: target_state, level: level+1, edges: vec![(node_id, sppf_node_id)] });
+                        new_frontier.insert(target_state, self.gss.len()-1);
+                    }
+                    Some(&target_id) => self.gss[target_id].edges.push((node_id, sppf_node_id)),
+                }
+            }
+        }
+        new_frontier
+    }
+}
+
+impl <'parser, 'a> Machine<Token, Sppf, ParserError> for GLRParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    fn clear(&mut self) {
+        self.gss = vec![GssNode { state_id: 0, level: 0, edges: Vec::new() }];
+        self.frontier = HashMap::from([(0, 0)]);
+        self.sppf = Sppf::new(Vec::new());
+        self.run_info = RunInfo::Ready;
+    }
+
+    fn get_run_info(&self) -> &RunInfo {
+        &self.run_info
+    }
+
+    fn update(&mut self, token: &Token) -> Result<(), MachineError<ParserError>> {
+        if self.is_finished() {
+            return Err(MachineError::Finished);
+        }
+
+        let cfg: &Cfg = self.parser.cfg;
+        if token.token_type.id >= cfg.nbr_symbols() || !cfg.is_terminal(token.token_type) {
+            return Err(ParserError::InvalidTokenType { token: token.clone() }.into());
+        }
+
+        self.run_info = RunInfo::Running;
+
+        self.reduce_all(token.token_type);
+
+        // a rule of START that doesn't end with END, like START -> ε, is reduced before END, which can't be shifted
+        if token.token_type == cfg.END() && self.is_accepted() && !self.can_shift(cfg.END()) {
+            self.run_info = RunInfo::Finished;
+            return Ok(());
+        }
+
+        let new_frontier: HashMap<usize, usize> = self.shift_all(token);
+        if new_frontier.is_empty() {
+            return Err(self.unexpected_token(token));
+        }
+        self.frontier = new_frontier;
+
+        // nothing can be read after END, so the rules of START are reduced right away
+        if token.token_type == cfg.END() {
+            self.reduce_all(cfg.END());
+            if self.sppf.root().is_none() {
+                return Err(self.unexpected_token(token));
+            }
+            self.run_info = RunInfo::Finished;
+        }
+
+        Ok(())
+    }
+
+    fn get_state(&self) -> &Sppf {
+        &self.sppf
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::grammars::test_grammars::{create_ambiguous_sum_grammar, create_expression_grammar, create_nullable_grammar, create_nullable_start_grammar, tokens};
+    use crate::parsing::general_parsing::Earley_parser::EarleyParser;
+    use crate::parsing::LR_parsing::LR1_generators::LR0_generator::generate_LR0_table;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+
+    /// the non-terminal nodes reachable from the root with their families, independently of the order of the nodes
+    /// the LR(0) table makes the parser build some nodes which aren't part of any parse, they are left out
+    fn forest_shape(cfg: &Cfg, sppf: &Sppf) -> BTreeSet<String> {
+        let mut shape: BTreeSet<String> = BTreeSet::new();
+        let mut visited: BTreeSet<usize> = BTreeSet::new();
+        let mut to_visit: Vec<usize> = sppf.root().into_iter().collect();
+        while let Some(node_id) = to_visit.pop() {
+            if !visited.insert(node_id) {
+                continue;
+            }
+            for family in &sppf.get_node(node_id).families {
+                let children: Vec<String> = family.children.iter().map(|&child_id| sppf.repr_node(cfg, child_id)).collect();
+                shape.insert(format!("{} = {}: {}", sppf.repr_node(cfg, node_id), cfg.repr_rule(family.rule_id), children.join(" ")));
+                to_visit.extend(&family.children);
+            }
+        }
+        shape
+    }
+
+    #[test]
+    fn ambiguous_sum() {
+        let cfg = create_ambiguous_sum_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let input: Vec<Token> = tokens(&cfg, &["id", "+", "id", "+", "id"]);
+        let earley_sppf = EarleyParser::new(&cfg).parse_from_iter(input.clone().into_iter()).unwrap();
+
+        // the stacks split on the conflicts and the forest is the same as the one of the Earley parser
+        for table in [generate_LR0_table(&cfg), generate_LALR1_table(&cfg)] {
+            assert!(table.has_conflicts());
+            let sppf = GLRParser::new(&cfg, &table).parse_from_iter(input.clone().into_iter()).unwrap();
+            println!("{}", sppf.repr(&cfg));
+            assert_eq!(sppf.root(), sppf.find_node(S("START"), 0, 6));
+            assert_eq!(forest_shape(&cfg, &sppf), forest_shape(&cfg, &earley_sppf));
+        }
+    }
+
+    #[test]
+    fn deterministic_grammar() {
+        let cfg = create_expression_grammar();
+        let table = generate_LALR1_table(&cfg);
+        let parser = GLRParser::new(&cfg, &table);
+        let mut runner = GLRParserRunner::new(&parser);
+
+        for token in tokens(&cfg, &["(", "id", "+", "id", ")", "*", "id"]) {
+            runner.update(&token).unwrap();
+            // without conflicts there is a single stack
+            assert_eq!(runner.get_stack_tops().len(), 1);
+        }
+        runner.update(&tokens(&cfg, &["END"])[0]).unwrap();
+        assert!(runner.is_finished());
+        assert_eq!(runner.get_state().ambiguous_nodes().count(), 0);
+
+        let result = parser.parse_from_iter(tokens(&cfg, &["(", "id", "+", ")"]).into_iter());
+        let Err(MachineError::Other { other_err: err @ ParserError::UnexpectedToken { .. } }) = result else {
+            panic!("the parse should fail on the )");
+        };
+        assert_eq!(err.repr(&cfg), "Unexpected token \")\" at line 0, column 3: found ), expected one of {(, id}");
+
+        // the input ends too early
+        let result = parser.parse_from_iter(tokens(&cfg, &["id", "+"]).into_iter());
+        assert!(matches!(result, Err(MachineError::Other { other_err: ParserError::UnexpectedToken { .. } })));
+    }
+
+    #[test]
+    fn nullable_symbols() {
+        // the empty rules are reduced without going through any edge of the stacks
+        let cfg = create_nullable_grammar();
+        let table = generate_LR0_table(&cfg);
+        let parser = GLRParser::new(&cfg, &table);
+        let earley_parser = EarleyParser::new(&cfg);
+
+        for input in [&[][..], &["a"], &["a", "a", "a"]] {
+            let sppf = parser.parse_from_iter(tokens(&cfg, input).into_iter()).unwrap();
+            println!("{}", sppf.repr(&cfg));
+            let earley_sppf = earley_parser.parse_from_iter(tokens(&cfg, input).into_iter()).unwrap();
+            assert_eq!(forest_shape(&cfg, &sppf), forest_shape(&cfg, &earley_sppf));
+        }
+    }
+
+    #[test]
+    fn epsilon_rule_of_START() {
+        let cfg = create_nullable_start_grammar();
+        for table in [generate_LR0_table(&cfg), generate_LALR1_table(&cfg)] {
+            let parser = GLRParser::new(&cfg, &table);
+
+            let sppf = parser.parse_from_iter(tokens(&cfg, &[]).into_iter()).unwrap();
+            assert_eq!(sppf.to_parse_tree().unwrap().repr(&cfg), "START -->\n");
+            let sppf = parser.parse_from_iter(tokens(&cfg, &["a", "a"]).into_iter()).unwrap();
+            assert!(sppf.to_parse_tree().unwrap().repr(&cfg).starts_with("START --> a a END\n"));
+            assert!(parser.parse_from_iter(tokens(&cfg, &["a"]).into_iter()).is_err());
+        }
+    }
+}
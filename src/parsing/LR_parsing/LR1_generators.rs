@@ -1,6 +1,369 @@
 
-mod LR0_generator;
-mod SLR1_generator;
-mod LALR1_generator;
-mod CLR1_generator;
-mod IELR1_generator;
\ No newline at end of file
+pub mod LR0_generator;
+pub mod SLR1_generator;
+pub mod LALR1_generator;
+pub mod CLR1_generator;
+pub mod IELR1_generator;
+
+use std::collections::HashMap;
+
+use crate::datastructures::bitset::BitSet;
+use crate::datastructures::flat_table::RectFlatTable;
+use crate::formal_language::*;
+use crate::formal_language::precedence::*;
+use crate::parsing::counterexamples::CounterexampleGenerator;
+use crate::UINT;
+use LR0_generator::{Lr0Automaton, Lr0Item};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LR1Action {
+    Error,
+    // shifts the lookahead and goes to the state
+    Shift(usize),
+    Reduce(CfgRuleIdx),
+    // reduction of a rule of START when the lookahead is END
+    Accept(CfgRuleIdx),
+}
+
+impl LR1Action {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        match self {
+            LR1Action::Error => String::from("error"),
+            LR1Action::Shift(state_id) => format!("shift {state_id}"),
+            LR1Action::Reduce(rule_id) => format!("reduce {}", cfg.repr_rule(*rule_id)),
+            LR1Action::Accept(rule_id) => format!("accept {}", cfg.repr_rule(*rule_id)),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LR1ConflictKind {
+    ShiftReduce,
+    ReduceReduce,
+}
+
+/// several actions are possible in a state for the same lookahead
+#[derive(Debug, Clone)]
+pub struct LR1Conflict {
+    pub state_id: usize,
+    pub lookahead: Symbol,
+    // every possible action, the first one is the one kept in the table
+    pub actions: Vec<LR1Action>,
+    // the items of the state responsible for the actions
+    pub items: Vec<Lr0Item>,
+}
+
+impl LR1Conflict {
+    pub fn kind(&self) -> LR1ConflictKind {
+        if self.actions.iter().any(|action| matches!(action, LR1Action::Shift(_))) {
+            LR1ConflictKind::ShiftReduce
+        }
+        else {
+            LR1ConflictKind::ReduceReduce
+        }
+    }
+
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = format!("{:?} conflict in state {} on lookahead {}:\n",
+            self.kind(), self.state_id, cfg.repr_symbol(self.lookahead));
+        for item in &self.items {
+            if item.is_complete(cfg) {
+                s.push_str(&format!("    reduce: {}\n", cfg.repr_rule(item.rule_id)));
+            }
+            else {
+                s.push_str(&format!("    shift: {}\n", item.repr(cfg)));
+            }
+        }
+        s
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecedenceResolution {
+    Shift,
+    Reduce,
+    // %nonassoc: neither the shift nor the reduction is possible
+    Error,
+}
+
+/// a shift/reduce conflict resolved with the precedence of the lookahead and of the rule
+#[derive(Debug, Clone)]
+pub struct LR1ResolvedConflict {
+    pub state_id: usize,
+    pub lookahead: Symbol,
+    pub rule_id: CfgRuleIdx,
+    pub resolution: PrecedenceResolution,
+}
+
+impl LR1ResolvedConflict {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        format!("conflict in state {} between shift {} and reduce {} resolved as {:?}\n",
+            self.state_id, cfg.repr_symbol(self.lookahead), cfg.repr_rule(self.rule_id), self.resolution)
+    }
+}
+
+// --------------------------------------------
+
+/// ACTION and GOTO tables shared by every LR(1) generator
+/// when there are conflicts, shifts are preferred to reductions, and reductions by rules with
+/// a smaller id are preferred to the others, but every conflict is kept in `conflicts`
+/// when the grammar has a precedence table, the shift/reduce conflicts are first resolved like in yacc
+/// and kept in `resolved_conflicts` instead
+pub struct LR1Table {
+    // action_table[state][local terminal]
+    action_table: RectFlatTable<LR1Action, u16>,
+    // goto_table[state][non-terminal]
+    goto_table: RectFlatTable<Option<usize>, u16>,
+
+    nbr_non_terminals: SymbolIdx,
+
+    conflicts: Vec<LR1Conflict>,
+    // (state, lookahead) -> index in conflicts
+    conflict_ids: HashMap<(usize, Symbol), usize>,
+
+    resolved_conflicts: Vec<LR1ResolvedConflict>,
+}
+
+impl LR1Table {
+
+    pub fn nbr_states(&self) -> usize {
+        usize::from(self.action_table.height)
+    }
+
+    pub fn get_action(&self, state_id: usize, terminal: Symbol) -> LR1Action {
+        self.action_table[u16::try_from(state_id).unwrap()][usize::from(terminal.id - self.nbr_non_terminals)]
+    }
+
+    /// every possible action, including the ones discarded because of conflicts
+    pub fn get_all_actions(&self, state_id: usize, terminal: Symbol) -> Vec<LR1Action> {
+        match self.conflict_ids.get(&(state_id, terminal)) {
+            Some(&conflict_id) => self.conflicts[conflict_id].actions.clone(),
+            None => match self.get_action(state_id, terminal) {
+                LR1Action::Error => vec![],
+                action => vec![action],
+            },
+        }
+    }
+
+    pub fn get_goto(&self, state_id: usize, non_terminal: Symbol) -> Option<usize> {
+        self.goto_table[u16::try_from(state_id).unwrap()][usize::from(non_terminal.id)]
+    }
+
+    /// the terminals that don't lead to an error in the state
+    pub fn expected_terminals(&self, state_id: usize) -> impl Iterator<Item = Symbol> + '_ {
+        self.action_table[u16::try_from(state_id).unwrap()]
+            .iter()
+            .enumerate()
+            .filter(|(_, action)| **action != LR1Action::Error)
+            .map(|(local_id, _)| Symbol { id: SymbolIdx::from(local_id) + self.nbr_non_terminals })
+    }
+
+    pub fn conflicts(&self) -> &[LR1Conflict] {
+        &self.conflicts
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    pub fn repr_conflicts(&self, cfg: &Cfg) -> String {
+        self.conflicts.iter().map(|conflict| conflict.repr(cfg)).collect()
+    }
+
+    /// each conflict followed by a counterexample built from the LR(0) automaton of the grammar
+    pub fn repr_conflicts_with_counterexamples(&self, cfg: &Cfg) -> String {
+        let lr0_automaton: Lr0Automaton = Lr0Automaton::new(cfg);
+        let generator: CounterexampleGenerator = CounterexampleGenerator::new(cfg);
+
+        let mut s: String = String::new();
+        for conflict in &self.conflicts {
+            s.push_str(&conflict.repr(cfg));
+            match generator.lr1_counterexample(&lr0_automaton, conflict) {
+                Some(counterexample) => s.push_str(&counterexample.repr(cfg)),
+                None => s.push_str("no derivation reaches the conflict with this lookahead\n"),
+            }
+        }
+        s
+    }
+
+    /// the shift/reduce conflicts resolved by precedence
+    pub fn resolved_conflicts(&self) -> &[LR1ResolvedConflict] {
+        &self.resolved_conflicts
+    }
+
+    pub fn repr_resolved_conflicts(&self, cfg: &Cfg) -> String {
+        self.resolved_conflicts.iter().map(|conflict| conflict.repr(cfg)).collect()
+    }
+
+    /// one line per state and symbol that doesn't lead to an error
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for state_id in 0..self.nbr_states() {
+            s.push_str(&format!("state {state_id}:\n"));
+            for terminal in self.expected_terminals(state_id) {
+                s.push_str(&format!("    {}: {}\n", cfg.repr_symbol(terminal), self.get_action(state_id, terminal).repr(cfg)));
+            }
+            for non_terminal in cfg.all_non_terminals() {
+                if let Some(target_id) = self.get_goto(state_id, non_terminal) {
+                    s.push_str(&format!("    {}: goto {}\n", cfg.repr_symbol(non_terminal), target_id));
+                }
+            }
+        }
+        s
+    }
+}
+
+// --------------------------------------------
+
+/// an LR automaton whose states are sets of items with LR(0) cores
+pub trait LRAutomaton {
+    fn nbr_states(&self) -> usize;
+    // sorted by symbol
+    fn transitions(&self, state_id: usize) -> &[(Symbol, usize)];
+    // LR(0) cores of the items of the state
+    fn core_items(&self, state_id: usize) -> &[Lr0Item];
+}
+
+
+/// builds the ACTION and GOTO tables of an automaton from the lookaheads of its reductions
+/// lookaheads[state] = (rule, bitset of optional terminals indexed by to_local_nosymbol) for each complete item
+pub fn build_LR1_table(cfg: &Cfg, automaton: &impl LRAutomaton, lookaheads: &[Vec<(CfgRuleIdx, BitSet<UINT>)>])
+-> LR1Table {
+    let nbr_states: usize = automaton.nbr_states();
+    let nbr_terminals: usize = usize::from(cfg.nbr_terminals());
+    let nbr_non_terminals: usize = usize::from(cfg.nbr_non_terminals());
+
+    let mut action_table: Vec<LR1Action> = vec![LR1Action::Error; nbr_states*nbr_terminals];
+    let mut goto_table: Vec<Option<usize>> = vec![None; nbr_states*nbr_non_terminals];
+    let mut conflicts: Vec<LR1Conflict> = Vec::new();
+    let mut conflict_ids: HashMap<(usize, Symbol), usize> = HashMap::new();
+    let mut resolved_conflicts: Vec<LR1ResolvedConflict> = Vec::new();
+
+    for state_id in 0..nbr_states {
+
+        // every action possible for each terminal of the state
+        let mut actions: Vec<Vec<LR1Action>> = vec![Vec::new(); nbr_terminals];
+
+        for &(symbol, target_id) in automaton.transitions(state_id) {
+            if cfg.is_terminal(symbol) {
+                actions[usize::from(cfg.to_local(symbol))].push(LR1Action::Shift(target_id));
+            }
+            else {
+                goto_table[state_id*nbr_non_terminals + usize::from(symbol.id)] = Some(target_id);
+            }
+        }
+
+        for (rule_id, lookahead_set) in &lookaheads[state_id] {
+            let is_start_rule: bool = cfg.get_rule_by_id(*rule_id).origin == cfg.START();
+            for local_id in lookahead_set {
+                // unwrap is safe because NOSYMBOL is never a lookahead
+                let lookahead: Symbol = cfg.terminal_from_local_nosymbol(SymbolIdx::from(local_id)).unwrap();
+                let action: LR1Action = if is_start_rule && lookahead == cfg.END() {
+                    LR1Action::Accept(*rule_id)
+                }
+                else {
+                    LR1Action::Reduce(*rule_id)
+                };
+                let cell_actions: &mut Vec<LR1Action> = &mut actions[usize::from(cfg.to_local(lookahead))];
+                if !cell_actions.contains(&action) {
+                    cell_actions.push(action);
+                }
+            }
+        }
+
+        for (local_id, mut cell_actions) in actions.into_iter().enumerate() {
+            let lookahead: Symbol = Symbol { id: SymbolIdx::from(local_id) + cfg.nbr_non_terminals() };
+
+            if cell_actions.len() > 1 && let Some(precedence_table) = cfg.get_precedence_table() {
+                resolve_with_precedence(cfg, precedence_table, state_id, lookahead, &mut cell_actions, &mut resolved_conflicts);
+            }
+
+            if cell_actions.is_empty() {
+                continue;
+            }
+
+            if cell_actions.len() > 1 {
+                // shift first, then reductions by increasing rule id
+                cell_actions.sort_by_key(|action| match action {
+                    LR1Action::Shift(_) => (0, CfgRuleIdx(0)),
+                    LR1Action::Reduce(rule_id) | LR1Action::Accept(rule_id) => (1, *rule_id),
+                    LR1Action::Error => (2, CfgRuleIdx(0)),
+                });
+
+                let items: Vec<Lr0Item> = automaton
+                    .core_items(state_id)
+                    .iter()
+                    .filter(|item| match item.next_symbol(cfg) {
+                        Some(symbol) => symbol == lookahead,
+                        None => cell_actions.iter().any(|action| matches!(action,
+                            LR1Action::Reduce(rule_id) | LR1Action::Accept(rule_id) if *rule_id == item.rule_id)),
+                    })
+                    .copied()
+                    .collect();
+
+                conflict_ids.insert((state_id, lookahead), conflicts.len());
+                conflicts.push(LR1Conflict { state_id, lookahead, actions: cell_actions.clone(), items });
+            }
+
+            action_table[state_id*nbr_terminals + local_id] = cell_actions[0];
+        }
+    }
+
+    let height: u16 = u16::try_from(nbr_states).expect("the number of states must be storable in the u16 type");
+    LR1Table {
+        action_table: RectFlatTable::new(action_table, height, nbr_terminals),
+        goto_table: RectFlatTable::new(goto_table, height, nbr_non_terminals),
+        nbr_non_terminals: cfg.nbr_non_terminals(),
+        conflicts,
+        conflict_ids,
+        resolved_conflicts,
+    }
+}
+
+/// resolves the conflicts between the shift and each reduction of a cell when both the lookahead and the rule
+/// have a precedence: the higher level wins, and the associativity decides between equal levels
+fn resolve_with_precedence(cfg: &Cfg, precedence_table: &PrecedenceTable, state_id: usize, lookahead: Symbol,
+    cell_actions: &mut Vec<LR1Action>, resolved_conflicts: &mut Vec<LR1ResolvedConflict>) {
+
+    if !cell_actions.iter().any(|action| matches!(action, LR1Action::Shift(_))) {
+        return;
+    }
+    let Some(lookahead_precedence) = precedence_table.get_terminal_precedence(lookahead) else {
+        return;
+    };
+
+    let mut keeps_shift: bool = true;
+    cell_actions.retain(|action| {
+        let LR1Action::Reduce(rule_id) = *action else {
+            return true;
+        };
+        let Some(rule_precedence) = precedence_table.get_rule_precedence(cfg, rule_id) else {
+            return true;
+        };
+
+        let resolution: PrecedenceResolution = if rule_precedence.level > lookahead_precedence.level {
+            PrecedenceResolution::Reduce
+        }
+        else if rule_precedence.level < lookahead_precedence.level {
+            PrecedenceResolution::Shift
+        }
+        else {
+            match lookahead_precedence.associativity {
+                Associativity::Left => PrecedenceResolution::Reduce,
+                Associativity::Right => PrecedenceResolution::Shift,
+                Associativity::NonAssoc => PrecedenceResolution::Error,
+            }
+        };
+        resolved_conflicts.push(LR1ResolvedConflict { state_id, lookahead, rule_id, resolution });
+
+        keeps_shift &= resolution == PrecedenceResolution::Shift;
+        resolution == PrecedenceResolution::Reduce
+    });
+
+    if !keeps_shift {
+        cell_actions.retain(|action| !matches!(action, LR1Action::Shift(_)));
+    }
+}
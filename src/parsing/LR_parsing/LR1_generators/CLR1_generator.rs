@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::LR0_generator::Lr0Item;
+use super::{build_LR1_table, LR1Table, LRAutomaton};
+use crate::datastructures::bitset::BitSet;
+use crate::formal_language::*;
+use crate::UINT;
+
+
+/// canonical LR(1) table of Knuth
+pub fn generate_CLR1_table(cfg: &Cfg) -> LR1Table {
+    let automaton: Lr1Automaton = Lr1Automaton::new(cfg);
+    build_LR1_table(cfg, &automaton, &automaton.lookaheads(cfg))
+}
+
+
+pub struct Lr1State {
+    // sorted by item, identifies the state with the lookaheads of the kernel
+    pub kernel: Vec<Lr0Item>,
+    // the kernel followed by the items added by the closure
+    pub items: Vec<Lr0Item>,
+    // lookaheads[i] is the bitset of optional terminals that can follow items[i]
+    pub lookaheads: Vec<BitSet<UINT>>,
+    // sorted by symbol
+    pub transitions: Vec<(Symbol, usize)>,
+    // the symbol read to enter this state, None for the start state
+    pub accessing_symbol: Option<Symbol>,
+}
+
+
+/// number of states of the canonical LR(1) automaton compared to its LR(0) cores,
+/// which are the states of the LALR(1) automaton
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lr1AutomatonStatistics {
+    pub nbr_states: usize,
+    pub nbr_cores: usize,
+    // greatest number of states sharing the same core
+    pub max_states_per_core: usize,
+}
+
+impl Lr1AutomatonStatistics {
+    pub fn repr(&self) -> String {
+        format!("{} LR(1) states for {} LR(0) cores ({:.2} states per core, at most {})",
+            self.nbr_states, self.nbr_cores,
+            self.nbr_states as f64 / self.nbr_cores as f64, self.max_states_per_core)
+    }
+}
+
+
+/// canonical collection of LR(1) item sets, the start state is 0
+pub struct Lr1Automaton {
+    states: Vec<Lr1State>,
+}
+
+// the kernel items with the terminals of their lookahead
+type Lr1Kernel = Vec<(Lr0Item, Vec<usize>)>;
+
+impl Lr1Automaton {
+
+    pub fn new(cfg: &Cfg) -> Self {
+        let set_size: usize = usize::from(cfg.nbr_terminals())+1;
+
+        let mut end_set: BitSet<UINT> = BitSet::new_filled(false, set_size);
+        end_set.insert(usize::from(cfg.to_local_nosymbol(cfg.END())));
+        let start_kernel: Vec<(Lr0Item, BitSet<UINT>)> = cfg
+            .get_rules_by_origin(cfg.START())
+            .map(|(rule_id, _)| (Lr0Item::new(rule_id, 0), end_set.clone()))
+            .collect();
+
+        // FIRST sets of the symbols after the non-terminal following the dot, by item
+        let mut first_sets: HashMap<Lr0Item, BitSet<UINT>> = HashMap::new();
+
+        let mut states: Vec<Lr1State> = vec![Self::new_state(cfg, &start_kernel, None, &mut first_sets)];
+        let mut state_ids: HashMap<Lr1Kernel, usize> = HashMap::from([(Self::kernel_key(&start_kernel), 0)]);
+
+        // states are processed in the order of creation
+        let mut state_id: usize = 0;
+        while state_id < states.len() {
+
+            // kernels of the successors, grouped by the symbol read
+            let mut successor_kernels: BTreeMap<Symbol, BTreeMap<Lr0Item, BitSet<UINT>>> = BTreeMap::new();
+            let state: &Lr1State = &states[state_id];
+            for (item, lookahead_set) in state.items.iter().zip(&state.lookaheads) {
+                if let Some(symbol) = item.next_symbol(cfg) {
+                    successor_kernels
+                        .entry(symbol)
+                        .or_default()
+                        .entry(item.advance())
+                        .and_modify(|kernel_lookahead_set| kernel_lookahead_set.update_union(lookahead_set))
+                        .or_insert_with(|| lookahead_set.clone());
+                }
+            }
+
+            let mut transitions: Vec<(Symbol, usize)> = Vec::with_capacity(successor_kernels.len());
+            for (symbol, kernel) in successor_kernels {
+                let kernel: Vec<(Lr0Item, BitSet<UINT>)> = kernel.into_iter().collect();
+                let key: Lr1Kernel = Self::kernel_key(&kernel);
+
+                let target_id: usize = match state_ids.get(&key) {
+                    Some(&target_id) => target_id,
+                    None => {
+                        let target_id: usize = states.len();
+                        states.push(Self::new_state(cfg, &kernel, Some(symbol), &mut first_sets));
+                        state_ids.insert(key, target_id);
+                        target_id
+                    }
+                };
+                transitions.push((symbol, target_id));
+            }
+            states[state_id].transitions = transitions;
+
+            state_id += 1;
+        }
+
+        Lr1Automaton { states }
+    }
+
+    fn kernel_key(kernel: &[(Lr0Item, BitSet<UINT>)]) -> Lr1Kernel {
+        kernel.iter().map(|(item, lookahead_set)| (*item, lookahead_set.iter().collect())).collect()
+    }
+
+    fn new_state(cfg: &Cfg, kernel: &[(Lr0Item, BitSet<UINT>)], accessing_symbol: Option<Symbol>,
+        first_sets: &mut HashMap<Lr0Item, BitSet<UINT>>) -> Lr1State {
+        let (items, lookaheads): (Vec<Lr0Item>, Vec<BitSet<UINT>>) = Self::closure(cfg, kernel, first_sets);
+        Lr1State {
+            kernel: kernel.iter().map(|(item, _)| *item).collect(),
+            items,
+            lookaheads,
+            transitions: Vec::new(),
+            accessing_symbol,
+        }
+    }
+
+    /// the kernel followed by the items (rule, 0) of the non-terminals that can appear after a dot,
+    /// with the lookaheads propagated until nothing changes
+    pub fn closure(cfg: &Cfg, kernel: &[(Lr0Item, BitSet<UINT>)], first_sets: &mut HashMap<Lr0Item, BitSet<UINT>>)
+    -> (Vec<Lr0Item>, Vec<BitSet<UINT>>) {
+        let mut items: Vec<Lr0Item> = kernel.iter().map(|(item, _)| *item).collect();
+        let mut lookaheads: Vec<BitSet<UINT>> = kernel.iter().map(|(_, lookahead_set)| lookahead_set.clone()).collect();
+        let mut item_ids: HashMap<Lr0Item, usize> = items.iter().enumerate().map(|(index, item)| (*item, index)).collect();
+
+        let mut to_process: Vec<usize> = (0..items.len()).collect();
+        while let Some(item_index) = to_process.pop() {
+            let item: Lr0Item = items[item_index];
+            let Some(symbol) = item.next_symbol(cfg) else { continue };
+            if !cfg.is_non_terminal(symbol) {
+                continue;
+            }
+
+            // FIRST(β lookahead) for the item A -> α . B β
+            let first_set: &BitSet<UINT> = first_sets
+                .entry(item)
+                .or_insert_with(|| cfg.get_first_set_of_word(&item.remaining_symbols(cfg)[1..]));
+            let mut new_lookahead_set: BitSet<UINT> = first_set.clone();
+            if new_lookahead_set.contains(0) {
+                new_lookahead_set.remove(0);
+                new_lookahead_set.update_union(&lookaheads[item_index]);
+            }
+
+            for (rule_id, _) in cfg.get_rules_by_origin(symbol) {
+                let new_item: Lr0Item = Lr0Item::new(rule_id, 0);
+                match item_ids.get(&new_item) {
+                    Some(&new_item_index) => {
+                        if !new_lookahead_set.is_subset(&lookaheads[new_item_index]) {
+                            lookaheads[new_item_index].update_union(&new_lookahead_set);
+                            to_process.push(new_item_index);
+                        }
+                    }
+                    None => {
+                        item_ids.insert(new_item, items.len());
+                        to_process.push(items.len());
+                        items.push(new_item);
+                        lookaheads.push(new_lookahead_set.clone());
+                    }
+                }
+            }
+        }
+
+        (items, lookaheads)
+    }
+
+    pub fn nbr_states(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn get_state(&self, state_id: usize) -> &Lr1State {
+        &self.states[state_id]
+    }
+
+    pub fn all_states(&self) -> impl Iterator<Item = (usize, &Lr1State)> {
+        self.states.iter().enumerate()
+    }
+
+    /// the state reached by reading symbol from the state, if any
+    pub fn goto(&self, state_id: usize, symbol: Symbol) -> Option<usize> {
+        let transitions: &[(Symbol, usize)] = &self.states[state_id].transitions;
+        transitions
+            .binary_search_by_key(&symbol, |&(transition_symbol, _)| transition_symbol)
+            .ok()
+            .map(|index| transitions[index].1)
+    }
+
+    /// lookaheads of the complete items of each state, as expected by build_LR1_table
+    pub fn lookaheads(&self, cfg: &Cfg) -> Vec<Vec<(CfgRuleIdx, BitSet<UINT>)>> {
+        self.states
+            .iter()
+            .map(|state| state.items
+                .iter()
+                .zip(&state.lookaheads)
+                .filter(|(item, _)| item.is_complete(cfg))
+                .map(|(item, lookahead_set)| (item.rule_id, lookahead_set.clone()))
+                .collect())
+            .collect()
+    }
+
+    pub fn statistics(&self) -> Lr1AutomatonStatistics {
+        let mut nbr_states_by_core: HashMap<&[Lr0Item], usize> = HashMap::new();
+        for state in &self.states {
+            *nbr_states_by_core.entry(&state.kernel).or_default() += 1;
+        }
+        Lr1AutomatonStatistics {
+            nbr_states: self.nbr_states(),
+            nbr_cores: nbr_states_by_core.len(),
+            max_states_per_core: nbr_states_by_core.values().copied().max().unwrap_or(0),
+        }
+    }
+
+    /// the sets of states sharing the same core, with more than one state
+    pub fn split_cores(&self) -> Vec<Vec<usize>> {
+        let mut states_by_core: HashMap<&[Lr0Item], Vec<usize>> = HashMap::new();
+        for (state_id, state) in self.all_states() {
+            states_by_core.entry(&state.kernel).or_default().push(state_id);
+        }
+        let mut split_cores: Vec<Vec<usize>> = states_by_core.into_values().filter(|state_ids| state_ids.len() > 1).collect();
+        split_cores.sort();
+        split_cores
+    }
+
+    pub fn repr_state(&self, cfg: &Cfg, state_id: usize) -> String {
+        let state: &Lr1State = self.get_state(state_id);
+        let mut s: String = format!("state {state_id}:\n");
+        for (index, (item, lookahead_set)) in state.items.iter().zip(&state.lookaheads).enumerate() {
+            s.push_str(if index < state.kernel.len() {"    "} else {"  + "});
+            s.push_str(&format!("{}, {}\n", item.repr(cfg), cfg.repr_optional_terminal_set(lookahead_set)));
+        }
+        for &(symbol, target_id) in &state.transitions {
+            s.push_str(&format!("    {} => {}\n", cfg.repr_symbol(symbol), target_id));
+        }
+        s
+    }
+
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        (0..self.nbr_states()).map(|state_id| self.repr_state(cfg, state_id)).collect()
+    }
+}
+
+impl LRAutomaton for Lr1Automaton {
+    fn nbr_states(&self) -> usize {
+        self.states.len()
+    }
+
+    fn transitions(&self, state_id: usize) -> &[(Symbol, usize)] {
+        &self.states[state_id].transitions
+    }
+
+    fn core_items(&self, state_id: usize) -> &[Lr0Item] {
+        &self.states[state_id].items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar};
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_generators::LR0_generator::Lr0Automaton;
+
+    #[test]
+    fn expression_grammar_statistics() {
+        let cfg = create_expression_grammar();
+        let automaton = Lr1Automaton::new(&cfg);
+        let statistics = automaton.statistics();
+        println!("{}", statistics.repr());
+
+        assert_eq!(statistics.nbr_cores, Lr0Automaton::new(&cfg).nbr_states());
+        assert!(statistics.nbr_states > statistics.nbr_cores);
+        assert!(!generate_CLR1_table(&cfg).has_conflicts());
+
+        let start_state = automaton.get_state(0);
+        let closure: Vec<String> = start_state.items
+            .iter()
+            .zip(&start_state.lookaheads)
+            .map(|(item, lookahead_set)| format!("{}, {}", item.repr(&cfg), cfg.repr_optional_terminal_set(lookahead_set)))
+            .collect();
+        assert!(closure.contains(&String::from("F --> . id, {END, +, *}")));
+    }
+
+    #[test]
+    fn LR1_grammar_not_LALR1() {
+        // S -> a A d | b B d | a B e | b A e, A -> c, B -> c
+        let cfg = create_grammar(
+            &["a", "b", "c", "d", "e"],
+            &["S", "A", "B"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["a", "A", "d"]),
+                ("S", &["b", "B", "d"]),
+                ("S", &["a", "B", "e"]),
+                ("S", &["b", "A", "e"]),
+                ("A", &["c"]),
+                ("B", &["c"]),
+            ],
+        );
+
+        let automaton = Lr1Automaton::new(&cfg);
+        println!("{}", automaton.repr(&cfg));
+        assert!(!generate_CLR1_table(&cfg).has_conflicts());
+        assert!(generate_LALR1_table(&cfg).has_conflicts());
+
+        // the states after "a c" and "b c" share their core
+        let statistics = automaton.statistics();
+        assert_eq!(statistics.nbr_states, statistics.nbr_cores + 1);
+        assert_eq!(automaton.split_cores().len(), 1);
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use super::CLR1_generator::{Lr1Automaton, Lr1State};
+use super::LALR1_generator::{digraph, nullable_suffix_start, LALR1Lookaheads};
+use super::LR0_generator::{Lr0Automaton, Lr0Item};
+use super::{build_LR1_table, LR1Action, LR1Conflict, LR1Table, LRAutomaton};
+use crate::datastructures::bitset::BitSet;
+use crate::formal_language::*;
+use crate::UINT;
+
+
+/// IELR(1) table: as powerful as the canonical LR(1) table, with the size of the LALR(1) table when there is
+/// no mysterious conflict
+pub fn generate_IELR1_table(cfg: &Cfg) -> LR1Table {
+    generate_IELR1_table_with_report(cfg).0
+}
+
+/// the IELR(1) table, with a report on the LALR(1) states that had to be split
+pub fn generate_IELR1_table_with_report(cfg: &Cfg) -> (LR1Table, Ielr1Report) {
+    let automaton: Ielr1Automaton = Ielr1Automaton::new(cfg);
+    let table: LR1Table = build_LR1_table(cfg, &automaton, &automaton.lookaheads(cfg));
+    let report: Ielr1Report = automaton.report(cfg, &table);
+    (table, report)
+}
+
+
+/// an LALR(1) state split into several states
+#[derive(Debug, Clone)]
+pub struct Ielr1Split {
+    pub lalr_state_id: usize,
+    pub state_ids: Vec<usize>,
+    // the lookaheads on which the split states differ, which all reach a conflict of the LALR(1) table
+    pub tokens: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ielr1Report {
+    pub nbr_lalr_states: usize,
+    pub nbr_states: usize,
+    pub splits: Vec<Ielr1Split>,
+    pub lalr_conflicts: Vec<LR1Conflict>,
+    // the conflicts of the LALR(1) table that disappeared with the splits
+    pub mysterious_conflicts: Vec<LR1Conflict>,
+}
+
+impl Ielr1Report {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = format!("{} states for {} LALR(1) states, {} split, {} of the {} LALR(1) conflicts were mysterious\n",
+            self.nbr_states, self.nbr_lalr_states, self.splits.len(), self.mysterious_conflicts.len(), self.lalr_conflicts.len());
+        for split in &self.splits {
+            let state_ids: Vec<String> = split.state_ids.iter().map(|state_id| state_id.to_string()).collect();
+            let tokens: Vec<&str> = split.tokens.iter().map(|&token| cfg.repr_symbol(token)).collect();
+            s.push_str(&format!("LALR(1) state {} split into states {}: different lookaheads {{{}}}\n",
+                split.lalr_state_id, state_ids.join(", "), tokens.join(", ")));
+        }
+        for conflict in &self.mysterious_conflicts {
+            s.push_str("removed ");
+            s.push_str(&conflict.repr(cfg));
+        }
+        s
+    }
+}
+
+
+/// LR(1) automaton whose states are LR(0) states, split when their lookaheads reach an LALR(1) conflict differently
+///
+/// the lookaheads of each item of the LR(0) automaton that can flow into a conflict of the LALR(1) table are computed
+/// first, then the LR(1) states are built by propagating lookaheads, but two states with the same core are merged
+/// when their kernels have the same of these relevant lookaheads
+pub struct Ielr1Automaton {
+    states: Vec<Lr1State>,
+    // the state of the LR(0) automaton (and of the LALR(1) automaton) with the same core as each state
+    core_ids: Vec<usize>,
+    // relevant_lookaheads[lr0 state][item] = lookaheads of the item that can flow into an LALR(1) conflict
+    relevant_lookaheads: Vec<Vec<BitSet<UINT>>>,
+    nbr_lalr_states: usize,
+    lalr_conflicts: Vec<LR1Conflict>,
+}
+
+impl Ielr1Automaton {
+
+    pub fn new(cfg: &Cfg) -> Self {
+        let lr0_automaton: Lr0Automaton = Lr0Automaton::new(cfg);
+        let lalr_table: LR1Table = build_LR1_table(cfg, &lr0_automaton,
+            &LALR1Lookaheads::new(cfg, &lr0_automaton).lookaheads);
+        let relevant_lookaheads: Vec<Vec<BitSet<UINT>>> =
+            Self::compute_relevant_lookaheads(cfg, &lr0_automaton, lalr_table.conflicts());
+
+        let set_size: usize = usize::from(cfg.nbr_terminals())+1;
+        let mut end_set: BitSet<UINT> = BitSet::new_filled(false, set_size);
+        end_set.insert(usize::from(cfg.to_local_nosymbol(cfg.END())));
+
+        // lookaheads of the kernel of each state, grown until nothing changes
+        let mut kernel_lookaheads: Vec<Vec<BitSet<UINT>>> =
+            vec![vec![end_set; lr0_automaton.get_state(0).kernel.len()]];
+        let mut states: Vec<Lr1State> = vec![Self::new_state(&lr0_automaton.get_state(0).kernel, None)];
+        let mut core_ids: Vec<usize> = vec![0];
+
+        let mut state_ids: HashMap<(usize, Vec<Vec<usize>>), usize> = HashMap::new();
+        state_ids.insert((0, kernel_lookaheads[0].iter().zip(&relevant_lookaheads[0])
+            .map(|(lookahead_set, relevant_set)| lookahead_set.intersection(relevant_set).iter().collect())
+            .collect()), 0);
+
+        // FIRST sets of the symbols after the non-terminal following the dot, by item
+        let mut first_sets: HashMap<Lr0Item, BitSet<UINT>> = HashMap::new();
+
+        let mut to_process: VecDeque<usize> = VecDeque::from([0]);
+        let mut is_in_queue: Vec<bool> = vec![true];
+        while let Some(state_id) = to_process.pop_front() {
+            is_in_queue[state_id] = false;
+
+            let kernel: Vec<(Lr0Item, BitSet<UINT>)> = states[state_id].kernel
+                .iter()
+                .copied()
+                .zip(kernel_lookaheads[state_id].iter().cloned())
+                .collect();
+            let (items, lookaheads): (Vec<Lr0Item>, Vec<BitSet<UINT>>) = Lr1Automaton::closure(cfg, &kernel, &mut first_sets);
+
+            // kernels of the successors, grouped by the symbol read
+            let mut successor_kernels: BTreeMap<Symbol, BTreeMap<Lr0Item, BitSet<UINT>>> = BTreeMap::new();
+            for (item, lookahead_set) in items.iter().zip(&lookaheads) {
+                if let Some(symbol) = item.next_symbol(cfg) {
+                    successor_kernels
+                        .entry(symbol)
+                        .or_default()
+                        .entry(item.advance())
+                        .and_modify(|kernel_lookahead_set| kernel_lookahead_set.update_union(lookahead_set))
+                        .or_insert_with(|| lookahead_set.clone());
+                }
+            }
+
+            let mut transitions: Vec<(Symbol, usize)> = Vec::with_capacity(successor_kernels.len());
+            for (symbol, kernel) in successor_kernels {
+                // unwrap is safe because the states have the same cores as the LR(0) states
+                let core_id: usize = lr0_automaton.goto(core_ids[state_id], symbol).unwrap();
+                // the kernel items are sorted like the kernel of the LR(0) state, which starts its items
+                let successor_lookaheads: Vec<BitSet<UINT>> = kernel.into_values().collect();
+                let key: (usize, Vec<Vec<usize>>) = (core_id, successor_lookaheads
+                    .iter()
+                    .zip(&relevant_lookaheads[core_id])
+                    .map(|(lookahead_set, relevant_set)| lookahead_set.intersection(relevant_set).iter().collect())
+                    .collect());
+
+                let target_id: usize = match state_ids.get(&key) {
+                    Some(&target_id) => {
+                        let mut has_changed: bool = false;
+                        for (old_set, new_set) in kernel_lookaheads[target_id].iter_mut().zip(&successor_lookaheads) {
+                            if !new_set.is_subset(old_set) {
+                                old_set.update_union(new_set);
+                                has_changed = true;
+                            }
+                        }
+                        if has_changed && !is_in_queue[target_id] {
+                            is_in_queue[target_id] = true;
+                            to_process.push_back(target_id);
+                        }
+                        target_id
+                    }
+                    None => {
+                        let target_id: usize = states.len();
+                        states.push(Self::new_state(&lr0_automaton.get_state(core_id).kernel, Some(symbol)));
+                        kernel_lookaheads.push(successor_lookaheads);
+                        core_ids.push(core_id);
+                        state_ids.insert(key, target_id);
+                        is_in_queue.push(true);
+                        to_process.push_back(target_id);
+                        target_id
+                    }
+                };
+                transitions.push((symbol, target_id));
+            }
+
+            let state: &mut Lr1State = &mut states[state_id];
+            state.items = items;
+            state.lookaheads = lookaheads;
+            state.transitions = transitions;
+        }
+
+        Ielr1Automaton {
+            states,
+            core_ids,
+            relevant_lookaheads,
+            nbr_lalr_states: lr0_automaton.nbr_states(),
+            lalr_conflicts: lalr_table.conflicts().to_vec(),
+        }
+    }
+
+    fn new_state(kernel: &[Lr0Item], accessing_symbol: Option<Symbol>) -> Lr1State {
+        Lr1State {
+            kernel: kernel.to_vec(),
+            items: Vec::new(),
+            lookaheads: Vec::new(),
+            transitions: Vec::new(),
+            accessing_symbol,
+        }
+    }
+
+    /// for each item of each LR(0) state, the lookaheads that can flow from the item into a reduction
+    /// involved in a conflict on this lookahead
+    fn compute_relevant_lookaheads(cfg: &Cfg, lr0_automaton: &Lr0Automaton, conflicts: &[LR1Conflict])
+    -> Vec<Vec<BitSet<UINT>>> {
+        let set_size: usize = usize::from(cfg.nbr_terminals())+1;
+
+        // one node per item of each state
+        let mut offsets: Vec<usize> = Vec::with_capacity(lr0_automaton.nbr_states());
+        let mut nbr_nodes: usize = 0;
+        for (_, state) in lr0_automaton.all_states() {
+            offsets.push(nbr_nodes);
+            nbr_nodes += state.items.len();
+        }
+
+        // the reductions involved in conflicts
+        let mut sets: Vec<BitSet<UINT>> = vec![BitSet::new_filled(false, set_size); nbr_nodes];
+        for conflict in conflicts {
+            let state_items: &[Lr0Item] = &lr0_automaton.get_state(conflict.state_id).items;
+            for action in &conflict.actions {
+                if let LR1Action::Reduce(rule_id) | LR1Action::Accept(rule_id) = action
+                    && let Some(item_index) = state_items.iter().position(|item| item.is_complete(cfg) && item.rule_id == *rule_id) {
+                    sets[offsets[conflict.state_id] + item_index].insert(usize::from(cfg.to_local_nosymbol(conflict.lookahead)));
+                }
+            }
+        }
+
+        // an item propagates its lookaheads to the item after the transition,
+        // and to the closure items of the non-terminal after the dot when the rest of the item is nullable
+        let mut propagations: Vec<Vec<usize>> = vec![Vec::new(); nbr_nodes];
+        for (state_id, state) in lr0_automaton.all_states() {
+            for (item_index, item) in state.items.iter().enumerate() {
+                let Some(symbol) = item.next_symbol(cfg) else { continue };
+                let propagation: &mut Vec<usize> = &mut propagations[offsets[state_id] + item_index];
+
+                // unwrap is safe because the item has a symbol after the dot
+                let target_id: usize = lr0_automaton.goto(state_id, symbol).unwrap();
+                let advanced_item: Lr0Item = item.advance();
+                let target_index: usize = lr0_automaton.get_state(target_id).kernel.binary_search(&advanced_item).unwrap();
+                propagation.push(offsets[target_id] + target_index);
+
+                let remaining_symbols: &[Symbol] = &item.remaining_symbols(cfg)[1..];
+                if cfg.is_non_terminal(symbol) && nullable_suffix_start(cfg, remaining_symbols) == 0 {
+                    for (closure_index, closure_item) in state.items.iter().enumerate() {
+                        if closure_item.dot == 0 && cfg.get_rule_by_id(closure_item.rule_id).origin == symbol {
+                            propagation.push(offsets[state_id] + closure_index);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut sets: std::vec::IntoIter<BitSet<UINT>> = digraph(&propagations, sets).into_iter();
+        lr0_automaton
+            .all_states()
+            .map(|(_, state)| sets.by_ref().take(state.items.len()).collect())
+            .collect()
+    }
+
+    pub fn nbr_states(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn get_state(&self, state_id: usize) -> &Lr1State {
+        &self.states[state_id]
+    }
+
+    /// the LR(0) state with the same core
+    pub fn get_core_id(&self, state_id: usize) -> usize {
+        self.core_ids[state_id]
+    }
+
+    /// lookaheads of the complete items of each state, as expected by build_LR1_table
+    pub fn lookaheads(&self, cfg: &Cfg) -> Vec<Vec<(CfgRuleIdx, BitSet<UINT>)>> {
+        self.states
+            .iter()
+            .map(|state| state.items
+                .iter()
+                .zip(&state.lookaheads)
+                .filter(|(item, _)| item.is_complete(cfg))
+                .map(|(item, lookahead_set)| (item.rule_id, lookahead_set.clone()))
+                .collect())
+            .collect()
+    }
+
+    /// the splits of the LALR(1) states, and the LALR(1) conflicts that don't appear in the table of this automaton
+    pub fn report(&self, cfg: &Cfg, table: &LR1Table) -> Ielr1Report {
+        let mut state_ids_by_core: Vec<Vec<usize>> = vec![Vec::new(); self.nbr_lalr_states];
+        for (state_id, &core_id) in self.core_ids.iter().enumerate() {
+            state_ids_by_core[core_id].push(state_id);
+        }
+
+        let mut splits: Vec<Ielr1Split> = Vec::new();
+        for (core_id, state_ids) in state_ids_by_core.iter().enumerate() {
+            if state_ids.len() < 2 {
+                continue;
+            }
+
+            // the relevant lookaheads present in some split states but not all of them
+            let set_size: usize = usize::from(cfg.nbr_terminals())+1;
+            let mut tokens: BitSet<UINT> = BitSet::new_filled(false, set_size);
+            // only the kernel items are in the same order as in the LR(0) state
+            let kernel_size: usize = self.states[state_ids[0]].kernel.len();
+            for (item_index, relevant_set) in self.relevant_lookaheads[core_id].iter().take(kernel_size).enumerate() {
+                let mut union: BitSet<UINT> = BitSet::new_filled(false, set_size);
+                let mut intersection: BitSet<UINT> = relevant_set.clone();
+                for &state_id in state_ids {
+                    let lookahead_set: &BitSet<UINT> = &self.states[state_id].lookaheads[item_index];
+                    union.update_union(&lookahead_set.intersection(relevant_set));
+                    intersection.update_intersection(lookahead_set);
+                }
+                tokens.update_union(&union.difference(&intersection));
+            }
+
+            splits.push(Ielr1Split {
+                lalr_state_id: core_id,
+                state_ids: state_ids.clone(),
+                // unwrap is safe because NOSYMBOL is never a lookahead
+                tokens: tokens.iter().map(|local_id| cfg.terminal_from_local_nosymbol(SymbolIdx::from(local_id)).unwrap()).collect(),
+            });
+        }
+
+        let mysterious_conflicts: Vec<LR1Conflict> = self.lalr_conflicts
+            .iter()
+            .filter(|lalr_conflict| !table.conflicts().iter().any(|conflict|
+                self.core_ids[conflict.state_id] == lalr_conflict.state_id && conflict.lookahead == lalr_conflict.lookahead))
+            .cloned()
+            .collect();
+
+        Ielr1Report {
+            nbr_lalr_states: self.nbr_lalr_states,
+            nbr_states: self.nbr_states(),
+            splits,
+            lalr_conflicts: self.lalr_conflicts.clone(),
+            mysterious_conflicts,
+        }
+    }
+}
+
+impl LRAutomaton for Ielr1Automaton {
+    fn nbr_states(&self) -> usize {
+        self.states.len()
+    }
+
+    fn transitions(&self, state_id: usize) -> &[(Symbol, usize)] {
+        &self.states[state_id].transitions
+    }
+
+    fn core_items(&self, state_id: usize) -> &[Lr0Item] {
+        &self.states[state_id].items
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_ambiguous_sum_grammar, create_expression_grammar, create_grammar};
+    use crate::parsing::LR_parsing::LR1_generators::CLR1_generator::generate_CLR1_table;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+
+    #[test]
+    fn no_split_without_conflicts() {
+        let cfg = create_expression_grammar();
+        let (table, report) = generate_IELR1_table_with_report(&cfg);
+        let lalr1_table = generate_LALR1_table(&cfg);
+
+        assert!(!table.has_conflicts());
+        assert_eq!(table.nbr_states(), lalr1_table.nbr_states());
+        assert!(report.splits.is_empty());
+        assert!(table.nbr_states() < generate_CLR1_table(&cfg).nbr_states());
+        for state_id in 0..table.nbr_states() {
+            for terminal in cfg.all_terminals() {
+                assert_eq!(table.get_action(state_id, terminal), lalr1_table.get_action(state_id, terminal));
+            }
+        }
+    }
+
+    #[test]
+    fn mysterious_conflicts() {
+        // S -> a A d | b B d | a B e | b A e | x A y, A -> c, B -> c
+        let cfg = create_grammar(
+            &["a", "b", "c", "d", "e", "x", "y"],
+            &["S", "A", "B"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["a", "A", "d"]),
+                ("S", &["b", "B", "d"]),
+                ("S", &["a", "B", "e"]),
+                ("S", &["b", "A", "e"]),
+                ("S", &["x", "A", "y"]),
+                ("A", &["c"]),
+                ("B", &["c"]),
+            ],
+        );
+
+        let (table, report) = generate_IELR1_table_with_report(&cfg);
+        println!("{}", report.repr(&cfg));
+
+        assert!(!table.has_conflicts());
+        assert_eq!(report.lalr_conflicts.len(), 2);
+        assert_eq!(report.mysterious_conflicts.len(), 2);
+
+        // only the state after "a c" and "b c" is split
+        assert_eq!(report.splits.len(), 1);
+        assert_eq!(report.nbr_states, report.nbr_lalr_states + 1);
+        assert_eq!(report.nbr_states, generate_CLR1_table(&cfg).nbr_states());
+        let tokens: Vec<&str> = report.splits[0].tokens.iter().map(|&token| cfg.repr_symbol(token)).collect();
+        assert_eq!(tokens, vec!["d", "e"]);
+    }
+
+    #[test]
+    fn real_conflicts_remain() {
+        let cfg = create_ambiguous_sum_grammar();
+
+        let (table, report) = generate_IELR1_table_with_report(&cfg);
+        assert_eq!(table.conflicts().len(), 1);
+        assert!(report.mysterious_conflicts.is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use super::LR0_generator::Lr0Automaton;
+use super::{build_LR1_table, LR1Table};
+use crate::datastructures::bitset::BitSet;
+use crate::formal_language::*;
+use crate::UINT;
+
+
+/// LALR(1) table, with the lookaheads computed on the LR(0) automaton with the relations of DeRemer and Pennello
+pub fn generate_LALR1_table(cfg: &Cfg) -> LR1Table {
+    let automaton: Lr0Automaton = Lr0Automaton::new(cfg);
+    build_LR1_table(cfg, &automaton, &LALR1Lookaheads::new(cfg, &automaton).lookaheads)
+}
+
+
+/// intermediate results of the lookahead computation, indexed by the non-terminal transitions (p, A) of the automaton
+pub struct LALR1Lookaheads {
+    // (state, non-terminal) of each non-terminal transition
+    pub transitions: Vec<(usize, Symbol)>,
+    pub transition_ids: HashMap<(usize, Symbol), usize>,
+
+    // terminals that can be read after the transition, directly or after nullable non-terminals
+    pub read_sets: Vec<BitSet<UINT>>,
+    // (p, A) includes (p', B) iff B -> β A γ, γ is nullable and p' reads β to go to p
+    pub includes: Vec<Vec<usize>>,
+    // terminals that can follow the non-terminal of the transition
+    pub follow_sets: Vec<BitSet<UINT>>,
+    // (q, rule) -> the transitions (p, A) such that rule = A -> ω and p reads ω to go to q
+    pub lookbacks: HashMap<(usize, CfgRuleIdx), Vec<usize>>,
+
+    // lookaheads[state] = (rule, lookahead set) for each complete item of the state
+    pub lookaheads: Vec<Vec<(CfgRuleIdx, BitSet<UINT>)>>,
+}
+
+impl LALR1Lookaheads {
+
+    pub fn new(cfg: &Cfg, automaton: &Lr0Automaton) -> Self {
+        let set_size: usize = usize::from(cfg.nbr_terminals())+1;
+
+        let mut transitions: Vec<(usize, Symbol)> = Vec::new();
+        let mut transition_ids: HashMap<(usize, Symbol), usize> = HashMap::new();
+        for (state_id, state) in automaton.all_states() {
+            for &(symbol, _) in &state.transitions {
+                if cfg.is_non_terminal(symbol) {
+                    transition_ids.insert((state_id, symbol), transitions.len());
+                    transitions.push((state_id, symbol));
+                }
+            }
+        }
+
+        // direct reads and reads relation
+        let mut direct_read_sets: Vec<BitSet<UINT>> = Vec::with_capacity(transitions.len());
+        let mut reads: Vec<Vec<usize>> = Vec::with_capacity(transitions.len());
+        for &(state_id, non_terminal) in &transitions {
+            // unwrap is safe because this is a transition of the automaton
+            let target_id: usize = automaton.goto(state_id, non_terminal).unwrap();
+
+            let mut direct_read_set: BitSet<UINT> = BitSet::new_filled(false, set_size);
+            let mut transition_reads: Vec<usize> = Vec::new();
+            for &(symbol, _) in &automaton.get_state(target_id).transitions {
+                if cfg.is_terminal(symbol) {
+                    direct_read_set.insert(usize::from(cfg.to_local_nosymbol(symbol)));
+                }
+                else if cfg.is_symbol_nullable(symbol) {
+                    transition_reads.push(transition_ids[&(target_id, symbol)]);
+                }
+            }
+            direct_read_sets.push(direct_read_set);
+            reads.push(transition_reads);
+        }
+        let read_sets: Vec<BitSet<UINT>> = digraph(&reads, direct_read_sets);
+
+        // includes and lookback relations
+        let mut includes: Vec<Vec<usize>> = vec![Vec::new(); transitions.len()];
+        let mut lookbacks: HashMap<(usize, CfgRuleIdx), Vec<usize>> = HashMap::new();
+        for (transition_id, &(origin_state_id, non_terminal)) in transitions.iter().enumerate() {
+            for (rule_id, rule) in cfg.get_rules_by_origin(non_terminal) {
+                let nullable_suffix_start: usize = nullable_suffix_start(cfg, &rule.replacement);
+
+                let mut state_id: usize = origin_state_id;
+                for (position, &symbol) in rule.replacement.iter().enumerate() {
+                    if cfg.is_non_terminal(symbol) && position+1 >= nullable_suffix_start {
+                        includes[transition_ids[&(state_id, symbol)]].push(transition_id);
+                    }
+                    // unwrap is safe because origin_state_id contains the item non_terminal -> . replacement
+                    state_id = automaton.goto(state_id, symbol).unwrap();
+                }
+                lookbacks.entry((state_id, rule_id)).or_default().push(transition_id);
+            }
+        }
+        let follow_sets: Vec<BitSet<UINT>> = digraph(&includes, read_sets.clone());
+
+        let mut lookaheads: Vec<Vec<(CfgRuleIdx, BitSet<UINT>)>> = Vec::with_capacity(automaton.nbr_states());
+        for (state_id, state) in automaton.all_states() {
+            lookaheads.push(state.complete_items(cfg).map(|item| {
+                let mut lookahead_set: BitSet<UINT> = BitSet::new_filled(false, set_size);
+                if cfg.get_rule_by_id(item.rule_id).origin == cfg.START() {
+                    lookahead_set.insert(usize::from(cfg.to_local_nosymbol(cfg.END())));
+                }
+                for &transition_id in lookbacks.get(&(state_id, item.rule_id)).into_iter().flatten() {
+                    lookahead_set.update_union(&follow_sets[transition_id]);
+                }
+                (item.rule_id, lookahead_set)
+            }).collect());
+        }
+
+        LALR1Lookaheads {
+            transitions,
+            transition_ids,
+            read_sets,
+            includes,
+            follow_sets,
+            lookbacks,
+            lookaheads,
+        }
+    }
+}
+
+
+/// the smallest position such that the word is nullable from this position
+pub fn nullable_suffix_start(cfg: &Cfg, word: &[Symbol]) -> usize {
+    word.iter().rposition(|&symbol| !cfg.is_symbol_nullable(symbol)).map_or(0, |position| position+1)
+}
+
+
+/// computes F(x) = F'(x) ∪ {F(y) | x R y} for every x, where relation[x] = {y | x R y} and sets[x] = F'(x)
+/// every strongly connected component is processed once, so this is linear in the size of the relation
+pub fn digraph(relation: &[Vec<usize>], mut sets: Vec<BitSet<UINT>>) -> Vec<BitSet<UINT>> {
+    const DONE: usize = usize::MAX;
+
+    // 0: not visited yet, DONE: finished, otherwise: depth in the stack when visited (lowered to the SCC root)
+    let mut depths: Vec<usize> = vec![0; relation.len()];
+    let mut stack: Vec<usize> = Vec::new();
+
+    for start in 0..relation.len() {
+        if depths[start] != 0 {
+            continue;
+        }
+
+        stack.push(start);
+        depths[start] = stack.len();
+        // (node, index of the next edge to follow, depth when visited)
+        let mut call_stack: Vec<(usize, usize, usize)> = vec![(start, 0, stack.len())];
+
+        while let Some(&mut (x, ref mut edge_index, depth)) = call_stack.last_mut() {
+            if *edge_index < relation[x].len() {
+                let y: usize = relation[x][*edge_index];
+                *edge_index += 1;
+
+                if depths[y] == 0 {
+                    stack.push(y);
+                    depths[y] = stack.len();
+                    call_stack.push((y, 0, stack.len()));
+                }
+                else {
+                    depths[x] = depths[x].min(depths[y]);
+                    union_into(&mut sets, x, y);
+                }
+                continue;
+            }
+
+            call_stack.pop();
+
+            if depths[x] == depth {
+                // x is the root of its strongly connected component, which shares the set of x
+                loop {
+                    let top: usize = stack.pop().unwrap();
+                    depths[top] = DONE;
+                    if top == x {
+                        break;
+                    }
+                    sets[top] = sets[x].clone();
+                }
+            }
+
+            if let Some(&(parent, _, _)) = call_stack.last() {
+                depths[parent] = depths[parent].min(depths[x]);
+                union_into(&mut sets, parent, x);
+            }
+        }
+    }
+
+    sets
+}
+
+fn union_into(sets: &mut [BitSet<UINT>], target: usize, source: usize) {
+    if target == source {
+        return;
+    }
+    let source_set: BitSet<UINT> = sets[source].clone();
+    sets[target].update_union(&source_set);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar};
+    use crate::parsing::LR_parsing::LR1_generators::{LR1Action, LR1ConflictKind};
+    use crate::parsing::LR_parsing::LR1_generators::SLR1_generator::generate_SLR1_table;
+
+    #[test]
+    fn assignment_grammar_is_LALR1() {
+        let cfg = create_grammar(
+            &["=", "*", "id"],
+            &["S", "L", "R"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["L", "=", "R"]),
+                ("S", &["R"]),
+                ("L", &["*", "R"]),
+                ("L", &["id"]),
+                ("R", &["L"]),
+            ],
+        );
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+
+        let table = generate_LALR1_table(&cfg);
+        assert!(!table.has_conflicts(), "{}", table.repr_conflicts(&cfg));
+
+        let after_l = table.get_goto(0, S("L")).unwrap();
+        assert!(matches!(table.get_action(after_l, S("=")), LR1Action::Shift(_)));
+        assert!(matches!(table.get_action(after_l, cfg.END()), LR1Action::Reduce(_)));
+    }
+
+    #[test]
+    fn same_table_as_SLR1() {
+        let cfg = create_expression_grammar();
+        let slr1_table = generate_SLR1_table(&cfg);
+        let lalr1_table = generate_LALR1_table(&cfg);
+
+        assert_eq!(slr1_table.nbr_states(), lalr1_table.nbr_states());
+        for state_id in 0..slr1_table.nbr_states() {
+            for terminal in cfg.all_terminals() {
+                assert_eq!(slr1_table.get_action(state_id, terminal), lalr1_table.get_action(state_id, terminal));
+            }
+        }
+    }
+
+    #[test]
+    fn nullable_lookaheads() {
+        // the lookaheads of A -> EPS come through the nullable B and C
+        let cfg = create_grammar(
+            &["a", "b", "c"],
+            &["S", "A", "B", "C"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["A", "B", "C", "a"]),
+                ("A", &[]),
+                ("B", &["b"]),
+                ("B", &[]),
+                ("C", &["c"]),
+                ("C", &[]),
+            ],
+        );
+        let lookaheads = LALR1Lookaheads::new(&cfg, &Lr0Automaton::new(&cfg));
+        let (_, lookahead_set) = &lookaheads.lookaheads[0][0];
+        assert_eq!(cfg.repr_optional_terminal_set(lookahead_set), "{a, b, c}");
+    }
+
+    #[test]
+    fn LR1_grammar_not_LALR1() {
+        // S -> a A d | b B d | a B e | b A e, A -> c, B -> c
+        let cfg = create_grammar(
+            &["a", "b", "c", "d", "e"],
+            &["S", "A", "B"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["a", "A", "d"]),
+                ("S", &["b", "B", "d"]),
+                ("S", &["a", "B", "e"]),
+                ("S", &["b", "A", "e"]),
+                ("A", &["c"]),
+                ("B", &["c"]),
+            ],
+        );
+
+        let table = generate_LALR1_table(&cfg);
+        println!("{}", table.repr_conflicts(&cfg));
+        assert_eq!(table.conflicts().len(), 2);
+        assert!(table.conflicts().iter().all(|conflict| conflict.kind() == LR1ConflictKind::ReduceReduce));
+    }
+
+    #[test]
+    fn large_grammar() {
+        // 130 levels of binary operators L_i -> L_i a_i L_i+1 | L_i b_i L_i+1 | L_i+1, about 400 rules
+        let nbr_levels: usize = 130;
+        let levels: Vec<String> = (0..=nbr_levels).map(|level| format!("L{level}")).collect();
+        let operators: Vec<String> = (0..nbr_levels).flat_map(|level| [format!("a{level}"), format!("b{level}")]).collect();
+
+        let mut terminals: Vec<&str> = operators.iter().map(String::as_str).collect();
+        terminals.extend(["(", ")", "id"]);
+        let non_terminals: Vec<&str> = levels.iter().map(String::as_str).collect();
+
+        let mut replacements: Vec<(&str, Vec<&str>)> = vec![("START", vec!["L0", "END"])];
+        for level in 0..nbr_levels {
+            let (current, next) = (levels[level].as_str(), levels[level+1].as_str());
+            replacements.push((current, vec![current, operators[2*level].as_str(), next]));
+            replacements.push((current, vec![current, operators[2*level+1].as_str(), next]));
+            replacements.push((current, vec![next]));
+        }
+        replacements.push((levels[nbr_levels].as_str(), vec!["(", "L0", ")"]));
+        replacements.push((levels[nbr_levels].as_str(), vec!["id"]));
+        let rules: Vec<(&str, &[&str])> = replacements.iter().map(|(origin, replacement)| (*origin, replacement.as_slice())).collect();
+
+        let cfg = create_grammar(&terminals, &non_terminals, &rules);
+        let table = generate_LALR1_table(&cfg);
+        assert!(!table.has_conflicts());
+    }
+
+    #[test]
+    fn digraph_sccs() {
+        // 0 -> 1 -> 2 -> 1, 2 -> 3
+        let relation = vec![vec![1], vec![2], vec![1, 3], vec![]];
+        let sets: Vec<BitSet<UINT>> = (0..4).map(|i| {
+            let mut set = BitSet::new_filled(false, 4);
+            set.insert(i);
+            set
+        }).collect();
+
+        let sets = digraph(&relation, sets);
+        let sets: Vec<Vec<usize>> = sets.iter().map(|set| set.iter().collect()).collect();
+        assert_eq!(sets, vec![vec![0, 1, 2, 3], vec![1, 2, 3], vec![1, 2, 3], vec![3]]);
+    }
+}
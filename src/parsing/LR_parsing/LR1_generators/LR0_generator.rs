@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{build_LR1_table, LR1Table, LRAutomaton};
+use crate::datastructures::bitset::BitSet;
+use crate::formal_language::*;
+use crate::UINT;
+
+
+/// rule with a dot in its replacement: the symbols before the dot have already been read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lr0Item {
+    pub rule_id: CfgRuleIdx,
+    pub dot: u16,
+}
+
+impl Lr0Item {
+
+    pub fn new(rule_id: CfgRuleIdx, dot: u16) -> Self {
+        Lr0Item { rule_id, dot }
+    }
+
+    /// the symbol right after the dot, None if the item is complete
+    pub fn next_symbol(&self, cfg: &Cfg) -> Option<Symbol> {
+        cfg.get_rule_by_id(self.rule_id).replacement.get(usize::from(self.dot)).copied()
+    }
+
+    /// the symbols after the dot
+    pub fn remaining_symbols<'a>(&self, cfg: &'a Cfg) -> &'a [Symbol] {
+        &cfg.get_rule_by_id(self.rule_id).replacement[usize::from(self.dot)..]
+    }
+
+    pub fn is_complete(&self, cfg: &Cfg) -> bool {
+        usize::from(self.dot) == cfg.get_rule_by_id(self.rule_id).replacement_size()
+    }
+
+    /// the item with the dot moved one symbol to the right
+    pub fn advance(&self) -> Self {
+        Lr0Item { rule_id: self.rule_id, dot: self.dot + 1 }
+    }
+
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let rule: &CfgRule = cfg.get_rule_by_id(self.rule_id);
+        let mut s: String = String::from(cfg.repr_symbol(rule.origin));
+        s.push_str(" -->");
+        for (position, &symbol) in rule.replacement.iter().enumerate() {
+            if position == usize::from(self.dot) {
+                s.push_str(" .");
+            }
+            s.push(' ');
+            s.push_str(cfg.repr_symbol(symbol));
+        }
+        if self.is_complete(cfg) {
+            s.push_str(" .");
+        }
+        s
+    }
+}
+
+
+pub struct Lr0State {
+    // sorted, identifies the state
+    pub kernel: Vec<Lr0Item>,
+    // the kernel followed by the items added by the closure
+    pub items: Vec<Lr0Item>,
+    // sorted by symbol
+    pub transitions: Vec<(Symbol, usize)>,
+    // the symbol read to enter this state, None for the start state
+    pub accessing_symbol: Option<Symbol>,
+}
+
+impl Lr0State {
+    /// the completed items of the state, which correspond to possible reductions
+    pub fn complete_items<'a>(&'a self, cfg: &'a Cfg) -> impl Iterator<Item = Lr0Item> + 'a {
+        self.items.iter().filter(|item| item.is_complete(cfg)).copied()
+    }
+}
+
+
+/// canonical collection of LR(0) item sets, the start state is 0
+pub struct Lr0Automaton {
+    states: Vec<Lr0State>,
+}
+
+impl Lr0Automaton {
+
+    pub fn new(cfg: &Cfg) -> Self {
+        let start_kernel: Vec<Lr0Item> = cfg
+            .get_rules_by_origin(cfg.START())
+            .map(|(rule_id, _)| Lr0Item::new(rule_id, 0))
+            .collect();
+
+        let mut states: Vec<Lr0State> = vec![Lr0State {
+            items: Self::closure(cfg, &start_kernel),
+            kernel: start_kernel.clone(),
+            transitions: Vec::new(),
+            accessing_symbol: None,
+        }];
+        let mut state_ids: HashMap<Vec<Lr0Item>, usize> = HashMap::from([(start_kernel, 0)]);
+
+        // states are processed in the order of creation
+        let mut state_id: usize = 0;
+        while state_id < states.len() {
+
+            // kernels of the successors, grouped by the symbol read
+            let mut successor_kernels: BTreeMap<Symbol, Vec<Lr0Item>> = BTreeMap::new();
+            for item in &states[state_id].items {
+                if let Some(symbol) = item.next_symbol(cfg) {
+                    successor_kernels.entry(symbol).or_default().push(item.advance());
+                }
+            }
+
+            let mut transitions: Vec<(Symbol, usize)> = Vec::with_capacity(successor_kernels.len());
+            for (symbol, mut kernel) in successor_kernels {
+                kernel.sort();
+                kernel.dedup();
+
+                let target_id: usize = match state_ids.get(&kernel) {
+                    Some(&target_id) => target_id,
+                    None => {
+                        let target_id: usize = states.len();
+                        states.push(Lr0State {
+                            items: Self::closure(cfg, &kernel),
+                            kernel: kernel.clone(),
+                            transitions: Vec::new(),
+                            accessing_symbol: Some(symbol),
+                        });
+                        state_ids.insert(kernel, target_id);
+                        target_id
+                    }
+                };
+                transitions.push((symbol, target_id));
+            }
+            states[state_id].transitions = transitions;
+
+            state_id += 1;
+        }
+
+        Lr0Automaton { states }
+    }
+
+    /// the kernel followed by the items (rule, 0) of the non-terminals that can appear after a dot
+    pub fn closure(cfg: &Cfg, kernel: &[Lr0Item]) -> Vec<Lr0Item> {
+        let mut items: Vec<Lr0Item> = kernel.to_vec();
+        let mut added_non_terminals: BitSet<UINT> = BitSet::new_filled(false, usize::from(cfg.nbr_non_terminals()));
+
+        let mut item_index: usize = 0;
+        while item_index < items.len() {
+            if let Some(symbol) = items[item_index].next_symbol(cfg)
+                && cfg.is_non_terminal(symbol)
+                && !added_non_terminals.contains(usize::from(symbol.id)) {
+                added_non_terminals.insert(usize::from(symbol.id));
+                items.extend(cfg.get_rules_by_origin(symbol).map(|(rule_id, _)| Lr0Item::new(rule_id, 0)));
+            }
+            item_index += 1;
+        }
+
+        items
+    }
+
+    pub fn nbr_states(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn get_state(&self, state_id: usize) -> &Lr0State {
+        &self.states[state_id]
+    }
+
+    pub fn all_states(&self) -> impl Iterator<Item = (usize, &Lr0State)> {
+        self.states.iter().enumerate()
+    }
+
+    /// the state reached by reading symbol from the state, if any
+    pub fn goto(&self, state_id: usize, symbol: Symbol) -> Option<usize> {
+        let transitions: &[(Symbol, usize)] = &self.states[state_id].transitions;
+        transitions
+            .binary_search_by_key(&symbol, |&(transition_symbol, _)| transition_symbol)
+            .ok()
+            .map(|index| transitions[index].1)
+    }
+
+    /// the state reached by reading the word from the state, if any
+    pub fn goto_word(&self, state_id: usize, word: &[Symbol]) -> Option<usize> {
+        word.iter().try_fold(state_id, |current_state_id, &symbol| self.goto(current_state_id, symbol))
+    }
+
+    /// for each state, the states that have a transition to it
+    pub fn compute_predecessors(&self) -> Vec<Vec<usize>> {
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); self.nbr_states()];
+        for (state_id, state) in self.all_states() {
+            for &(_, target_id) in &state.transitions {
+                predecessors[target_id].push(state_id);
+            }
+        }
+        predecessors
+    }
+
+    pub fn repr_state(&self, cfg: &Cfg, state_id: usize) -> String {
+        let state: &Lr0State = self.get_state(state_id);
+        let mut s: String = format!("state {state_id}:\n");
+        for (index, item) in state.items.iter().enumerate() {
+            s.push_str(if index < state.kernel.len() {"    "} else {"  + "});
+            s.push_str(&item.repr(cfg));
+            s.push('\n');
+        }
+        for &(symbol, target_id) in &state.transitions {
+            s.push_str(&format!("    {} => {}\n", cfg.repr_symbol(symbol), target_id));
+        }
+        s
+    }
+
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        (0..self.nbr_states()).map(|state_id| self.repr_state(cfg, state_id)).collect()
+    }
+}
+
+impl LRAutomaton for Lr0Automaton {
+    fn nbr_states(&self) -> usize {
+        self.states.len()
+    }
+
+    fn transitions(&self, state_id: usize) -> &[(Symbol, usize)] {
+        &self.states[state_id].transitions
+    }
+
+    fn core_items(&self, state_id: usize) -> &[Lr0Item] {
+        &self.states[state_id].items
+    }
+}
+
+
+/// LR(0) table: the complete items are reduced whatever the lookahead, except the rules of START which are
+/// only accepted on END
+/// most grammars have conflicts in this table, it is mostly meant for the GLR parser
+pub fn generate_LR0_table(cfg: &Cfg) -> LR1Table {
+    let automaton: Lr0Automaton = Lr0Automaton::new(cfg);
+
+    let mut all_terminals: BitSet<UINT> = BitSet::new_filled(false, usize::from(cfg.nbr_terminals())+1);
+    for terminal in cfg.all_terminals() {
+        all_terminals.insert(usize::from(cfg.to_local_nosymbol(terminal)));
+    }
+    let mut end_only: BitSet<UINT> = BitSet::new_filled(false, usize::from(cfg.nbr_terminals())+1);
+    end_only.insert(usize::from(cfg.to_local_nosymbol(cfg.END())));
+
+    let lookaheads: Vec<Vec<(CfgRuleIdx, BitSet<UINT>)>> = automaton
+        .all_states()
+        .map(|(_, state)| {
+            state
+                .complete_items(cfg)
+                .map(|item| {
+                    let is_start_rule: bool = cfg.get_rule_by_id(item.rule_id).origin == cfg.START();
+                    (item.rule_id, if is_start_rule {end_only.clone()} else {all_terminals.clone()})
+                })
+                .collect()
+        })
+        .collect();
+
+    build_LR1_table(cfg, &automaton, &lookaheads)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::create_expression_grammar;
+
+    #[test]
+    fn expression_grammar_automaton() {
+        let cfg = create_expression_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+
+        let automaton = Lr0Automaton::new(&cfg);
+        println!("{}", automaton.repr(&cfg));
+
+        // the usual 12 states, plus the state reached after reading END
+        assert_eq!(automaton.nbr_states(), 13);
+
+        let start_state = automaton.get_state(0);
+        assert_eq!(start_state.kernel.len(), 1);
+        assert_eq!(start_state.items.len(), 7);
+
+        let after_e = automaton.goto(0, S("E")).unwrap();
+        let kernel: Vec<String> = automaton.get_state(after_e).kernel.iter().map(|item| item.repr(&cfg)).collect();
+        assert_eq!(kernel, vec!["START --> E . END", "E --> E . + T"]);
+
+        // reading "( id" and "( ( id" leads to the same state
+        let after_id = automaton.goto_word(0, &[S("("), S("id")]).unwrap();
+        assert_eq!(automaton.goto_word(0, &[S("("), S("("), S("id")]), Some(after_id));
+        assert_eq!(automaton.goto(0, S("id")), Some(after_id));
+        assert_eq!(automaton.get_state(after_id).complete_items(&cfg).count(), 1);
+        assert_eq!(automaton.goto(after_id, S("+")), None);
+
+        let accepting = automaton.goto_word(0, &[S("E"), S("END")]).unwrap();
+        assert_eq!(automaton.get_state(accepting).accessing_symbol, Some(cfg.END()));
+        assert_eq!(automaton.compute_predecessors()[accepting], vec![after_e]);
+    }
+}
@@ -0,0 +1,82 @@
+use super::LR0_generator::Lr0Automaton;
+use super::{build_LR1_table, LR1Table};
+use crate::datastructures::bitset::BitSet;
+use crate::formal_language::*;
+use crate::UINT;
+
+
+/// SLR(1) table: each complete item A -> α . is reduced on the FOLLOW set of A
+pub fn generate_SLR1_table(cfg: &Cfg) -> LR1Table {
+    let automaton: Lr0Automaton = Lr0Automaton::new(cfg);
+    build_LR1_table(cfg, &automaton, &compute_SLR1_lookaheads(cfg, &automaton))
+}
+
+pub fn compute_SLR1_lookaheads(cfg: &Cfg, automaton: &Lr0Automaton) -> Vec<Vec<(CfgRuleIdx, BitSet<UINT>)>> {
+    automaton
+        .all_states()
+        .map(|(_, state)| {
+            state
+                .complete_items(cfg)
+                .map(|item| (item.rule_id, cfg.get_follow_set(cfg.get_rule_by_id(item.rule_id).origin).clone()))
+                .collect()
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar};
+    use crate::parsing::LR_parsing::LR1_generators::{LR1Action, LR1ConflictKind};
+
+    #[test]
+    fn expression_grammar_is_SLR1() {
+        let cfg = create_expression_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+
+        let table = generate_SLR1_table(&cfg);
+        println!("{}", table.repr(&cfg));
+        assert!(!table.has_conflicts());
+
+        let LR1Action::Shift(after_id) = table.get_action(0, S("id")) else { panic!("id should be shifted") };
+        assert!(matches!(table.get_action(after_id, S("+")), LR1Action::Reduce(_)));
+        assert_eq!(table.get_action(after_id, S("(")), LR1Action::Error);
+        assert_eq!(table.expected_terminals(after_id).count(), 4);
+
+        let after_e = table.get_goto(0, S("E")).unwrap();
+        let LR1Action::Shift(after_end) = table.get_action(after_e, cfg.END()) else { panic!("END should be shifted") };
+        assert!(matches!(table.get_action(after_end, cfg.END()), LR1Action::Accept(_)));
+    }
+
+    #[test]
+    fn assignment_grammar_is_not_SLR1() {
+        // S -> L = R | R, L -> * R | id, R -> L
+        let cfg = create_grammar(
+            &["=", "*", "id"],
+            &["S", "L", "R"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["L", "=", "R"]),
+                ("S", &["R"]),
+                ("L", &["*", "R"]),
+                ("L", &["id"]),
+                ("R", &["L"]),
+            ],
+        );
+
+        let table = generate_SLR1_table(&cfg);
+        println!("{}", table.repr_conflicts(&cfg));
+
+        assert_eq!(table.conflicts().len(), 1);
+        let conflict = &table.conflicts()[0];
+        assert_eq!(conflict.kind(), LR1ConflictKind::ShiftReduce);
+        assert_eq!(cfg.repr_symbol(conflict.lookahead), "=");
+        assert_eq!(conflict.repr(&cfg), 
+            format!("ShiftReduce conflict in state {} on lookahead =:\n    shift: S --> L . = R\n    reduce: R --> L\n", conflict.state_id));
+
+        // the shift is kept in the table, both actions are still available
+        assert!(matches!(table.get_action(conflict.state_id, conflict.lookahead), LR1Action::Shift(_)));
+        assert_eq!(table.get_all_actions(conflict.state_id, conflict.lookahead).len(), 2);
+    }
+}
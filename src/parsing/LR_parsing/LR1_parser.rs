@@ -0,0 +1,278 @@
+use super::LR1_generators::{LR1Action, LR1Table};
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use crate::parsing::parse_tree::*;
+use crate::parsing::{run_until_end, BuildingParser, ParserError};
+
+
+/// shift/reduce parser driven by the table of any LR(1) generator
+pub struct LR1Parser<'a> {
+    cfg: &'a Cfg,
+    table: &'a LR1Table,
+}
+
+impl <'a> LR1Parser<'a> {
+
+    pub fn new(cfg: &'a Cfg, table: &'a LR1Table) -> Self {
+        LR1Parser { cfg, table }
+    }
+
+    pub fn get_cfg(&self) -> &'a Cfg {
+        self.cfg
+    }
+
+    pub fn get_table(&self) -> &'a LR1Table {
+        self.table
+    }
+}
+
+impl <'a> BuildingParser for LR1Parser<'a> {
+
+    fn build_from_iter<B: ParseBuilder, IT: Iterator<Item = Token>>(&self, token_stream: IT, builder: B)
+    -> Result<B, MachineError<ParserError>> {
+        let mut runner: LR1ParserRunner<'_, 'a, B> = LR1ParserRunner::with_builder(self, builder);
+        run_until_end(self.cfg, &mut runner, token_stream)?;
+        Ok(runner.builder)
+    }
+}
+
+
+/// reads one token at each update, and does every reduction possible before shifting it
+/// the parse tree (or any other value given by the builder) is built bottom-up, its root is only set when the
+/// input is accepted
+pub struct LR1ParserRunner<'parser, 'a, B = ParseTree>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    parser: &'parser LR1Parser<'a>,
+
+    // states of the automaton, with the value built for the symbol read to enter them
+    stack: Vec<(usize, Option<B::Value>)>,
+
+    builder: B,
+
+    run_info: RunInfo,
+}
+
+impl <'parser, 'a> LR1ParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    pub fn new(parser: &'parser LR1Parser<'a>) -> Self {
+        LR1ParserRunner::with_builder(parser, ParseTree::new())
+    }
+}
+
+impl <'parser, 'a, B> LR1ParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    pub fn with_builder(parser: &'parser LR1Parser<'a>, builder: B) -> Self {
+        LR1ParserRunner {
+            parser,
+            stack: vec![(0, None)],
+            builder,
+            run_info: RunInfo::Ready,
+        }
+    }
+
+    /// the states of the automaton in the stack, the current state is the last one
+    pub fn get_state_stack(&self) -> impl Iterator<Item = usize> + '_ {
+        self.stack.iter().map(|&(state_id, _)| state_id)
+    }
+
+    fn current_state(&self) -> usize {
+        // unwrap is safe because the start state is never popped
+        self.stack.last().unwrap().0
+    }
+
+    fn unexpected_token(&self, token: &Token) -> MachineError<ParserError> {
+        let expected: Vec<Symbol> = self.parser.table.expected_terminals(self.current_state()).collect();
+        MachineError::Other { other_err: ParserError::UnexpectedToken { token: token.clone(), expected } }
+    }
+
+    /// replaces the symbols of the replacement of the rule on top of the stack by its origin
+    fn reduce(&mut self, rule_id: CfgRuleIdx) -> B::Value {
+        let cfg: &Cfg = self.parser.cfg;
+        let rule: &CfgRule = cfg.get_rule_by_id(rule_id);
+
+        let children: Vec<B::Value> = self.stack
+            .drain(self.stack.len()-rule.replacement_size()..)
+            // unwrap is safe because only the start state has no value
+            .map(|(_, value)| value.unwrap())
+            .collect();
+        self.builder.reduce(rule_id, rule.origin, children)
+    }
+
+    fn accept(&mut self, rule_id: CfgRuleIdx) {
+        let root: B::Value = self.reduce(rule_id);
+        self.builder.accept(root);
+        self.run_info = RunInfo::Finished;
+    }
+
+    /// does the reductions for the lookahead, returns the action that stopped them
+    fn reduce_all(&mut self, lookahead: Symbol) -> LR1Action {
+        loop {
+            let action: LR1Action = self.parser.table.get_action(self.current_state(), lookahead);
+            let LR1Action::Reduce(rule_id) = action else {
+                return action;
+            };
+
+            let value: B::Value = self.reduce(rule_id);
+            let origin: Symbol = self.parser.cfg.get_rule_by_id(rule_id).origin;
+            // unwrap is safe because reductions are only possible in states where the origin was expected
+            let target_id: usize = self.parser.table.get_goto(self.current_state(), origin).unwrap();
+            self.stack.push((target_id, Some(value)));
+        }
+    }
+}
+
+impl <'parser, 'a, B> Machine<Token, B, ParserError> for LR1ParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    fn clear(&mut self) {
+        self.stack = vec![(0, None)];
+        self.builder.clear();
+        self.run_info = RunInfo::Ready;
+    }
+
+    fn get_run_info(&self) -> &RunInfo {
+        &self.run_info
+    }
+
+    fn update(&mut self, token: &Token) -> Result<(), MachineError<ParserError>> {
+        if self.is_finished() {
+            return Err(MachineError::Finished);
+        }
+
+        let cfg: &Cfg = self.parser.cfg;
+        if token.token_type.id >= cfg.nbr_symbols() || !cfg.is_terminal(token.token_type) {
+            return Err(ParserError::InvalidTokenType { token: token.clone() }.into());
+        }
+
+        self.run_info = RunInfo::Running;
+
+        match self.reduce_all(token.token_type) {
+            LR1Action::Shift(target_id) => {
+                let value: B::Value = self.builder.shift(token);
+                self.stack.push((target_id, Some(value)));
+            }
+            // a rule of START that doesn't end with END, like START -> ε, is accepted before END is shifted
+            LR1Action::Accept(rule_id) if token.token_type == cfg.END() => {
+                self.accept(rule_id);
+                return Ok(());
+            }
+            _ => return Err(self.unexpected_token(token)),
+        }
+
+        // nothing can be read after END, so the rule of START is reduced right away
+        if token.token_type == cfg.END() {
+            let LR1Action::Accept(rule_id) = self.reduce_all(cfg.END()) else {
+                return Err(self.unexpected_token(token));
+            };
+            self.accept(rule_id);
+        }
+
+        Ok(())
+    }
+
+    fn get_state(&self) -> &B {
+        &self.builder
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_nullable_start_grammar, tokens};
+    use crate::parsing::LR_parsing::LR1_generators::SLR1_generator::generate_SLR1_table;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_generators::CLR1_generator::generate_CLR1_table;
+    use crate::parsing::LR_parsing::LR1_generators::IELR1_generator::generate_IELR1_table;
+
+    #[test]
+    fn parse_expression_with_every_table() {
+        let cfg = create_expression_grammar();
+        let input: Vec<Token> = tokens(&cfg, &["id", "+", "id", "*", "(", "id", "+", "id", ")"]);
+
+        let mut reprs: Vec<String> = Vec::new();
+        for table in [generate_SLR1_table(&cfg), generate_LALR1_table(&cfg), generate_CLR1_table(&cfg), generate_IELR1_table(&cfg)] {
+            let parser = LR1Parser::new(&cfg, &table);
+            let parse_tree = parser.parse_from_iter(input.clone().into_iter()).unwrap();
+            reprs.push(parse_tree.repr(&cfg));
+        }
+        println!("{}", reprs[0]);
+        assert!(reprs.iter().all(|repr| *repr == reprs[0]));
+
+        // id + (id * (...)): the root expression is a sum
+        let lines: Vec<&str> = reprs[0].lines().collect();
+        assert_eq!(lines[0], "START --> E END");
+        assert_eq!(lines[1], "    E --> E + T");
+        assert_eq!(lines.len(), 25);
+    }
+
+    #[test]
+    fn syntax_error() {
+        let cfg = create_expression_grammar();
+        let table = generate_LALR1_table(&cfg);
+        let parser = LR1Parser::new(&cfg, &table);
+
+        let result = parser.parse_from_iter(tokens(&cfg, &["(", "id", "+", "*"]).into_iter());
+        let Err(MachineError::Other { other_err: err @ ParserError::UnexpectedToken { .. } }) = result else {
+            panic!("the parse should fail on *");
+        };
+        println!("{}", err.repr(&cfg));
+        assert_eq!(err.repr(&cfg), "Unexpected token \"*\" at line 0, column 3: found *, expected one of {(, id}");
+
+        // the input ends too early
+        let result = parser.parse_from_iter(tokens(&cfg, &["id", "+"]).into_iter());
+        let Err(MachineError::Other { other_err: ParserError::UnexpectedToken { token, .. } }) = result else {
+            panic!("the parse should fail on END");
+        };
+        assert_eq!(token.token_type, cfg.END());
+        assert_eq!(token.column, 2);
+    }
+
+    #[test]
+    fn token_by_token() {
+        let cfg = create_expression_grammar();
+        let table = generate_CLR1_table(&cfg);
+        let parser = LR1Parser::new(&cfg, &table);
+        let mut runner = LR1ParserRunner::new(&parser);
+
+        assert!(runner.is_ready());
+        for token in tokens(&cfg, &["id", "*", "id"]) {
+            runner.update(&token).unwrap();
+        }
+        assert!(runner.is_running());
+        // nothing is reduced before the lookahead is known
+        assert_eq!(runner.get_state_stack().count(), 4);
+
+        runner.update(&tokens(&cfg, &["END"])[0]).unwrap();
+        assert!(runner.is_finished());
+        assert!(runner.get_state().root().is_some());
+        assert!(matches!(runner.update(&tokens(&cfg, &["id"])[0]), Err(MachineError::Finished)));
+
+        runner.clear();
+        assert!(runner.is_ready());
+        assert!(runner.get_state().root().is_none());
+    }
+
+    #[test]
+    fn epsilon_rule_of_START() {
+        let cfg = create_nullable_start_grammar();
+        let table = generate_LALR1_table(&cfg);
+        let parser = LR1Parser::new(&cfg, &table);
+
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &[]).into_iter()).unwrap();
+        assert_eq!(parse_tree.repr(&cfg), "START -->\n");
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &["a", "a"]).into_iter()).unwrap();
+        assert!(parse_tree.repr(&cfg).starts_with("START --> a a END\n"));
+        assert!(parser.parse_from_iter(tokens(&cfg, &["a"]).into_iter()).is_err());
+    }
+}
@@ -0,0 +1,572 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use super::LR1_generators::LR0_generator::Lr0Item;
+use super::LR1_generators::{LR1Action, LR1ConflictKind};
+use crate::datastructures::flat_table::RectFlatTable;
+use crate::formal_language::k_sets::*;
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use crate::parsing::parse_tree::*;
+use crate::parsing::{run_until_end, BuildingParser, ParserError};
+
+
+/// LR(0) item with a lookahead word of k terminals
+pub type LRkItem = (Lr0Item, TerminalWordIdx);
+
+
+/// several actions are possible in a state for the same lookahead word
+#[derive(Debug, Clone)]
+pub struct LRkConflict {
+    pub state_id: usize,
+    pub lookahead: Vec<Symbol>,
+    pub actions: Vec<LR1Action>,
+    // the items of the state responsible for the actions
+    pub items: Vec<Lr0Item>,
+}
+
+impl LRkConflict {
+    pub fn kind(&self) -> LR1ConflictKind {
+        if self.actions.iter().any(|action| matches!(action, LR1Action::Shift(_))) {
+            LR1ConflictKind::ShiftReduce
+        }
+        else {
+            LR1ConflictKind::ReduceReduce
+        }
+    }
+
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let lookahead: Vec<&str> = self.lookahead.iter().map(|&symbol| cfg.repr_symbol(symbol)).collect();
+        let mut s: String = format!("{:?} conflict in state {} on lookahead \"{}\":\n",
+            self.kind(), self.state_id, lookahead.join(" "));
+        for item in &self.items {
+            if item.is_complete(cfg) {
+                s.push_str(&format!("    reduce: {}\n", cfg.repr_rule(item.rule_id)));
+            }
+            else {
+                s.push_str(&format!("    shift: {}\n", item.repr(cfg)));
+            }
+        }
+        s
+    }
+}
+
+
+#[derive(Error, Debug)]
+pub enum LRkGeneratorError {
+    #[error("The grammar is not LR({k}), {} conflicts found", conflicts.len())]
+    NotLRk{k: usize, conflicts: Vec<LRkConflict>},
+    #[error("LR(0) tables have no lookahead, use the LR(0) automaton instead")]
+    NoLookahead,
+}
+
+impl LRkGeneratorError {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        match self {
+            LRkGeneratorError::NotLRk { conflicts, .. } => {
+                let mut s: String = format!("{self}\n");
+                for conflict in conflicts {
+                    s.push_str(&conflict.repr(cfg));
+                }
+                s
+            }
+            LRkGeneratorError::NoLookahead => format!("{self}\n"),
+        }
+    }
+}
+
+// --------------------------------------------
+
+/// ACTION and GOTO tables of the canonical LR(k) automaton
+/// the actions are indexed by lookahead words of exactly k terminals, the input being padded with END
+pub struct LRkTable {
+    k: usize,
+    // the lookahead words of the actions
+    interner: TerminalWordInterner,
+    actions: Vec<HashMap<TerminalWordIdx, LR1Action>>,
+    // goto_table[state][non-terminal]
+    goto_table: RectFlatTable<Option<usize>, u16>,
+}
+
+impl LRkTable {
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn nbr_states(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn get_action(&self, state_id: usize, lookahead: &[Symbol]) -> LR1Action {
+        self.interner
+            .get_id(lookahead)
+            .and_then(|word_id| self.actions[state_id].get(&word_id).copied())
+            .unwrap_or(LR1Action::Error)
+    }
+
+    pub fn get_goto(&self, state_id: usize, non_terminal: Symbol) -> Option<usize> {
+        self.goto_table[u16::try_from(state_id).unwrap()][usize::from(non_terminal.id)]
+    }
+
+    /// the lookahead words that don't lead to an error in the state
+    pub fn expected_lookaheads(&self, state_id: usize) -> impl Iterator<Item = &[Symbol]> + '_ {
+        self.actions[state_id].keys().map(|&word_id| self.interner.get_word(word_id))
+    }
+
+    /// one line per state and lookahead word that doesn't lead to an error
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for state_id in 0..self.nbr_states() {
+            s.push_str(&format!("state {state_id}:\n"));
+            let actions: BTreeMap<String, LR1Action> = self.actions[state_id]
+                .iter()
+                .map(|(&word_id, &action)| (self.interner.repr_word(cfg, word_id), action))
+                .collect();
+            for (lookahead, action) in actions {
+                s.push_str(&format!("    {}: {}\n", lookahead, action.repr(cfg)));
+            }
+            for non_terminal in cfg.all_non_terminals() {
+                if let Some(target_id) = self.get_goto(state_id, non_terminal) {
+                    s.push_str(&format!("    {}: goto {}\n", cfg.repr_symbol(non_terminal), target_id));
+                }
+            }
+        }
+        s
+    }
+}
+
+
+struct LRkState {
+    items: Vec<LRkItem>,
+    // sorted by symbol
+    transitions: Vec<(Symbol, usize)>,
+}
+
+/// builds the canonical LR(k) automaton and its table
+/// returns every conflict found if the grammar is not LR(k)
+pub fn generate_LRk_table(cfg: &Cfg, k: usize) -> Result<LRkTable, LRkGeneratorError> {
+    if k == 0 {
+        return Err(LRkGeneratorError::NoLookahead);
+    }
+
+    let k_sets: Rc<KSets> = cfg.get_k_sets(k);
+    let end_word: TerminalWordIdx = k_sets.intern(&vec![cfg.END(); k]);
+
+    // FIRST_k sets of the symbols after the dot, by item
+    let mut first_k_sets: HashMap<Lr0Item, TerminalWordSet> = HashMap::new();
+    let mut first_k_set = |item: Lr0Item| -> TerminalWordSet {
+        first_k_sets.entry(item).or_insert_with(|| k_sets.first_k_set_of_word(item.remaining_symbols(cfg))).clone()
+    };
+
+    let start_kernel: Vec<LRkItem> = cfg
+        .get_rules_by_origin(cfg.START())
+        .map(|(rule_id, _)| (Lr0Item::new(rule_id, 0), end_word))
+        .collect();
+
+    let mut states: Vec<LRkState> = vec![LRkState {
+        items: lrk_closure(cfg, &k_sets, &start_kernel, &mut first_k_set),
+        transitions: Vec::new(),
+    }];
+    let mut state_ids: HashMap<Vec<LRkItem>, usize> = HashMap::from([(start_kernel, 0)]);
+
+    // states are processed in the order of creation
+    let mut state_id: usize = 0;
+    while state_id < states.len() {
+        let mut successor_kernels: BTreeMap<Symbol, Vec<LRkItem>> = BTreeMap::new();
+        for &(item, lookahead) in &states[state_id].items {
+            if let Some(symbol) = item.next_symbol(cfg) {
+                successor_kernels.entry(symbol).or_default().push((item.advance(), lookahead));
+            }
+        }
+
+        let mut transitions: Vec<(Symbol, usize)> = Vec::with_capacity(successor_kernels.len());
+        for (symbol, mut kernel) in successor_kernels {
+            kernel.sort();
+            kernel.dedup();
+
+            let target_id: usize = match state_ids.get(&kernel) {
+                Some(&target_id) => target_id,
+                None => {
+                    let target_id: usize = states.len();
+                    states.push(LRkState {
+                        items: lrk_closure(cfg, &k_sets, &kernel, &mut first_k_set),
+                        transitions: Vec::new(),
+                    });
+                    state_ids.insert(kernel, target_id);
+                    target_id
+                }
+            };
+            transitions.push((symbol, target_id));
+        }
+        states[state_id].transitions = transitions;
+
+        state_id += 1;
+    }
+
+    let nbr_non_terminals: usize = usize::from(cfg.nbr_non_terminals());
+    let mut actions: Vec<HashMap<TerminalWordIdx, LR1Action>> = Vec::with_capacity(states.len());
+    let mut goto_table: Vec<Option<usize>> = vec![None; states.len()*nbr_non_terminals];
+    let mut conflicts: Vec<LRkConflict> = Vec::new();
+
+    for (state_id, state) in states.iter().enumerate() {
+        // every action possible for each lookahead word, with the items responsible for them
+        let mut state_actions: BTreeMap<TerminalWordIdx, Vec<(LR1Action, Lr0Item)>> = BTreeMap::new();
+
+        for &(item, lookahead) in &state.items {
+            match item.next_symbol(cfg) {
+                None => {
+                    let action: LR1Action = if cfg.get_rule_by_id(item.rule_id).origin == cfg.START() {
+                        LR1Action::Accept(item.rule_id)
+                    }
+                    else {
+                        LR1Action::Reduce(item.rule_id)
+                    };
+                    state_actions.entry(lookahead).or_default().push((action, item));
+                }
+                Some(symbol) if cfg.is_terminal(symbol) => {
+                    // unwrap is safe because the transitions are built from the items
+                    let target_id: usize = state.transitions[state.transitions
+                        .binary_search_by_key(&symbol, |&(transition_symbol, _)| transition_symbol).unwrap()].1;
+                    let shifted_words: TerminalWordSet =
+                        k_sets.concatenate_sets(&first_k_set(item), &TerminalWordSet::from([lookahead]));
+                    for word in shifted_words {
+                        state_actions.entry(word).or_default().push((LR1Action::Shift(target_id), item));
+                    }
+                }
+                Some(symbol) => {
+                    let target_id: usize = state.transitions[state.transitions
+                        .binary_search_by_key(&symbol, |&(transition_symbol, _)| transition_symbol).unwrap()].1;
+                    goto_table[state_id*nbr_non_terminals + usize::from(symbol.id)] = Some(target_id);
+                }
+            }
+        }
+
+        let mut cells: HashMap<TerminalWordIdx, LR1Action> = HashMap::with_capacity(state_actions.len());
+        for (word, mut cell_actions) in state_actions {
+            // shift first, then reductions by increasing rule id
+            cell_actions.sort();
+            cell_actions.dedup();
+
+            let mut distinct_actions: Vec<LR1Action> = cell_actions.iter().map(|&(action, _)| action).collect();
+            distinct_actions.dedup();
+            if distinct_actions.len() > 1 {
+                let mut items: Vec<Lr0Item> = cell_actions.iter().map(|&(_, item)| item).collect();
+                items.sort();
+                items.dedup();
+                conflicts.push(LRkConflict {
+                    state_id,
+                    lookahead: k_sets.get_word(word),
+                    actions: distinct_actions.clone(),
+                    items,
+                });
+            }
+            cells.insert(word, distinct_actions[0]);
+        }
+        actions.push(cells);
+    }
+
+    if !conflicts.is_empty() {
+        return Err(LRkGeneratorError::NotLRk { k, conflicts });
+    }
+
+    let height: u16 = u16::try_from(states.len()).expect("the number of states must be storable in the u16 type");
+    Ok(LRkTable {
+        k,
+        interner: k_sets.interner(),
+        actions,
+        goto_table: RectFlatTable::new(goto_table, height, nbr_non_terminals),
+    })
+}
+
+/// the kernel followed by the items (rule, 0) of the non-terminals after a dot, with every lookahead
+/// FIRST_k(β lookahead) for the items A -> α . B β
+fn lrk_closure(cfg: &Cfg, k_sets: &KSets, kernel: &[LRkItem], first_k_set: &mut impl FnMut(Lr0Item) -> TerminalWordSet)
+-> Vec<LRkItem> {
+    let mut items: Vec<LRkItem> = kernel.to_vec();
+    let mut added_items: HashSet<LRkItem> = kernel.iter().copied().collect();
+
+    let mut item_index: usize = 0;
+    while item_index < items.len() {
+        let (item, lookahead) = items[item_index];
+        item_index += 1;
+
+        let Some(symbol) = item.next_symbol(cfg) else { continue };
+        if !cfg.is_non_terminal(symbol) {
+            continue;
+        }
+
+        let new_lookaheads: TerminalWordSet =
+            k_sets.concatenate_sets(&first_k_set(item.advance()), &TerminalWordSet::from([lookahead]));
+        for (rule_id, _) in cfg.get_rules_by_origin(symbol) {
+            for &new_lookahead in &new_lookaheads {
+                let new_item: LRkItem = (Lr0Item::new(rule_id, 0), new_lookahead);
+                if added_items.insert(new_item) {
+                    items.push(new_item);
+                }
+            }
+        }
+    }
+
+    items
+}
+
+// --------------------------------------------
+
+pub struct LRkParser<'a> {
+    cfg: &'a Cfg,
+    table: &'a LRkTable,
+}
+
+impl <'a> LRkParser<'a> {
+
+    pub fn new(cfg: &'a Cfg, table: &'a LRkTable) -> Self {
+        LRkParser { cfg, table }
+    }
+
+    pub fn get_cfg(&self) -> &'a Cfg {
+        self.cfg
+    }
+}
+
+impl <'a> BuildingParser for LRkParser<'a> {
+
+    fn build_from_iter<B: ParseBuilder, IT: Iterator<Item = Token>>(&self, token_stream: IT, builder: B)
+    -> Result<B, MachineError<ParserError>> {
+        let mut runner: LRkParserRunner<'_, 'a, B> = LRkParserRunner::with_builder(self, builder);
+        run_until_end(self.cfg, &mut runner, token_stream)?;
+        Ok(runner.builder)
+    }
+}
+
+
+/// reads one token at each update into a buffer of k tokens, and acts once the buffer is full
+/// the parse tree (or any other value given by the builder) is built bottom-up, its root is only set when the
+/// input is accepted
+pub struct LRkParserRunner<'parser, 'a, B = ParseTree>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    parser: &'parser LRkParser<'a>,
+
+    // states of the automaton, with the value built for the symbol read to enter them
+    stack: Vec<(usize, Option<B::Value>)>,
+    // the tokens read but not shifted yet
+    lookahead_buffer: VecDeque<Token>,
+
+    builder: B,
+
+    run_info: RunInfo,
+}
+
+impl <'parser, 'a> LRkParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    pub fn new(parser: &'parser LRkParser<'a>) -> Self {
+        LRkParserRunner::with_builder(parser, ParseTree::new())
+    }
+}
+
+impl <'parser, 'a, B> LRkParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    pub fn with_builder(parser: &'parser LRkParser<'a>, builder: B) -> Self {
+        LRkParserRunner {
+            parser,
+            stack: vec![(0, None)],
+            lookahead_buffer: VecDeque::with_capacity(parser.table.k()),
+            builder,
+            run_info: RunInfo::Ready,
+        }
+    }
+
+    fn current_state(&self) -> usize {
+        // unwrap is safe because the start state is never popped
+        self.stack.last().unwrap().0
+    }
+
+    /// the first token of the buffer that isn't the prefix of an expected lookahead,
+    /// with the terminals that could have been at its place
+    fn unexpected_token(&self) -> MachineError<ParserError> {
+        let buffer: Vec<Symbol> = self.lookahead_buffer.iter().map(|token| token.token_type).collect();
+        let common_prefix_size = |lookahead: &[Symbol]|
+            lookahead.iter().zip(&buffer).take_while(|(symbol1, symbol2)| symbol1 == symbol2).count();
+
+        let error_position: usize = self.parser.table
+            .expected_lookaheads(self.current_state())
+            .map(common_prefix_size)
+            .max()
+            .unwrap_or(0);
+        let mut expected: Vec<Symbol> = self.parser.table
+            .expected_lookaheads(self.current_state())
+            .filter(|lookahead| common_prefix_size(lookahead) == error_position)
+            .map(|lookahead| lookahead[error_position])
+            .collect();
+        expected.sort();
+        expected.dedup();
+
+        MachineError::Other { other_err: ParserError::UnexpectedToken {
+            token: self.lookahead_buffer[error_position].clone(),
+            expected,
+        }}
+    }
+
+    /// replaces the symbols of the replacement of the rule on top of the stack by its origin
+    fn reduce(&mut self, rule_id: CfgRuleIdx) -> B::Value {
+        let rule: &CfgRule = self.parser.cfg.get_rule_by_id(rule_id);
+        let children: Vec<B::Value> = self.stack
+            .drain(self.stack.len()-rule.replacement_size()..)
+            // unwrap is safe because only the start state has no value
+            .map(|(_, value)| value.unwrap())
+            .collect();
+        self.builder.reduce(rule_id, rule.origin, children)
+    }
+}
+
+impl <'parser, 'a, B> Machine<Token, B, ParserError> for LRkParserRunner<'parser, 'a, B>
+where
+    'a: 'parser,
+    B: ParseBuilder,
+{
+    fn clear(&mut self) {
+        self.stack = vec![(0, None)];
+        self.lookahead_buffer.clear();
+        self.builder.clear();
+        self.run_info = RunInfo::Ready;
+    }
+
+    fn get_run_info(&self) -> &RunInfo {
+        &self.run_info
+    }
+
+    fn update(&mut self, token: &Token) -> Result<(), MachineError<ParserError>> {
+        if self.is_finished() {
+            return Err(MachineError::Finished);
+        }
+
+        let cfg: &Cfg = self.parser.cfg;
+        if token.token_type.id >= cfg.nbr_symbols() || !cfg.is_terminal(token.token_type) {
+            return Err(ParserError::InvalidTokenType { token: token.clone() }.into());
+        }
+
+        self.run_info = RunInfo::Running;
+
+        self.lookahead_buffer.push_back(token.clone());
+        if self.lookahead_buffer.len() < self.parser.table.k() {
+            return Ok(());
+        }
+
+        let lookahead: Vec<Symbol> = self.lookahead_buffer.iter().map(|token| token.token_type).collect();
+        loop {
+            match self.parser.table.get_action(self.current_state(), &lookahead) {
+                LR1Action::Shift(target_id) => {
+                    // unwrap is safe because the buffer is full
+                    let shifted_token: Token = self.lookahead_buffer.pop_front().unwrap();
+                    let value: B::Value = self.builder.shift(&shifted_token);
+                    self.stack.push((target_id, Some(value)));
+                    return Ok(());
+                }
+                LR1Action::Reduce(rule_id) => {
+                    let value: B::Value = self.reduce(rule_id);
+                    let origin: Symbol = cfg.get_rule_by_id(rule_id).origin;
+                    // unwrap is safe because reductions are only possible in states where the origin was expected
+                    let target_id: usize = self.parser.table.get_goto(self.current_state(), origin).unwrap();
+                    self.stack.push((target_id, Some(value)));
+                }
+                LR1Action::Accept(rule_id) => {
+                    let root: B::Value = self.reduce(rule_id);
+                    self.builder.accept(root);
+                    self.lookahead_buffer.clear();
+                    self.run_info = RunInfo::Finished;
+                    return Ok(());
+                }
+                LR1Action::Error => return Err(self.unexpected_token()),
+            }
+        }
+    }
+
+    fn get_state(&self) -> &B {
+        &self.builder
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar, tokens};
+    use crate::parsing::LR_parsing::LR1_generators::CLR1_generator::generate_CLR1_table;
+    use crate::parsing::LR_parsing::LR1_parser::LR1Parser;
+
+    // S -> A a b | B a c, A -> x, B -> x
+    fn create_LR2_grammar() -> Cfg {
+        create_grammar(
+            &["a", "b", "c", "x"],
+            &["S", "A", "B"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["A", "a", "b"]),
+                ("S", &["B", "a", "c"]),
+                ("A", &["x"]),
+                ("B", &["x"]),
+            ],
+        )
+    }
+
+    #[test]
+    fn not_LR1() {
+        let cfg = create_LR2_grammar();
+        let Err(err @ LRkGeneratorError::NotLRk { .. }) = generate_LRk_table(&cfg, 1) else {
+            panic!("the grammar is not LR(1)");
+        };
+        println!("{}", err.repr(&cfg));
+        let LRkGeneratorError::NotLRk { k, conflicts } = err else { unreachable!() };
+        assert_eq!(k, 1);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind(), LR1ConflictKind::ReduceReduce);
+        assert_eq!(conflicts[0].lookahead, vec![cfg.symbol_set().get_symbol_by_representation("a")]);
+
+        assert!(matches!(generate_LRk_table(&cfg, 0), Err(LRkGeneratorError::NoLookahead)));
+    }
+
+    #[test]
+    fn parse_LR2() {
+        let cfg = create_LR2_grammar();
+        let table = generate_LRk_table(&cfg, 2).unwrap();
+        println!("{}", table.repr(&cfg));
+        let parser = LRkParser::new(&cfg, &table);
+
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &["x", "a", "c"]).into_iter()).unwrap();
+        println!("{}", parse_tree.repr(&cfg));
+        let repr: String = parse_tree.repr(&cfg);
+        let lines: Vec<&str> = repr.lines().map(str::trim).collect();
+        assert_eq!(lines, vec!["START --> S END", "S --> B a c", "B --> x", "x \"x\"", "a \"a\"", "c \"c\"", "END \"\""]);
+
+        let result = parser.parse_from_iter(tokens(&cfg, &["x", "a", "a"]).into_iter());
+        let Err(MachineError::Other { other_err: err @ ParserError::UnexpectedToken { .. } }) = result else {
+            panic!("the parse should fail on the second a");
+        };
+        assert_eq!(err.repr(&cfg), "Unexpected token \"a\" at line 0, column 2: found a, expected one of {b, c}");
+    }
+
+    #[test]
+    fn same_tree_as_LR1() {
+        let cfg = create_expression_grammar();
+        let input: Vec<Token> = tokens(&cfg, &["(", "id", "+", "id", ")", "*", "id", "END"]);
+
+        let clr1_table = generate_CLR1_table(&cfg);
+        let expected: String = LR1Parser::new(&cfg, &clr1_table).parse_from_iter(input.clone().into_iter()).unwrap().repr(&cfg);
+
+        for k in 1..=3 {
+            let table = generate_LRk_table(&cfg, k).unwrap();
+            let parse_tree = LRkParser::new(&cfg, &table).parse_from_iter(input.clone().into_iter()).unwrap();
+            assert_eq!(parse_tree.repr(&cfg), expected);
+        }
+    }
+}
@@ -0,0 +1,665 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use crate::formal_language::*;
+use crate::parsing::LL_parsing::LL1_generator::LL1Conflict;
+use crate::parsing::LR_parsing::LR1_generators::LR0_generator::{Lr0Automaton, Lr0Item};
+use crate::parsing::LR_parsing::LR1_generators::{LR1Action, LR1Conflict};
+
+
+/// derivation tree of an example, whose leaves form a sentential form with a dot at the position of the conflict
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Derivation {
+    // a symbol of the sentential form, which isn't derived further
+    Leaf(Symbol),
+    Dot,
+    Node { rule_id: CfgRuleIdx, children: Vec<Derivation> },
+}
+
+impl Derivation {
+
+    /// the symbols of the leaves, with the position of the dot among them
+    pub fn sentential_form(&self) -> (Vec<Symbol>, usize) {
+        let mut symbols: Vec<Symbol> = Vec::new();
+        let mut dot: usize = 0;
+        self.collect_leaves(&mut symbols, &mut dot);
+        (symbols, dot)
+    }
+
+    fn collect_leaves(&self, symbols: &mut Vec<Symbol>, dot: &mut usize) {
+        match self {
+            Derivation::Leaf(symbol) => symbols.push(*symbol),
+            Derivation::Dot => *dot = symbols.len(),
+            Derivation::Node { children, .. } => {
+                for child in children {
+                    child.collect_leaves(symbols, dot);
+                }
+            }
+        }
+    }
+
+    pub fn repr_sentential_form(&self, cfg: &Cfg) -> String {
+        let (symbols, dot) = self.sentential_form();
+        let mut words: Vec<&str> = symbols.iter().map(|&symbol| cfg.repr_symbol(symbol)).collect();
+        words.insert(dot, ".");
+        words.join(" ")
+    }
+
+    /// the rules applied, like `E --> [ E --> [ E + E . ] + E ]`
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        match self {
+            Derivation::Leaf(symbol) => String::from(cfg.repr_symbol(*symbol)),
+            Derivation::Dot => String::from("."),
+            Derivation::Node { rule_id, children } => {
+                let mut s: String = format!("{} --> [", cfg.repr_symbol(cfg.get_rule_by_id(*rule_id).origin));
+                for child in children {
+                    s.push(' ');
+                    s.push_str(&child.repr(cfg));
+                }
+                s.push_str(" ]");
+                s
+            }
+        }
+    }
+}
+
+
+/// two derivations of examples reaching the same conflict with two different actions
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    // both derivations give the same sentential form, so the grammar is ambiguous
+    pub is_unifying: bool,
+    // the action chosen at the dot by each derivation
+    pub derivations: [(String, Derivation); 2],
+}
+
+impl Counterexample {
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = if self.is_unifying {
+            format!("unifying counterexample: {}\n", self.derivations[0].1.repr_sentential_form(cfg))
+        }
+        else {
+            String::from("non-unifying counterexample:\n")
+        };
+        for (action, derivation) in &self.derivations {
+            if self.is_unifying {
+                s.push_str(&format!("    {}: {}\n", action, derivation.repr(cfg)));
+            }
+            else {
+                s.push_str(&format!("    {}: {}\n        {}\n", action, derivation.repr_sentential_form(cfg), derivation.repr(cfg)));
+            }
+        }
+        s
+    }
+}
+
+
+// the example of one action of a conflict
+struct Example {
+    // index of the action in the conflict
+    action_id: usize,
+    action: String,
+    derivation: Derivation,
+}
+
+// a rule whose derivation is in progress while following a path: its children up to the symbol being derived
+struct Frame {
+    rule_id: CfgRuleIdx,
+    // position of the symbol being derived in the replacement
+    position: usize,
+    children: Vec<Derivation>,
+}
+
+// --------------------------------------------
+
+/// builds examples of sentential forms that reach the conflicts of the LR and LL generators
+/// each example is a shortest derivation reaching the conflict with its lookahead, found by a backward breadth-first
+/// search in the LR(0) automaton for LR conflicts, and along the rules producing each non-terminal for LL conflicts
+/// when the examples of two actions give the same sentential form, the counterexample is unifying
+pub struct CounterexampleGenerator<'a> {
+    cfg: &'a Cfg,
+    // empty_derivations[non-terminal] = the first rule of a shortest derivation of EPS, if the non-terminal is nullable
+    empty_derivations: Vec<Option<CfgRuleIdx>>,
+    // first_derivations[non-terminal][local terminal] = the first rule of a shortest derivation of a word
+    // starting with the terminal, with the position of the symbol deriving the terminal in its replacement
+    first_derivations: Vec<Vec<Option<(CfgRuleIdx, usize)>>>,
+}
+
+impl <'a> CounterexampleGenerator<'a> {
+
+    pub fn new(cfg: &'a Cfg) -> Self {
+        let (empty_derivations, empty_costs) = Self::compute_empty_derivations(cfg);
+        let first_derivations = Self::compute_first_derivations(cfg, &empty_costs);
+        CounterexampleGenerator { cfg, empty_derivations, first_derivations }
+    }
+
+    /// the number of rules of the shortest derivations of EPS, computed until nothing changes
+    fn compute_empty_derivations(cfg: &Cfg) -> (Vec<Option<CfgRuleIdx>>, Vec<usize>) {
+        let nbr_non_terminals: usize = usize::from(cfg.nbr_non_terminals());
+        let mut empty_derivations: Vec<Option<CfgRuleIdx>> = vec![None; nbr_non_terminals];
+        let mut costs: Vec<usize> = vec![usize::MAX; nbr_non_terminals];
+
+        let mut has_changed: bool = true;
+        while has_changed {
+            has_changed = false;
+            for (rule_id, rule) in cfg.all_rules() {
+                let cost: Option<usize> = rule.replacement.iter().try_fold(1, |cost: usize, &symbol|
+                    if cfg.is_non_terminal(symbol) && costs[usize::from(symbol.id)] != usize::MAX {
+                        Some(cost + costs[usize::from(symbol.id)])
+                    }
+                    else {
+                        None
+                    });
+
+                let origin: usize = usize::from(rule.origin.id);
+                if let Some(cost) = cost && cost < costs[origin] {
+                    costs[origin] = cost;
+                    empty_derivations[origin] = Some(rule_id);
+                    has_changed = true;
+                }
+            }
+        }
+
+        (empty_derivations, costs)
+    }
+
+    /// the number of rules of the shortest derivations of a word starting with each terminal,
+    /// the symbols before the one deriving the terminal being derived to EPS
+    fn compute_first_derivations(cfg: &Cfg, empty_costs: &[usize]) -> Vec<Vec<Option<(CfgRuleIdx, usize)>>> {
+        let nbr_non_terminals: usize = usize::from(cfg.nbr_non_terminals());
+        let nbr_terminals: usize = usize::from(cfg.nbr_terminals());
+        let mut first_derivations: Vec<Vec<Option<(CfgRuleIdx, usize)>>> = vec![vec![None; nbr_terminals]; nbr_non_terminals];
+        let mut costs: Vec<Vec<usize>> = vec![vec![usize::MAX; nbr_terminals]; nbr_non_terminals];
+
+        let mut has_changed: bool = true;
+        while has_changed {
+            has_changed = false;
+            for (rule_id, rule) in cfg.all_rules() {
+                let origin: usize = usize::from(rule.origin.id);
+                let mut update = |costs: &mut Vec<Vec<usize>>, terminal: usize, cost: usize, position: usize| {
+                    if cost < costs[origin][terminal] {
+                        costs[origin][terminal] = cost;
+                        first_derivations[origin][terminal] = Some((rule_id, position));
+                        has_changed = true;
+                    }
+                };
+
+                // cost of the rule and of the derivations to EPS of the symbols before the position
+                let mut prefix_cost: usize = 1;
+                for (position, &symbol) in rule.replacement.iter().enumerate() {
+                    if cfg.is_terminal(symbol) {
+                        update(&mut costs, usize::from(cfg.to_local(symbol)), prefix_cost, position);
+                        break;
+                    }
+
+                    let symbol_id: usize = usize::from(symbol.id);
+                    for terminal in 0..nbr_terminals {
+                        let symbol_cost: usize = costs[symbol_id][terminal];
+                        if symbol_cost != usize::MAX {
+                            update(&mut costs, terminal, prefix_cost + symbol_cost, position);
+                        }
+                    }
+
+                    if empty_costs[symbol_id] == usize::MAX {
+                        break;
+                    }
+                    prefix_cost += empty_costs[symbol_id];
+                }
+            }
+        }
+
+        first_derivations
+    }
+
+    // -------------------------- derivations
+
+    fn can_start_with(&self, symbol: Symbol, terminal: Symbol) -> bool {
+        symbol == terminal
+            || (self.cfg.is_non_terminal(symbol)
+                && self.first_derivations[usize::from(symbol.id)][usize::from(self.cfg.to_local(terminal))].is_some())
+    }
+
+    /// a shortest derivation of EPS from the nullable symbol
+    fn derive_empty(&self, symbol: Symbol) -> Derivation {
+        // unwrap is safe because only nullable symbols are derived to EPS
+        let rule_id: CfgRuleIdx = self.empty_derivations[usize::from(symbol.id)].unwrap();
+        Derivation::Node {
+            rule_id,
+            children: self.cfg.get_rule_by_id(rule_id).replacement.iter().map(|&symbol| self.derive_empty(symbol)).collect(),
+        }
+    }
+
+    /// a shortest derivation of a word starting with the terminal
+    /// it always ends because the cost of the derivations decreases at each step
+    fn derive_first(&self, symbol: Symbol, terminal: Symbol) -> Derivation {
+        if symbol == terminal {
+            return Derivation::Leaf(symbol);
+        }
+
+        // unwrap is safe because the symbol can derive a word starting with the terminal
+        let (rule_id, position) = self.first_derivations[usize::from(symbol.id)][usize::from(self.cfg.to_local(terminal))].unwrap();
+        let replacement: &[Symbol] = &self.cfg.get_rule_by_id(rule_id).replacement;
+
+        let mut children: Vec<Derivation> = replacement[..position].iter().map(|&symbol| self.derive_empty(symbol)).collect();
+        children.push(self.derive_first(replacement[position], terminal));
+        children.extend(replacement[position+1..].iter().map(|&symbol| Derivation::Leaf(symbol)));
+        Derivation::Node { rule_id, children }
+    }
+
+    /// the word left as it is if no terminal is pending, else derived to start with the pending terminal,
+    /// or derived to EPS if it can't start with it
+    fn derive_word(&self, word: &[Symbol], pending: &mut Option<Symbol>) -> Vec<Derivation> {
+        let Some(terminal) = *pending else {
+            return word.iter().map(|&symbol| Derivation::Leaf(symbol)).collect();
+        };
+
+        for (position, &symbol) in word.iter().enumerate() {
+            if self.can_start_with(symbol, terminal) {
+                *pending = None;
+                let mut derivations: Vec<Derivation> = word[..position].iter().map(|&symbol| self.derive_empty(symbol)).collect();
+                derivations.push(self.derive_first(symbol, terminal));
+                derivations.extend(word[position+1..].iter().map(|&symbol| Derivation::Leaf(symbol)));
+                return derivations;
+            }
+            if !self.cfg.is_symbol_nullable(symbol) {
+                break;
+            }
+        }
+
+        // the searches only keep a terminal pending after nullable words
+        word.iter().map(|&symbol| self.derive_empty(symbol)).collect()
+    }
+
+    /// the terminal that must still follow a word when the required terminal must follow what precedes it:
+    /// None if the word can start with it or if nothing was required, and no result if the word can't be followed by it
+    fn requirement_after(&self, word: &[Symbol], requirement: Option<Symbol>) -> Option<Option<Symbol>> {
+        match requirement {
+            None => Some(None),
+            Some(terminal) if self.cfg.get_first_set_of_word(word).contains(usize::from(self.cfg.to_local_nosymbol(terminal))) => Some(None),
+            Some(terminal) if self.cfg.is_word_nullable(word) => Some(Some(terminal)),
+            Some(_) => None,
+        }
+    }
+
+    /// completes the rules of the frames from the innermost one, which is completed by the derivation,
+    /// the pending terminal must be the first one derived after the derivation
+    fn close_frames(&self, frames: Vec<Frame>, mut derivation: Derivation, mut pending: Option<Symbol>) -> Derivation {
+        for Frame { rule_id, position, mut children } in frames.into_iter().rev() {
+            children.push(derivation);
+            children.extend(self.derive_word(&self.cfg.get_rule_by_id(rule_id).replacement[position+1..], &mut pending));
+            derivation = Derivation::Node { rule_id, children };
+        }
+        derivation
+    }
+
+    /// a unifying pair of examples of different actions if there is one, else the first examples of the first two actions
+    fn pair_examples(mut examples: Vec<Example>) -> Option<Counterexample> {
+        examples.sort_by_key(|example| example.action_id);
+
+        let forms: Vec<(Vec<Symbol>, usize)> = examples.iter().map(|example| example.derivation.sentential_form()).collect();
+        let unifying_pair: Option<(usize, usize)> = (0..examples.len())
+            .flat_map(|index1| (index1+1..examples.len()).map(move |index2| (index1, index2)))
+            .find(|&(index1, index2)| examples[index1].action_id != examples[index2].action_id && forms[index1] == forms[index2]);
+
+        let (index1, index2) = match unifying_pair {
+            Some(pair) => pair,
+            None => {
+                let first_action_id: usize = examples.first()?.action_id;
+                (0, examples.iter().position(|example| example.action_id != first_action_id)?)
+            }
+        };
+
+        let derivation = |index: usize| (examples[index].action.clone(), examples[index].derivation.clone());
+        Some(Counterexample { is_unifying: unifying_pair.is_some(), derivations: [derivation(index1), derivation(index2)] })
+    }
+
+    // -------------------------- LR conflicts
+
+    /// a counterexample for a conflict of a table built from any LR automaton whose cores are LR(0) states
+    /// the state of the conflict is searched among the LR(0) states that contain its items, starting with the one
+    /// with the same id; None if no derivation reaches the conflict with its lookahead (like some SLR(1) conflicts)
+    pub fn lr1_counterexample(&self, lr0_automaton: &Lr0Automaton, conflict: &LR1Conflict) -> Option<Counterexample> {
+        let contains_items = |&state_id: &usize|
+            conflict.items.iter().all(|item| lr0_automaton.get_state(state_id).items.contains(item));
+
+        let mut state_ids: Vec<usize> = (0..lr0_automaton.nbr_states()).filter(contains_items).collect();
+        if let Some(index) = state_ids.iter().position(|&state_id| state_id == conflict.state_id) {
+            state_ids[..=index].rotate_right(1);
+        }
+
+        let predecessors: Vec<Vec<usize>> = lr0_automaton.compute_predecessors();
+        state_ids
+            .into_iter()
+            .find_map(|state_id| {
+                let actions: Vec<(Lr0Item, usize, String)> = conflict.items
+                    .iter()
+                    .filter_map(|&item| self.lr1_action_of_item(conflict, item).map(|(action_id, action)| (item, action_id, action)))
+                    .collect();
+                let mut examples: Vec<Example> = actions
+                    .iter()
+                    .filter_map(|(item, action_id, action)| Some(Example {
+                        action_id: *action_id,
+                        action: action.clone(),
+                        derivation: self.lr0_item_example(lr0_automaton, &predecessors, state_id, *item, conflict.lookahead, None)?,
+                    }))
+                    .collect();
+
+                // the shortest examples of two actions can have different prefixes, like with the dangling else,
+                // so the other actions are also derived with the prefix of each example
+                let mut aligned_examples: Vec<Example> = Vec::new();
+                for example in &examples {
+                    let (symbols, dot) = example.derivation.sentential_form();
+                    for (item, action_id, action) in &actions {
+                        if *action_id != example.action_id
+                            && let Some(derivation) = self.lr0_item_example(lr0_automaton, &predecessors, state_id, *item,
+                                conflict.lookahead, Some(&symbols[..dot])) {
+                            aligned_examples.push(Example { action_id: *action_id, action: action.clone(), derivation });
+                        }
+                    }
+                }
+                examples.extend(aligned_examples);
+
+                Self::pair_examples(examples)
+            })
+    }
+
+    fn lr1_action_of_item(&self, conflict: &LR1Conflict, item: Lr0Item) -> Option<(usize, String)> {
+        let is_complete: bool = item.is_complete(self.cfg);
+        let action_id: usize = conflict.actions.iter().position(|action| match action {
+            LR1Action::Shift(_) => !is_complete,
+            LR1Action::Reduce(rule_id) | LR1Action::Accept(rule_id) => is_complete && *rule_id == item.rule_id,
+            LR1Action::Error => false,
+        })?;
+
+        if is_complete {
+            Some((action_id, format!("reduce {}", self.cfg.repr_rule(item.rule_id))))
+        }
+        else {
+            Some((action_id, format!("shift {}", item.repr(self.cfg))))
+        }
+    }
+
+    /// a shortest derivation of a sentential form whose prefix before the dot leads to the state, where the item
+    /// is used, and which continues with the lookahead if the item is complete
+    /// the prefix before the dot can be imposed
+    fn lr0_item_example(&self, lr0_automaton: &Lr0Automaton, predecessors: &[Vec<usize>], state_id: usize,
+        item: Lr0Item, lookahead: Symbol, prefix: Option<&[Symbol]>) -> Option<Derivation> {
+
+        let cfg: &Cfg = self.cfg;
+        let requirement: Option<Symbol> = if item.is_complete(cfg) && cfg.get_rule_by_id(item.rule_id).origin != cfg.START() {
+            Some(lookahead)
+        }
+        else {
+            None
+        };
+
+        // with a prefix, the states of the path are the ones reached by reading the prefix
+        let prefix_states: Option<Vec<usize>> = match prefix {
+            None => None,
+            Some(prefix) => {
+                let prefix_states: Vec<usize> = (0..=prefix.len())
+                    .map(|length| lr0_automaton.goto_word(0, &prefix[..length]))
+                    .collect::<Option<Vec<usize>>>()?;
+                if prefix_states[prefix.len()] != state_id {
+                    return None;
+                }
+                Some(prefix_states)
+            }
+        };
+
+        // (state, item, terminal that must follow the derivation of the rule of the item,
+        // number of symbols of the prefix still to read, always 0 without prefix)
+        type Node = (usize, Lr0Item, Option<Symbol>, usize);
+
+        // backward search from the item to a rule of START in the start state,
+        // with the successor of each node on its shortest path to the item
+        let start_node: Node = (state_id, item, requirement, prefix.map_or(0, <[Symbol]>::len));
+        let mut successors: HashMap<Node, Option<Node>> = HashMap::from([(start_node, None)]);
+        let mut queue: VecDeque<Node> = VecDeque::from([start_node]);
+        let mut goal: Option<Node> = None;
+
+        while let Some(node @ (node_state_id, node_item, node_requirement, remaining)) = queue.pop_front() {
+            let node_rule: &CfgRule = cfg.get_rule_by_id(node_item.rule_id);
+            if node_state_id == 0 && node_item.dot == 0 && node_rule.origin == cfg.START() && node_requirement.is_none()
+                && remaining == 0 {
+                goal = Some(node);
+                break;
+            }
+
+            let mut predecessor_nodes: Vec<Node> = Vec::new();
+            if node_item.dot > 0 {
+                // the kernel of a state is the same from each of its predecessors, which all contain the previous item
+                let previous_item: Lr0Item = Lr0Item::new(node_item.rule_id, node_item.dot - 1);
+                match &prefix_states {
+                    None => {
+                        for &predecessor_id in &predecessors[node_state_id] {
+                            predecessor_nodes.push((predecessor_id, previous_item, node_requirement, 0));
+                        }
+                    }
+                    Some(prefix_states) => if remaining > 0 {
+                        predecessor_nodes.push((prefix_states[remaining-1], previous_item, node_requirement, remaining-1));
+                    }
+                }
+            }
+            else {
+                // the item has been added to the state by the closure of the items expecting its origin
+                for &parent_item in &lr0_automaton.get_state(node_state_id).items {
+                    if parent_item.next_symbol(cfg) == Some(node_rule.origin)
+                        && let Some(parent_requirement) = self.requirement_after(&parent_item.remaining_symbols(cfg)[1..], node_requirement) {
+                        predecessor_nodes.push((node_state_id, parent_item, parent_requirement, remaining));
+                    }
+                }
+            }
+
+            for predecessor_node in predecessor_nodes {
+                if let Entry::Vacant(entry) = successors.entry(predecessor_node) {
+                    entry.insert(Some(node));
+                    queue.push_back(predecessor_node);
+                }
+            }
+        }
+
+        // follows the path from the start state, each transition reads a symbol of the innermost rule
+        // and each closure starts the derivation of a new rule
+        let mut node: Node = goal?;
+        let mut frames: Vec<Frame> = vec![Frame { rule_id: node.1.rule_id, position: 0, children: Vec::new() }];
+        while let Some(next_node) = successors[&node] {
+            // unwrap is safe because there is at least the frame of the goal
+            let frame: &mut Frame = frames.last_mut().unwrap();
+            if next_node.1.rule_id == node.1.rule_id && next_node.1.dot == node.1.dot + 1 {
+                // unwrap is safe because the item of the node isn't complete
+                frame.children.push(Derivation::Leaf(node.1.next_symbol(cfg).unwrap()));
+                frame.position += 1;
+            }
+            else {
+                frames.push(Frame { rule_id: next_node.1.rule_id, position: 0, children: Vec::new() });
+            }
+            node = next_node;
+        }
+
+        // unwrap is safe because the last frame is the one of the item
+        let Frame { rule_id, position, mut children } = frames.pop().unwrap();
+        children.push(Derivation::Dot);
+        children.extend(cfg.get_rule_by_id(rule_id).replacement[position..].iter().map(|&symbol| Derivation::Leaf(symbol)));
+
+        Some(self.close_frames(frames, Derivation::Node { rule_id, children }, requirement))
+    }
+
+    // -------------------------- LL conflicts
+
+    /// a counterexample for a conflict of the LL(1) generator, the dot is where the non-terminal is expanded
+    pub fn ll1_counterexample(&self, conflict: &LL1Conflict) -> Option<Counterexample> {
+        let examples: Vec<Example> = [conflict.rule1, conflict.rule2]
+            .into_iter()
+            .enumerate()
+            .filter_map(|(action_id, rule_id)| Some(Example {
+                action_id,
+                action: self.cfg.repr_rule(rule_id),
+                derivation: self.rule_example(rule_id, conflict.lookahead)?,
+            }))
+            .collect();
+        Self::pair_examples(examples)
+    }
+
+    /// a shortest derivation of a sentential form where the rule is applied at the dot,
+    /// and whose word after the dot starts with the lookahead
+    fn rule_example(&self, rule_id: CfgRuleIdx, lookahead: Symbol) -> Option<Derivation> {
+        let cfg: &Cfg = self.cfg;
+        let rule: &CfgRule = cfg.get_rule_by_id(rule_id);
+        let requirement: Option<Symbol> = self.requirement_after(&rule.replacement, Some(lookahead))?;
+
+        // (non-terminal, terminal that must follow its derivation)
+        type Node = (Symbol, Option<Symbol>);
+
+        // backward search from the origin of the rule to START through the rules producing each non-terminal,
+        // with the successor of each node on its shortest path and the (rule, position) that leads to it
+        let start_node: Node = (rule.origin, requirement);
+        let mut successors: HashMap<Node, Option<(Node, CfgRuleIdx, usize)>> = HashMap::from([(start_node, None)]);
+        let mut queue: VecDeque<Node> = VecDeque::from([start_node]);
+        let mut goal: Option<Node> = None;
+
+        while let Some(node @ (non_terminal, node_requirement)) = queue.pop_front() {
+            if non_terminal == cfg.START() && node_requirement.is_none() {
+                goal = Some(node);
+                break;
+            }
+
+            for (parent_rule_id, parent_rule) in cfg.get_rules_producing(non_terminal) {
+                for (position, &symbol) in parent_rule.replacement.iter().enumerate() {
+                    if symbol != non_terminal {
+                        continue;
+                    }
+                    let Some(parent_requirement) = self.requirement_after(&parent_rule.replacement[position+1..], node_requirement) else {
+                        continue;
+                    };
+                    let parent_node: Node = (parent_rule.origin, parent_requirement);
+                    if let Entry::Vacant(entry) = successors.entry(parent_node) {
+                        entry.insert(Some((node, parent_rule_id, position)));
+                        queue.push_back(parent_node);
+                    }
+                }
+            }
+        }
+
+        let mut node: Node = goal?;
+        let mut frames: Vec<Frame> = Vec::new();
+        while let Some((next_node, parent_rule_id, position)) = successors[&node] {
+            let children: Vec<Derivation> = cfg.get_rule_by_id(parent_rule_id).replacement[..position]
+                .iter()
+                .map(|&symbol| Derivation::Leaf(symbol))
+                .collect();
+            frames.push(Frame { rule_id: parent_rule_id, position, children });
+            node = next_node;
+        }
+
+        let mut pending: Option<Symbol> = Some(lookahead);
+        let mut children: Vec<Derivation> = vec![Derivation::Dot];
+        children.extend(self.derive_word(&rule.replacement, &mut pending));
+
+        Some(self.close_frames(frames, Derivation::Node { rule_id, children }, pending))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_ambiguous_sum_grammar, create_dangling_else_grammar, create_expression_grammar, create_grammar};
+    use crate::parsing::LL_parsing::LL1_generator::{generate_LL1_table, LL1GeneratorError};
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+
+    fn lalr1_counterexamples(cfg: &Cfg) -> Vec<Counterexample> {
+        let table = generate_LALR1_table(cfg);
+        println!("{}", table.repr_conflicts_with_counterexamples(cfg));
+        let automaton = Lr0Automaton::new(cfg);
+        let generator = CounterexampleGenerator::new(cfg);
+        table.conflicts().iter().map(|conflict| generator.lr1_counterexample(&automaton, conflict).unwrap()).collect()
+    }
+
+    #[test]
+    fn ambiguous_sum() {
+        let cfg = create_ambiguous_sum_grammar();
+
+        let counterexamples = lalr1_counterexamples(&cfg);
+        assert_eq!(counterexamples.len(), 1);
+        assert!(counterexamples[0].is_unifying);
+        assert_eq!(counterexamples[0].repr(&cfg),
+            "unifying counterexample: E + E . + E END\n\
+            \x20   shift E --> E . + E: START --> [ E --> [ E + E --> [ E . + E ] ] END ]\n\
+            \x20   reduce E --> E + E: START --> [ E --> [ E --> [ E + E . ] + E ] END ]\n");
+    }
+
+    #[test]
+    fn dangling_else() {
+        let cfg = create_dangling_else_grammar();
+
+        let counterexamples = lalr1_counterexamples(&cfg);
+        assert_eq!(counterexamples.len(), 1);
+        assert!(counterexamples[0].is_unifying);
+        assert_eq!(counterexamples[0].derivations[0].1.repr_sentential_form(&cfg),
+            "if cond then if cond then S . else S END");
+    }
+
+    #[test]
+    fn lalr_conflict_is_not_unifying() {
+        // the state after "a c" and "b c" is shared, but the lookaheads d and e come from different prefixes
+        let cfg = create_grammar(
+            &["a", "b", "c", "d", "e"],
+            &["S", "A", "B"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["a", "A", "d"]),
+                ("S", &["b", "B", "d"]),
+                ("S", &["a", "B", "e"]),
+                ("S", &["b", "A", "e"]),
+                ("A", &["c"]),
+                ("B", &["c"]),
+            ],
+        );
+
+        let counterexamples = lalr1_counterexamples(&cfg);
+        assert_eq!(counterexamples.len(), 2);
+        assert!(counterexamples.iter().all(|counterexample| !counterexample.is_unifying));
+        let forms: Vec<String> = counterexamples[0].derivations
+            .iter()
+            .map(|(_, derivation)| derivation.repr_sentential_form(&cfg))
+            .collect();
+        assert_eq!(forms, vec!["a c . d END", "b c . d END"]);
+    }
+
+    #[test]
+    fn ll1_counterexamples() {
+        // E -> E + T | T is left-recursive, but not ambiguous
+        let cfg = create_expression_grammar();
+        let Err(err @ LL1GeneratorError::NotLL1 { .. }) = generate_LL1_table(&cfg) else {
+            panic!("the expression grammar is left-recursive");
+        };
+        println!("{}", err.repr(&cfg));
+        let LL1GeneratorError::NotLL1 { conflicts } = &err;
+        let generator = CounterexampleGenerator::new(&cfg);
+        assert!(conflicts.iter().all(|conflict| !generator.ll1_counterexample(conflict).unwrap().is_unifying));
+
+        // S -> A | B, A -> a, B -> a is ambiguous
+        let cfg = create_grammar(
+            &["a"],
+            &["S", "A", "B"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["A"]),
+                ("S", &["B"]),
+                ("A", &["a"]),
+                ("B", &["a"]),
+            ],
+        );
+        let Err(LL1GeneratorError::NotLL1 { conflicts }) = generate_LL1_table(&cfg) else {
+            panic!("the grammar is not LL(1)");
+        };
+        assert_eq!(conflicts.len(), 1);
+        let counterexample: Counterexample = CounterexampleGenerator::new(&cfg).ll1_counterexample(&conflicts[0]).unwrap();
+        assert!(counterexample.is_unifying);
+        assert_eq!(counterexample.repr(&cfg),
+            "unifying counterexample: . a END\n\
+            \x20   S --> A: START --> [ S --> [ . A --> [ a ] ] END ]\n\
+            \x20   S --> B: START --> [ S --> [ . B --> [ a ] ] END ]\n");
+    }
+}
@@ -0,0 +1,2 @@
+pub mod Earley_parser;
+pub mod CYK_parser;
@@ -0,0 +1,324 @@
+use crate::datastructures::bitset::BitSet;
+use crate::formal_language::*;
+use crate::formal_language::transforms::translate_symbol;
+use crate::formal_language::transforms::CNF::ChomskyNormalForm;
+use crate::lexing::machine::*;
+use crate::parsing::parse_tree::*;
+use crate::parsing::{run_until_end, ParserError};
+use crate::UINT;
+
+
+/// table of the non-terminals of the grammar in Chomsky normal form deriving each span of the tokens
+pub struct CYKChart {
+    tokens: Vec<Token>,
+    // cells[end][start]: bitset of the non-terminals deriving the tokens from start (included) to end (excluded)
+    cells: Vec<Vec<BitSet<UINT>>>,
+}
+
+impl CYKChart {
+
+    fn new() -> Self {
+        CYKChart { tokens: Vec::new(), cells: vec![vec![]] }
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// whether the non-terminal of the grammar in Chomsky normal form derives the tokens from start to end
+    pub fn derives(&self, non_terminal: Symbol, start: usize, end: usize) -> bool {
+        start < end && self.cells[end][start].contains(usize::from(non_terminal.id))
+    }
+
+    /// whether START derives the tokens read, the empty word is only derived by the ε-rule of START
+    pub fn is_accepted(&self, cnf: &Cfg) -> bool {
+        match self.tokens.len() {
+            0 => cnf.get_rules_by_origin(cnf.START()).any(|(_, rule)| rule.replacement.is_empty()),
+            nbr_tokens => self.derives(cnf.START(), 0, nbr_tokens),
+        }
+    }
+}
+
+// --------------------------------------------
+
+/// Cocke-Younger-Kasami parser: works on any grammar by converting it to Chomsky normal form, in O(n³)
+/// it is slow, but simple enough to be used to check the results of the other parsers
+pub struct CYKParser<'a> {
+    cfg: &'a Cfg,
+    cnf: ChomskyNormalForm,
+    // (A, B, C) for the rules A -> B C of the grammar in Chomsky normal form
+    binary_rules: Vec<(Symbol, Symbol, Symbol)>,
+}
+
+impl <'a> CYKParser<'a> {
+
+    pub fn new(cfg: &'a Cfg) -> Self {
+        let cnf: ChomskyNormalForm = ChomskyNormalForm::new(cfg);
+        let binary_rules: Vec<(Symbol, Symbol, Symbol)> = cnf.cfg()
+            .all_rules()
+            .filter(|(_, rule)| rule.replacement_size() == 2)
+            .map(|(_, rule)| (rule.origin, rule.replacement[0], rule.replacement[1]))
+            .collect();
+        CYKParser { cfg, cnf, binary_rules }
+    }
+
+    pub fn get_cfg(&self) -> &'a Cfg {
+        self.cfg
+    }
+
+    pub fn get_cnf(&self) -> &ChomskyNormalForm {
+        &self.cnf
+    }
+
+    /// reads the whole token stream, an END token is added if the stream doesn't end with one
+    pub fn recognize<IT: Iterator<Item = Token>>(&self, token_stream: IT) -> bool {
+        self.fill_chart(token_stream).is_ok()
+    }
+
+    /// parses the whole token stream, an END token is added if the stream doesn't end with one
+    /// the parse tree uses the rules of the original grammar, when the input is ambiguous only one of its
+    /// parse trees is built
+    pub fn parse_from_iter<IT: Iterator<Item = Token>>(&self, token_stream: IT)
+    -> Result<ParseTree, MachineError<ParserError>> {
+
+        let chart: CYKChart = self.fill_chart(token_stream)?;
+        // unwrap is safe because the runner only finishes on an accepted chart
+        Ok(self.build_parse_tree(&chart).unwrap())
+    }
+
+    fn fill_chart<IT: Iterator<Item = Token>>(&self, token_stream: IT) -> Result<CYKChart, MachineError<ParserError>> {
+        let mut runner: CYKParserRunner<'_, 'a> = CYKParserRunner::new(self);
+        run_until_end(self.cfg, &mut runner, token_stream)?;
+        Ok(runner.chart)
+    }
+
+    /// the parse tree of the chart with the rules of the original grammar, None if the chart isn't accepted
+    pub fn build_parse_tree(&self, chart: &CYKChart) -> Option<ParseTree> {
+        let cnf: &Cfg = self.cnf.cfg();
+        if !chart.is_accepted(cnf) {
+            return None;
+        }
+        let mut cnf_parse_tree: ParseTree = ParseTree::new();
+        let root_id: usize = self.build_node(chart, &mut cnf_parse_tree, cnf.START(), 0, chart.tokens.len());
+        cnf_parse_tree.set_root(root_id);
+        Some(self.cnf.rewrite_parse_tree(self.cfg, &cnf_parse_tree))
+    }
+
+    /// the node of the grammar in Chomsky normal form for a non-terminal deriving a span
+    fn build_node(&self, chart: &CYKChart, parse_tree: &mut ParseTree, non_terminal: Symbol, start: usize, end: usize) -> usize {
+        let cnf: &Cfg = self.cnf.cfg();
+
+        // unwrap is safe because the non-terminal derives the span, so one of its rules does
+        let (rule_id, children) = cnf
+            .get_rules_by_origin(non_terminal)
+            .find_map(|(rule_id, rule)| match *rule.replacement.as_slice() {
+                [terminal] => {
+                    let mut token: Token = chart.tokens[start].clone();
+                    token.token_type = translate_symbol(self.cfg, cnf, token.token_type);
+                    (end == start+1 && token.token_type == terminal).then(|| {
+                        (rule_id, vec![parse_tree.add_node(ParseTreeNodeKind::Leaf { token }, None)])
+                    })
+                }
+                [left, right] => (start+1..end)
+                    .find(|&middle| chart.derives(left, start, middle) && chart.derives(right, middle, end))
+                    .map(|middle| {
+                        let left_id: usize = self.build_node(chart, parse_tree, left, start, middle);
+                        let right_id: usize = self.build_node(chart, parse_tree, right, middle, end);
+                        (rule_id, vec![left_id, right_id])
+                    }),
+                // only the ε-rule of START, for the empty input
+                [] => (start == end).then(|| (rule_id, vec![])),
+                _ => None,
+            })
+            .unwrap();
+
+        parse_tree.add_parent_node(ParseTreeNodeKind::Inner { rule_id, origin: non_terminal }, children)
+    }
+}
+
+
+/// reads one token at each update and fills the cells of the spans ending with it
+/// the runner is finished when END has been read and the tokens are a word of the language
+pub struct CYKParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    parser: &'parser CYKParser<'a>,
+    chart: CYKChart,
+    run_info: RunInfo,
+}
+
+impl <'parser, 'a> CYKParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    pub fn new(parser: &'parser CYKParser<'a>) -> Self {
+        CYKParserRunner {
+            parser,
+            chart: CYKChart::new(),
+            run_info: RunInfo::Ready,
+        }
+    }
+}
+
+impl <'parser, 'a> Machine<Token, CYKChart, ParserError> for CYKParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    fn clear(&mut self) {
+        self.chart = CYKChart::new();
+        self.run_info = RunInfo::Ready;
+    }
+
+    fn get_run_info(&self) -> &RunInfo {
+        &self.run_info
+    }
+
+    fn update(&mut self, token: &Token) -> Result<(), MachineError<ParserError>> {
+        if self.is_finished() {
+            return Err(MachineError::Finished);
+        }
+
+        let cfg: &Cfg = self.parser.cfg;
+        if token.token_type.id >= cfg.nbr_symbols() || !cfg.is_terminal(token.token_type) {
+            return Err(ParserError::InvalidTokenType { token: token.clone() }.into());
+        }
+
+        self.run_info = RunInfo::Running;
+
+        let cnf: &Cfg = self.parser.cnf.cfg();
+        let terminal: Symbol = translate_symbol(cfg, cnf, token.token_type);
+        self.chart.tokens.push(token.clone());
+        let end: usize = self.chart.tokens.len();
+
+        // the spans ending with the token, from the shortest one
+        let mut column: Vec<BitSet<UINT>> = vec![BitSet::new_filled(false, usize::from(cnf.nbr_non_terminals())); end];
+        for (_, rule) in cnf.all_rules() {
+            if rule.replacement == [terminal] {
+                column[end-1].insert(usize::from(rule.origin.id));
+            }
+        }
+        for start in (0..end-1).rev() {
+            for middle in start+1..end {
+                for &(origin, left, right) in &self.parser.binary_rules {
+                    if self.chart.cells[middle][start].contains(usize::from(left.id))
+                    && column[middle].contains(usize::from(right.id)) {
+                        column[start].insert(usize::from(origin.id));
+                    }
+                }
+            }
+        }
+        self.chart.cells.push(column);
+
+        // nothing can be read after END
+        if token.token_type == cfg.END() {
+            if !self.chart.is_accepted(cnf) {
+                // a rule of START that doesn't end with END, like START -> ε, can derive the tokens before it
+                self.chart.tokens.pop();
+                self.chart.cells.pop();
+                if !self.chart.is_accepted(cnf) {
+                    return Err(ParserError::NotInLanguage { token: token.clone() }.into());
+                }
+            }
+            self.run_info = RunInfo::Finished;
+        }
+
+        Ok(())
+    }
+
+    fn get_state(&self) -> &CYKChart {
+        &self.chart
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::regex_grammar::create_regex_grammar;
+    use crate::grammars::test_grammars::{create_ambiguous_sum_grammar, create_expression_grammar, create_nullable_start_grammar, tokens};
+    use crate::parsing::LL_parsing::LL1_generator::generate_LL1_table;
+    use crate::parsing::LL_parsing::LL1_parser::LL1Parser;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_parser::LR1Parser;
+    use crate::parsing::BuildingParser;
+
+    #[test]
+    fn same_trees_as_LR1() {
+        let cfg = create_expression_grammar();
+        let table = generate_LALR1_table(&cfg);
+        let lr1_parser = LR1Parser::new(&cfg, &table);
+        let cyk_parser = CYKParser::new(&cfg);
+
+        for input in [
+            vec!["id"],
+            vec!["id", "+", "id", "*", "(", "id", "+", "id", ")"],
+            vec!["(", "(", "id", ")", ")", "*", "id", "*", "id"],
+        ] {
+            let input: Vec<Token> = tokens(&cfg, &input);
+            let expected: String = lr1_parser.parse_from_iter(input.clone().into_iter()).unwrap().repr(&cfg);
+            assert_eq!(cyk_parser.parse_from_iter(input.into_iter()).unwrap().repr(&cfg), expected);
+        }
+
+        let result = cyk_parser.parse_from_iter(tokens(&cfg, &["id", "+", "+", "id"]).into_iter());
+        let Err(MachineError::Other { other_err: ParserError::NotInLanguage { token } }) = result else {
+            panic!("the input should be rejected");
+        };
+        assert_eq!(token.token_type, cfg.END());
+        assert!(!cyk_parser.recognize(tokens(&cfg, &["(", "id"]).into_iter()));
+    }
+
+    #[test]
+    fn same_trees_as_LL1() {
+        // the regex grammar has ε-rules, so the trees contain derivations of the empty word
+        let cfg = create_regex_grammar();
+        let table = generate_LL1_table(&cfg).unwrap();
+        let ll1_parser = LL1Parser::new(&cfg, &table);
+        let cyk_parser = CYKParser::new(&cfg);
+
+        for input in [
+            vec!["char"],
+            vec!["char", "*", "|", "(", "char", "char", "+", ")", "?"],
+            vec!["[", "^", "list_char", "]", "{", "int", ",", "}"],
+        ] {
+            let input: Vec<Token> = tokens(&cfg, &input);
+            let expected: String = ll1_parser.parse_from_iter(input.clone().into_iter()).unwrap().repr(&cfg);
+            assert_eq!(cyk_parser.parse_from_iter(input.into_iter()).unwrap().repr(&cfg), expected);
+        }
+    }
+
+    #[test]
+    fn ambiguous_grammar() {
+        let cfg = create_ambiguous_sum_grammar();
+        let parser = CYKParser::new(&cfg);
+        let mut runner = CYKParserRunner::new(&parser);
+        for token in tokens(&cfg, &["id", "+", "id", "+", "id"]) {
+            runner.update(&token).unwrap();
+        }
+        // E derives id + id at the beginning and at the end
+        let E = parser.get_cnf().cfg().symbol_set().get_symbol_by_representation("E");
+        // the tokens aren't a word of the language before END
+        assert!(parser.build_parse_tree(runner.get_state()).is_none());
+        assert!(runner.get_state().derives(E, 0, 3));
+        assert!(runner.get_state().derives(E, 2, 5));
+        assert!(!runner.get_state().derives(E, 1, 3));
+
+        runner.update(&tokens(&cfg, &["END"])[0]).unwrap();
+        assert!(runner.is_finished());
+        let parse_tree = parser.build_parse_tree(runner.get_state()).unwrap();
+        assert_eq!(parse_tree.repr(&cfg).lines().filter(|line| line.contains("E --> E + E")).count(), 2);
+    }
+
+    #[test]
+    fn epsilon_rule_of_START() {
+        let cfg = create_nullable_start_grammar();
+        let parser = CYKParser::new(&cfg);
+
+        assert!(parser.recognize(tokens(&cfg, &[]).into_iter()));
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &[]).into_iter()).unwrap();
+        assert_eq!(parse_tree.repr(&cfg), "START -->\n");
+        let parse_tree = parser.parse_from_iter(tokens(&cfg, &["a", "a"]).into_iter()).unwrap();
+        assert!(parse_tree.repr(&cfg).starts_with("START --> a a END\n"));
+        assert!(!parser.recognize(tokens(&cfg, &["a"]).into_iter()));
+    }
+}
@@ -0,0 +1,561 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::formal_language::*;
+use crate::lexing::machine::*;
+use crate::parsing::{run_until_end, ParserError};
+use crate::parsing::LR_parsing::LR1_generators::LR0_generator::Lr0Item;
+use crate::parsing::sppf::*;
+
+
+/// LR(0) item whose derivation started at the token of index origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EarleyItem {
+    pub item: Lr0Item,
+    pub origin: usize,
+}
+
+// the deterministic reduction path of a symbol completed from a set (Leo's transitive item)
+#[derive(Debug, Clone, Copy)]
+struct LeoItem {
+    // the only item of the set expecting the symbol, advanced over it: it is complete
+    completed: EarleyItem,
+    // the item at the top of the path, which is complete too
+    topmost: EarleyItem,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EarleySet {
+    items: Vec<EarleyItem>,
+    item_ids: HashSet<EarleyItem>,
+    // the indexes of the items by the symbol after their dot
+    waiting_items: HashMap<Symbol, Vec<usize>>,
+    // the (symbol, origin) completed in this set whose deterministic reduction path was skipped
+    leo_completions: Vec<(Symbol, usize)>,
+    // the deterministic reduction paths of the symbols completed from this set, None if there is none
+    leo_items: HashMap<Symbol, Option<LeoItem>>,
+}
+
+// the derivations completed in a set
+struct SetCompletions {
+    // the origins of the completed derivations of each symbol
+    origins: HashMap<Symbol, Vec<usize>>,
+    // the completed (rule, origin)
+    rules: HashSet<(CfgRuleIdx, usize)>,
+}
+
+
+/// the Earley sets of the tokens read so far, the set i contains the items after reading i tokens
+/// nullable symbols are skipped as soon as they are predicted (Aycock and Horspool), and the completion of
+/// right recursions goes directly to the top of their deterministic reduction paths (Leo)
+#[derive(Debug, Clone)]
+pub struct EarleyChart {
+    sets: Vec<EarleySet>,
+    tokens: Vec<Token>,
+}
+
+impl EarleyChart {
+
+    fn new(cfg: &Cfg) -> Self {
+        let mut chart: EarleyChart = EarleyChart { sets: vec![EarleySet::default()], tokens: Vec::new() };
+        for (rule_id, _) in cfg.get_rules_by_origin(cfg.START()) {
+            chart.add_item(0, EarleyItem { item: Lr0Item::new(rule_id, 0), origin: 0 });
+        }
+        chart.close_set(cfg, 0);
+        chart
+    }
+
+    pub fn nbr_sets(&self) -> usize {
+        self.sets.len()
+    }
+
+    pub fn nbr_items(&self, set_id: usize) -> usize {
+        self.sets[set_id].items.len()
+    }
+
+    pub fn get_items(&self, set_id: usize) -> &[EarleyItem] {
+        &self.sets[set_id].items
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// the terminals that can be read after the last set
+    pub fn expected_terminals(&self, cfg: &Cfg) -> Vec<Symbol> {
+        // unwrap is safe because there is always the first set
+        let mut expected: Vec<Symbol> = self.sets.last().unwrap().waiting_items
+            .keys()
+            .copied()
+            .filter(|&symbol| cfg.is_terminal(symbol))
+            .collect();
+        expected.sort();
+        expected
+    }
+
+    /// a rule of START has been completed over every token read
+    pub fn is_accepted(&self, cfg: &Cfg) -> bool {
+        // unwrap is safe because there is always the first set
+        self.sets.last().unwrap().items.iter().any(|earley_item|
+            earley_item.origin == 0
+            && earley_item.item.is_complete(cfg)
+            && cfg.get_rule_by_id(earley_item.item.rule_id).origin == cfg.START())
+    }
+
+    fn add_item(&mut self, set_id: usize, earley_item: EarleyItem) {
+        let set: &mut EarleySet = &mut self.sets[set_id];
+        if set.item_ids.insert(earley_item) {
+            set.items.push(earley_item);
+        }
+    }
+
+    /// reads the token: the set of the items which expect it is created, and closed
+    /// returns false and leaves the chart unchanged if no item expects it
+    fn scan(&mut self, cfg: &Cfg, token: &Token) -> bool {
+        // unwrap is safe because there is always the first set
+        let last_set: &EarleySet = self.sets.last().unwrap();
+        let Some(waiting_ids) = last_set.waiting_items.get(&token.token_type) else {
+            return false;
+        };
+        let scanned_items: Vec<EarleyItem> = waiting_ids
+            .iter()
+            .map(|&item_id| EarleyItem { item: last_set.items[item_id].item.advance(), origin: last_set.items[item_id].origin })
+            .collect();
+
+        self.sets.push(EarleySet::default());
+        self.tokens.push(token.clone());
+        let set_id: usize = self.sets.len()-1;
+        for earley_item in scanned_items {
+            self.add_item(set_id, earley_item);
+        }
+        self.close_set(cfg, set_id);
+        true
+    }
+
+    /// predicts and completes the items of the set until no item is added
+    fn close_set(&mut self, cfg: &Cfg, set_id: usize) {
+        let mut item_id: usize = 0;
+        while item_id < self.sets[set_id].items.len() {
+            let EarleyItem { item, origin } = self.sets[set_id].items[item_id];
+
+            match item.next_symbol(cfg) {
+                Some(symbol) => {
+                    self.sets[set_id].waiting_items.entry(symbol).or_default().push(item_id);
+                    if cfg.is_non_terminal(symbol) {
+                        for (rule_id, _) in cfg.get_rules_by_origin(symbol) {
+                            self.add_item(set_id, EarleyItem { item: Lr0Item::new(rule_id, 0), origin: set_id });
+                        }
+                        // the completion of the symbol may already have happened in this set
+                        if cfg.is_symbol_nullable(symbol) {
+                            self.add_item(set_id, EarleyItem { item: item.advance(), origin });
+                        }
+                    }
+                }
+                None => self.complete(cfg, set_id, cfg.get_rule_by_id(item.rule_id).origin, origin),
+            }
+
+            item_id += 1;
+        }
+    }
+
+    /// advances the items of the origin set which expect the completed symbol
+    fn complete(&mut self, cfg: &Cfg, set_id: usize, symbol: Symbol, origin: usize) {
+        // the origin set must be closed to look for a deterministic reduction path
+        if origin < set_id && let Some(leo_item) = self.leo_item(cfg, origin, symbol) {
+            self.sets[set_id].leo_completions.push((symbol, origin));
+            self.add_item(set_id, leo_item.topmost);
+            return;
+        }
+
+        let origin_set: &EarleySet = &self.sets[origin];
+        let advanced_items: Vec<EarleyItem> = origin_set.waiting_items
+            .get(&symbol)
+            .map(|waiting_ids| waiting_ids
+                .iter()
+                .map(|&item_id| EarleyItem { item: origin_set.items[item_id].item.advance(), origin: origin_set.items[item_id].origin })
+                .collect())
+            .unwrap_or_default();
+        for earley_item in advanced_items {
+            self.add_item(set_id, earley_item);
+        }
+    }
+
+    /// the only item of the closed set which expects the symbol, advanced over it, if it is complete
+    fn deterministic_completion(&self, cfg: &Cfg, set_id: usize, symbol: Symbol) -> Option<EarleyItem> {
+        let waiting_ids: &[usize] = self.sets[set_id].waiting_items.get(&symbol)?;
+        let &[item_id] = waiting_ids else {
+            return None;
+        };
+        let EarleyItem { item, origin } = self.sets[set_id].items[item_id];
+        let completed: EarleyItem = EarleyItem { item: item.advance(), origin };
+        completed.item.is_complete(cfg).then_some(completed)
+    }
+
+    /// the memoized deterministic reduction path of the symbol completed from the closed set
+    /// the path is followed down to a memoized item, and stops before an item whose origin is the same set
+    fn leo_item(&mut self, cfg: &Cfg, set_id: usize, symbol: Symbol) -> Option<LeoItem> {
+        let mut path: Vec<(usize, Symbol, EarleyItem)> = Vec::new();
+        let (mut current_set_id, mut current_symbol) = (set_id, symbol);
+
+        let mut topmost: Option<EarleyItem> = loop {
+            if let Some(leo_item) = self.sets[current_set_id].leo_items.get(&current_symbol) {
+                break leo_item.map(|leo_item| leo_item.topmost);
+            }
+            let Some(completed) = self.deterministic_completion(cfg, current_set_id, current_symbol) else {
+                self.sets[current_set_id].leo_items.insert(current_symbol, None);
+                break None;
+            };
+            path.push((current_set_id, current_symbol, completed));
+            if completed.origin == current_set_id {
+                break None;
+            }
+            (current_set_id, current_symbol) = (completed.origin, cfg.get_rule_by_id(completed.item.rule_id).origin);
+        };
+
+        for (path_set_id, path_symbol, completed) in path.into_iter().rev() {
+            let path_topmost: EarleyItem = topmost.unwrap_or(completed);
+            self.sets[path_set_id].leo_items.insert(path_symbol, Some(LeoItem { completed, topmost: path_topmost }));
+            topmost = Some(path_topmost);
+        }
+
+        self.sets[set_id].leo_items[&symbol]
+    }
+
+    // -------------------------- forest
+
+    /// the derivations completed in each set, including the ones skipped by the deterministic reduction paths
+    fn compute_completions(&self, cfg: &Cfg) -> Vec<SetCompletions> {
+        let mut completions: Vec<SetCompletions> = Vec::with_capacity(self.sets.len());
+
+        for set in &self.sets {
+            let mut completed_items: Vec<EarleyItem> = set.items.iter().filter(|earley_item| earley_item.item.is_complete(cfg)).copied().collect();
+
+            for &(symbol, origin) in &set.leo_completions {
+                let (mut path_set_id, mut path_symbol) = (origin, symbol);
+                // unwrap is safe because the paths of the Leo completions are memoized
+                while let Some(leo_item) = self.sets[path_set_id].leo_items[&path_symbol] {
+                    completed_items.push(leo_item.completed);
+                    if leo_item.completed == leo_item.topmost {
+                        break;
+                    }
+                    (path_set_id, path_symbol) = (leo_item.completed.origin, cfg.get_rule_by_id(leo_item.completed.item.rule_id).origin);
+                }
+            }
+
+            let mut origins: HashMap<Symbol, Vec<usize>> = HashMap::new();
+            let mut rules: HashSet<(CfgRuleIdx, usize)> = HashSet::new();
+            for EarleyItem { item, origin } in completed_items {
+                if rules.insert((item.rule_id, origin)) {
+                    origins.entry(cfg.get_rule_by_id(item.rule_id).origin).or_default().push(origin);
+                }
+            }
+            for symbol_origins in origins.values_mut() {
+                symbol_origins.sort();
+                symbol_origins.dedup();
+            }
+            completions.push(SetCompletions { origins, rules });
+        }
+
+        completions
+    }
+
+    /// the forest of every parse of the tokens read, without root if they haven't been accepted
+    /// the nodes are built top-down from the root, and each family is found by splitting the span of a completed
+    /// rule between its symbols, from the last one to the first one
+    /// every split is a family of its own (see Sppf), the grammar can be converted to CNF first to keep the forest
+    /// cubic when the long rules are very ambiguous
+    pub fn build_sppf(&self, cfg: &Cfg) -> Sppf {
+        let mut sppf: Sppf = Sppf::new(self.tokens.clone());
+        if !self.is_accepted(cfg) {
+            return sppf;
+        }
+
+        let completions: Vec<SetCompletions> = self.compute_completions(cfg);
+        let root_id: usize = sppf.add_node(cfg.START(), 0, self.tokens.len());
+        sppf.set_root(root_id);
+
+        let mut nodes_to_expand: Vec<usize> = vec![root_id];
+        let mut is_expanded: HashSet<usize> = HashSet::from([root_id]);
+
+        while let Some(node_id) = nodes_to_expand.pop() {
+            let SppfNode { symbol, start, end, .. } = *sppf.get_node(node_id);
+            if cfg.is_terminal(symbol) {
+                continue;
+            }
+
+            for (rule_id, rule) in cfg.get_rules_by_origin(symbol) {
+                if !completions[end].rules.contains(&(rule_id, start)) {
+                    continue;
+                }
+                let splits: Vec<Vec<(Symbol, usize, usize)>> = self.split(cfg, &completions, rule_id, start, rule.replacement_size(), end);
+                for split in splits {
+                    let children: Vec<usize> = split
+                        .into_iter()
+                        .map(|(child_symbol, child_start, child_end)| {
+                            let child_id: usize = sppf.add_node(child_symbol, child_start, child_end);
+                            if is_expanded.insert(child_id) {
+                                nodes_to_expand.push(child_id);
+                            }
+                            child_id
+                        })
+                        .collect();
+                    sppf.add_family(node_id, rule_id, children);
+                }
+            }
+        }
+
+        sppf
+    }
+
+    /// every way of deriving the tokens from start to end with the first symbols of the replacement of the rule,
+    /// knowing that the item with the dot after them is in the set end
+    /// the splits aren't shared between the prefixes, so there are O(n^(nbr_symbols-1)) of them at most
+    fn split(&self, cfg: &Cfg, completions: &[SetCompletions],
+        rule_id: CfgRuleIdx, start: usize, nbr_symbols: usize, end: usize) -> Vec<Vec<(Symbol, usize, usize)>> {
+
+        if nbr_symbols == 0 {
+            return if start == end {vec![Vec::new()]} else {Vec::new()};
+        }
+
+        let symbol: Symbol = cfg.get_rule_by_id(rule_id).replacement[nbr_symbols-1];
+        let symbol_starts: Vec<usize> = if cfg.is_terminal(symbol) {
+            if end > start && self.tokens[end-1].token_type == symbol {vec![end-1]} else {Vec::new()}
+        }
+        else {
+            completions[end].origins.get(&symbol).map_or(Vec::new(), |origins| origins.iter().copied().filter(|&origin| origin >= start).collect())
+        };
+
+        let mut splits: Vec<Vec<(Symbol, usize, usize)>> = Vec::new();
+        for symbol_start in symbol_starts {
+            let is_prefix_derived: bool = if nbr_symbols == 1 {
+                symbol_start == start
+            }
+            else {
+                let prefix_item: EarleyItem = EarleyItem { item: Lr0Item::new(rule_id, u16::try_from(nbr_symbols-1).unwrap()), origin: start };
+                self.sets[symbol_start].item_ids.contains(&prefix_item)
+            };
+            if !is_prefix_derived {
+                continue;
+            }
+
+            for mut split in self.split(cfg, completions, rule_id, start, nbr_symbols-1, symbol_start) {
+                split.push((symbol, symbol_start, end));
+                splits.push(split);
+            }
+        }
+        splits
+    }
+
+    /// one line per item, grouped by set
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for (set_id, set) in self.sets.iter().enumerate() {
+            s.push_str(&format!("set {set_id}:\n"));
+            for earley_item in &set.items {
+                s.push_str(&format!("    {}, {}\n", earley_item.item.repr(cfg), earley_item.origin));
+            }
+        }
+        s
+    }
+}
+
+// --------------------------------------------
+
+/// general parser, works with any grammar
+pub struct EarleyParser<'a> {
+    cfg: &'a Cfg,
+}
+
+impl <'a> EarleyParser<'a> {
+
+    pub fn new(cfg: &'a Cfg) -> Self {
+        EarleyParser { cfg }
+    }
+
+    pub fn get_cfg(&self) -> &'a Cfg {
+        self.cfg
+    }
+
+    /// parses the whole token stream, an END token is added if the stream doesn't end with one
+    /// returns every parse of the tokens
+    pub fn parse_from_iter<IT: Iterator<Item = Token>>(&self, token_stream: IT)
+    -> Result<Sppf, MachineError<ParserError>> {
+        let mut runner: EarleyParserRunner<'_, 'a> = EarleyParserRunner::new(self);
+        run_until_end(self.cfg, &mut runner, token_stream)?;
+        Ok(runner.chart.build_sppf(self.cfg))
+    }
+}
+
+
+/// reads one token at each update into the chart, the runner is finished when END has been read
+pub struct EarleyParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    parser: &'parser EarleyParser<'a>,
+    chart: EarleyChart,
+    run_info: RunInfo,
+}
+
+impl <'parser, 'a> EarleyParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    pub fn new(parser: &'parser EarleyParser<'a>) -> Self {
+        EarleyParserRunner {
+            parser,
+            chart: EarleyChart::new(parser.cfg),
+            run_info: RunInfo::Ready,
+        }
+    }
+}
+
+impl <'parser, 'a> Machine<Token, EarleyChart, ParserError> for EarleyParserRunner<'parser, 'a>
+where
+    'a: 'parser,
+{
+    fn clear(&mut self) {
+        self.chart = EarleyChart::new(self.parser.cfg);
+        self.run_info = RunInfo::Ready;
+    }
+
+    fn get_run_info(&self) -> &RunInfo {
+        &self.run_info
+    }
+
+    fn update(&mut self, token: &Token) -> Result<(), MachineError<ParserError>> {
+        if self.is_finished() {
+            return Err(MachineError::Finished);
+        }
+
+        let cfg: &Cfg = self.parser.cfg;
+        if token.token_type.id >= cfg.nbr_symbols() || !cfg.is_terminal(token.token_type) {
+            return Err(ParserError::InvalidTokenType { token: token.clone() }.into());
+        }
+
+        self.run_info = RunInfo::Running;
+
+        // a rule of START that doesn't end with END, like START -> ε, is completed before END is read
+        let is_end_expected: bool = self.chart.expected_terminals(cfg).contains(&cfg.END());
+        if token.token_type == cfg.END() && !is_end_expected && self.chart.is_accepted(cfg) {
+            self.run_info = RunInfo::Finished;
+            return Ok(());
+        }
+
+        if !self.chart.scan(cfg, token) {
+            return Err(ParserError::UnexpectedToken {
+                token: token.clone(),
+                expected: self.chart.expected_terminals(cfg),
+            }.into());
+        }
+
+        // END is only read at the end of the rules of START
+        if token.token_type == cfg.END() {
+            self.run_info = RunInfo::Finished;
+        }
+        Ok(())
+    }
+
+    fn get_state(&self) -> &EarleyChart {
+        &self.chart
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_ambiguous_sum_grammar, create_expression_grammar, create_grammar, create_nullable_grammar, create_nullable_start_grammar, tokens};
+
+    #[test]
+    fn ambiguous_sum() {
+        let cfg = create_ambiguous_sum_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+
+        let sppf = EarleyParser::new(&cfg).parse_from_iter(tokens(&cfg, &["id", "+", "id", "+", "id"]).into_iter()).unwrap();
+        println!("{}", sppf.repr(&cfg));
+
+        // (id + id) + id and id + (id + id)
+        let ambiguous_nodes: Vec<usize> = sppf.ambiguous_nodes().collect();
+        assert_eq!(ambiguous_nodes, vec![sppf.find_node(S("E"), 0, 5).unwrap()]);
+        assert_eq!(sppf.get_node(ambiguous_nodes[0]).families.len(), 2);
+        // START, 6 tokens including END, 6 distinct sub-expressions
+        assert_eq!(sppf.nbr_nodes(), 13);
+    }
+
+    #[test]
+    fn unambiguous_grammar() {
+        let cfg = create_expression_grammar();
+        let parser = EarleyParser::new(&cfg);
+
+        let sppf = parser.parse_from_iter(tokens(&cfg, &["(", "id", "+", "id", ")", "*", "id"]).into_iter()).unwrap();
+        println!("{}", sppf.repr(&cfg));
+        assert!(sppf.root().is_some());
+        assert_eq!(sppf.ambiguous_nodes().count(), 0);
+
+        let result = parser.parse_from_iter(tokens(&cfg, &["(", "id", "+", ")"]).into_iter());
+        let Err(MachineError::Other { other_err: err @ ParserError::UnexpectedToken { .. } }) = result else {
+            panic!("the parse should fail on the )");
+        };
+        assert_eq!(err.repr(&cfg), "Unexpected token \")\" at line 0, column 3: found ), expected one of {(, id}");
+    }
+
+    #[test]
+    fn nullable_symbols() {
+        // A is nullable, and the empty word has a derivation with each A empty
+        let cfg = create_nullable_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let parser = EarleyParser::new(&cfg);
+
+        let sppf = parser.parse_from_iter(tokens(&cfg, &[]).into_iter()).unwrap();
+        let s_node = sppf.find_node(S("S"), 0, 0).unwrap();
+        assert_eq!(sppf.get_node(s_node).families.len(), 1);
+
+        // the a can be derived by any of the 3 A
+        let sppf = parser.parse_from_iter(tokens(&cfg, &["a"]).into_iter()).unwrap();
+        println!("{}", sppf.repr(&cfg));
+        let s_node = sppf.find_node(S("S"), 0, 1).unwrap();
+        assert_eq!(sppf.get_node(s_node).families.len(), 3);
+    }
+
+    #[test]
+    fn epsilon_rule_of_START() {
+        let cfg = create_nullable_start_grammar();
+        let parser = EarleyParser::new(&cfg);
+
+        let sppf = parser.parse_from_iter(tokens(&cfg, &[]).into_iter()).unwrap();
+        assert_eq!(sppf.to_parse_tree().unwrap().repr(&cfg), "START -->\n");
+        let sppf = parser.parse_from_iter(tokens(&cfg, &["a", "a"]).into_iter()).unwrap();
+        assert!(sppf.to_parse_tree().unwrap().repr(&cfg).starts_with("START --> a a END\n"));
+        assert!(parser.parse_from_iter(tokens(&cfg, &["a"]).into_iter()).is_err());
+    }
+
+    #[test]
+    fn right_recursion() {
+        // S -> a S | a
+        let cfg = create_grammar(
+            &["a"],
+            &["S"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["a", "S"]),
+                ("S", &["a"]),
+            ],
+        );
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let parser = EarleyParser::new(&cfg);
+
+        let input: Vec<Token> = tokens(&cfg, &["a"; 200]);
+        let mut runner = EarleyParserRunner::new(&parser);
+        for token in &input {
+            runner.update(token).unwrap();
+        }
+        // without Leo's optimization, the last set would contain an item S --> a S . for each a
+        let chart: &EarleyChart = runner.get_state();
+        assert!((0..chart.nbr_sets()).all(|set_id| chart.nbr_items(set_id) <= 6), "{}", chart.repr(&cfg));
+
+        // the skipped completions are still in the forest
+        let sppf = parser.parse_from_iter(input.into_iter()).unwrap();
+        assert!((0..200).all(|start| sppf.find_node(S("S"), start, 200).is_some()));
+        assert_eq!(sppf.ambiguous_nodes().count(), 0);
+    }
+}
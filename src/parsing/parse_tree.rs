@@ -0,0 +1,419 @@
+use crate::formal_language::*;
+
+
+#[derive(Debug, Clone)]
+pub enum ParseTreeNodeKind {
+    // a non-terminal symbol replaced using the rule
+    Inner{rule_id: CfgRuleIdx, origin: Symbol},
+    // a terminal symbol
+    Leaf{token: Token},
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseTreeNode {
+    pub kind: ParseTreeNodeKind,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+
+/// concrete parse tree, the nodes are stored in a vector and refer to each other by their index
+/// it is the output of every parser: the LL and LR parsers build it directly, and the general parsers
+/// through their forest (see Sppf::to_parse_tree)
+#[derive(Debug, Clone, Default)]
+pub struct ParseTree {
+    nodes: Vec<ParseTreeNode>,
+    root: Option<usize>,
+}
+
+impl ParseTree {
+
+    pub fn new() -> Self {
+        ParseTree {
+            nodes: Vec::new(),
+            root: None,
+        }
+    }
+
+    pub fn nbr_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn root(&self) -> Option<usize> {
+        self.root
+    }
+
+    pub fn set_root(&mut self, node_id: usize) {
+        self.root = Some(node_id);
+    }
+
+    pub fn get_node(&self, node_id: usize) -> &ParseTreeNode {
+        &self.nodes[node_id]
+    }
+
+    /// adds a node as the last child of parent (top-down construction)
+    pub fn add_node(&mut self, kind: ParseTreeNodeKind, parent: Option<usize>) -> usize {
+        let node_id: usize = self.nodes.len();
+        self.nodes.push(ParseTreeNode { kind, parent, children: Vec::new() });
+        if let Some(parent_id) = parent {
+            self.nodes[parent_id].children.push(node_id);
+        }
+        node_id
+    }
+
+    /// adds a node which is the parent of already existing nodes (bottom-up construction)
+    pub fn add_parent_node(&mut self, kind: ParseTreeNodeKind, children: Vec<usize>) -> usize {
+        let node_id: usize = self.nodes.len();
+        for &child_id in &children {
+            self.nodes[child_id].parent = Some(node_id);
+        }
+        self.nodes.push(ParseTreeNode { kind, parent: None, children });
+        node_id
+    }
+
+    // -------------------------- navigation
+
+    pub fn parent(&self, node_id: usize) -> Option<usize> {
+        self.nodes[node_id].parent
+    }
+
+    pub fn children(&self, node_id: usize) -> &[usize] {
+        &self.nodes[node_id].children
+    }
+
+    pub fn child(&self, node_id: usize, position: usize) -> Option<usize> {
+        self.nodes[node_id].children.get(position).copied()
+    }
+
+    /// the position of the node among the children of its parent, None for the root
+    pub fn position(&self, node_id: usize) -> Option<usize> {
+        let parent_id: usize = self.parent(node_id)?;
+        self.children(parent_id).iter().position(|&child_id| child_id == node_id)
+    }
+
+    pub fn next_sibling(&self, node_id: usize) -> Option<usize> {
+        let parent_id: usize = self.parent(node_id)?;
+        self.child(parent_id, self.position(node_id)? + 1)
+    }
+
+    pub fn previous_sibling(&self, node_id: usize) -> Option<usize> {
+        let parent_id: usize = self.parent(node_id)?;
+        self.child(parent_id, self.position(node_id)?.checked_sub(1)?)
+    }
+
+    /// the origin of the rule of an inner node, the type of the token of a leaf
+    pub fn symbol(&self, node_id: usize) -> Symbol {
+        match &self.nodes[node_id].kind {
+            ParseTreeNodeKind::Inner { origin, .. } => *origin,
+            ParseTreeNodeKind::Leaf { token } => token.token_type,
+        }
+    }
+
+    pub fn rule_id(&self, node_id: usize) -> Option<CfgRuleIdx> {
+        match &self.nodes[node_id].kind {
+            ParseTreeNodeKind::Inner { rule_id, .. } => Some(*rule_id),
+            ParseTreeNodeKind::Leaf { .. } => None,
+        }
+    }
+
+    pub fn token(&self, node_id: usize) -> Option<&Token> {
+        match &self.nodes[node_id].kind {
+            ParseTreeNodeKind::Inner { .. } => None,
+            ParseTreeNodeKind::Leaf { token } => Some(token),
+        }
+    }
+
+    pub fn is_leaf(&self, node_id: usize) -> bool {
+        matches!(self.nodes[node_id].kind, ParseTreeNodeKind::Leaf { .. })
+    }
+
+    // -------------------------- traversals
+
+    /// the nodes of the subtree, each one before its children
+    pub fn pre_order(&self, node_id: usize) -> PreOrder<'_> {
+        PreOrder { parse_tree: self, to_visit: vec![node_id] }
+    }
+
+    /// the nodes of the subtree, each one after its children
+    pub fn post_order(&self, node_id: usize) -> PostOrder<'_> {
+        PostOrder { parse_tree: self, to_visit: vec![(node_id, false)] }
+    }
+
+    /// the tokens of the leaves of the subtree, from left to right
+    pub fn tokens(&self, node_id: usize) -> impl Iterator<Item = &Token> + '_ {
+        self.pre_order(node_id).filter_map(|node_id| self.token(node_id))
+    }
+
+    /// visits the tree from the root, nothing is visited if there is no root
+    pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        if let Some(root_id) = self.root {
+            self.visit(visitor, root_id);
+        }
+    }
+
+    /// calls the method of the visitor corresponding to the kind of the node
+    pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V, node_id: usize) {
+        match &self.nodes[node_id].kind {
+            ParseTreeNodeKind::Inner { rule_id, .. } => visitor.visit_inner(self, node_id, *rule_id),
+            ParseTreeNodeKind::Leaf { token } => visitor.visit_leaf(self, node_id, token),
+        }
+    }
+
+    /// visits the children of the node from left to right
+    pub fn visit_children<V: Visitor + ?Sized>(&self, visitor: &mut V, node_id: usize) {
+        for &child_id in self.children(node_id) {
+            self.visit(visitor, child_id);
+        }
+    }
+
+    /// the value of the root computed by the folder, None if there is no root
+    pub fn fold<F: Folder + ?Sized>(&self, folder: &mut F) -> Option<F::Value> {
+        self.root.map(|root_id| self.fold_subtree(folder, root_id))
+    }
+
+    /// the value of the node computed by the folder, from the values of the nodes below it
+    pub fn fold_subtree<F: Folder + ?Sized>(&self, folder: &mut F, node_id: usize) -> F::Value {
+        // values of the nodes whose parent hasn't been folded yet, the post-order gives them in the order of the children
+        let mut values: Vec<F::Value> = Vec::new();
+        for current_id in self.post_order(node_id) {
+            let value: F::Value = match &self.nodes[current_id].kind {
+                ParseTreeNodeKind::Inner { rule_id, .. } => {
+                    let children_values: Vec<F::Value> = values.split_off(values.len() - self.children(current_id).len());
+                    folder.fold_inner(self, current_id, *rule_id, children_values)
+                }
+                ParseTreeNodeKind::Leaf { token } => folder.fold_leaf(self, current_id, token),
+            };
+            values.push(value);
+        }
+        // unwrap is safe because the node itself is folded last
+        values.pop().unwrap()
+    }
+
+    // -------------------------- representations
+
+    /// one line per node, indented by depth
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        if let Some(root_id) = self.root {
+            self.repr_node(cfg, root_id, 0, &mut s);
+        }
+        s
+    }
+
+    /// one line per node of the subtree, indented by depth
+    pub fn repr_subtree(&self, cfg: &Cfg, node_id: usize) -> String {
+        let mut s: String = String::new();
+        self.repr_node(cfg, node_id, 0, &mut s);
+        s
+    }
+
+    fn repr_node(&self, cfg: &Cfg, node_id: usize, depth: usize, s: &mut String) {
+        s.push_str(&"    ".repeat(depth));
+        let node: &ParseTreeNode = self.get_node(node_id);
+        match &node.kind {
+            ParseTreeNodeKind::Inner { rule_id, .. } => s.push_str(&cfg.repr_rule(*rule_id)),
+            ParseTreeNodeKind::Leaf { token } =>
+                s.push_str(&format!("{} \"{}\"", cfg.repr_symbol(token.token_type), token.lexeme)),
+        }
+        s.push('\n');
+        for &child_id in &node.children {
+            self.repr_node(cfg, child_id, depth+1, s);
+        }
+    }
+}
+
+// --------------------------------------------
+
+/// what the LL and LR parsers build while parsing: they give it the tokens they read, and the rules they apply
+/// once all the symbols of the replacement are built, so the values of the children are always built first
+pub trait ParseBuilder {
+    type Value;
+
+    fn shift(&mut self, token: &Token) -> Self::Value;
+
+    fn reduce(&mut self, rule_id: CfgRuleIdx, origin: Symbol, children: Vec<Self::Value>) -> Self::Value;
+
+    /// the value of the rule of START, given once the input is accepted
+    fn accept(&mut self, value: Self::Value);
+
+    fn clear(&mut self);
+}
+
+/// the values are the nodes
+impl ParseBuilder for ParseTree {
+    type Value = usize;
+
+    fn shift(&mut self, token: &Token) -> usize {
+        self.add_node(ParseTreeNodeKind::Leaf { token: token.clone() }, None)
+    }
+
+    fn reduce(&mut self, rule_id: CfgRuleIdx, origin: Symbol, children: Vec<usize>) -> usize {
+        self.add_parent_node(ParseTreeNodeKind::Inner { rule_id, origin }, children)
+    }
+
+    fn accept(&mut self, value: usize) {
+        self.set_root(value);
+    }
+
+    fn clear(&mut self) {
+        *self = ParseTree::new();
+    }
+}
+
+// --------------------------------------------
+
+pub struct PreOrder<'t> {
+    parse_tree: &'t ParseTree,
+    to_visit: Vec<usize>,
+}
+
+impl Iterator for PreOrder<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let node_id: usize = self.to_visit.pop()?;
+        self.to_visit.extend(self.parse_tree.children(node_id).iter().rev());
+        Some(node_id)
+    }
+}
+
+pub struct PostOrder<'t> {
+    parse_tree: &'t ParseTree,
+    // (node, children already pushed)
+    to_visit: Vec<(usize, bool)>,
+}
+
+impl Iterator for PostOrder<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            let (node_id, are_children_pushed) = self.to_visit.pop()?;
+            if are_children_pushed {
+                return Some(node_id);
+            }
+            self.to_visit.push((node_id, true));
+            self.to_visit.extend(self.parse_tree.children(node_id).iter().rev().map(|&child_id| (child_id, false)));
+        }
+    }
+}
+
+// --------------------------------------------
+
+/// walks a parse tree, by default every node is visited, each one before its children
+/// overriding a method without calling visit_children stops the walk under the node
+pub trait Visitor {
+    fn visit_inner(&mut self, parse_tree: &ParseTree, node_id: usize, _rule_id: CfgRuleIdx) {
+        parse_tree.visit_children(self, node_id);
+    }
+
+    fn visit_leaf(&mut self, _parse_tree: &ParseTree, _node_id: usize, _token: &Token) {}
+}
+
+/// computes a value for each node from the values of its children, bottom-up
+pub trait Folder {
+    type Value;
+
+    fn fold_inner(&mut self, parse_tree: &ParseTree, node_id: usize, rule_id: CfgRuleIdx, children: Vec<Self::Value>) -> Self::Value;
+
+    fn fold_leaf(&mut self, parse_tree: &ParseTree, node_id: usize, token: &Token) -> Self::Value;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, tokens};
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_parser::LR1Parser;
+    use crate::parsing::BuildingParser;
+
+    fn parse(cfg: &Cfg, input: &[&str]) -> ParseTree {
+        let table = generate_LALR1_table(cfg);
+        LR1Parser::new(cfg, &table).parse_from_iter(tokens(cfg, input).into_iter()).unwrap()
+    }
+
+    #[test]
+    fn navigation_and_traversals() {
+        let cfg = create_expression_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let parse_tree = parse(&cfg, &["id", "+", "id"]);
+        let root_id: usize = parse_tree.root().unwrap();
+
+        // START --> E END, E --> E + T
+        assert_eq!(parse_tree.symbol(root_id), cfg.START());
+        let sum_id: usize = parse_tree.child(root_id, 0).unwrap();
+        assert_eq!(parse_tree.symbol(sum_id), S("E"));
+        assert_eq!(cfg.repr_rule(parse_tree.rule_id(sum_id).unwrap()), "E --> E + T");
+        assert_eq!(parse_tree.parent(sum_id), Some(root_id));
+        let plus_id: usize = parse_tree.child(sum_id, 1).unwrap();
+        assert_eq!(parse_tree.token(plus_id).unwrap().lexeme, "+");
+        assert_eq!(parse_tree.previous_sibling(plus_id), parse_tree.child(sum_id, 0));
+        assert_eq!(parse_tree.next_sibling(plus_id), parse_tree.child(sum_id, 2));
+        assert_eq!(parse_tree.next_sibling(parse_tree.child(sum_id, 2).unwrap()), None);
+
+        let pre_order: Vec<Symbol> = parse_tree.pre_order(sum_id).map(|node_id| parse_tree.symbol(node_id)).collect();
+        assert_eq!(pre_order, ["E", "E", "T", "F", "id", "+", "T", "F", "id"].map(S));
+        let post_order: Vec<Symbol> = parse_tree.post_order(sum_id).map(|node_id| parse_tree.symbol(node_id)).collect();
+        assert_eq!(post_order, ["id", "F", "T", "E", "+", "id", "F", "T", "E"].map(S));
+        assert_eq!(parse_tree.tokens(root_id).count(), 4);
+
+        assert_eq!(parse_tree.repr_subtree(&cfg, plus_id), "+ \"+\"\n");
+    }
+
+    /// counts the applications of each rule, without going inside parentheses
+    struct RuleCounter {
+        counts: Vec<usize>,
+        parenthesis_rule_id: CfgRuleIdx,
+    }
+
+    impl Visitor for RuleCounter {
+        fn visit_inner(&mut self, parse_tree: &ParseTree, node_id: usize, rule_id: CfgRuleIdx) {
+            self.counts[usize::from(rule_id)] += 1;
+            if rule_id != self.parenthesis_rule_id {
+                parse_tree.visit_children(self, node_id);
+            }
+        }
+    }
+
+    /// evaluates the expression, with the ids being their column
+    struct Evaluator<'a> {
+        cfg: &'a Cfg,
+    }
+
+    impl Folder for Evaluator<'_> {
+        type Value = usize;
+
+        fn fold_inner(&mut self, _parse_tree: &ParseTree, _node_id: usize, rule_id: CfgRuleIdx, children: Vec<usize>) -> usize {
+            match self.cfg.repr_rule(rule_id).as_str() {
+                "E --> E + T" => children[0] + children[2],
+                "T --> T * F" => children[0] * children[2],
+                "F --> ( E )" => children[1],
+                _ => children[0],
+            }
+        }
+
+        fn fold_leaf(&mut self, _parse_tree: &ParseTree, _node_id: usize, token: &Token) -> usize {
+            token.column
+        }
+    }
+
+    #[test]
+    fn visitor_and_folder() {
+        let cfg = create_expression_grammar();
+        let parse_tree = parse(&cfg, &["id", "*", "(", "id", "+", "id", ")"]);
+        let (parenthesis_rule_id, _) = cfg.all_rules().find(|&(rule_id, _)| cfg.repr_rule(rule_id) == "F --> ( E )").unwrap();
+
+        let mut rule_counter = RuleCounter { counts: vec![0; usize::from(cfg.nbr_rules())], parenthesis_rule_id };
+        parse_tree.accept(&mut rule_counter);
+        // the sum is inside the parentheses
+        assert_eq!(rule_counter.counts.iter().sum::<usize>(), 6);
+        assert_eq!(rule_counter.counts[usize::from(parenthesis_rule_id)], 1);
+
+        // 0 * (3 + 5)
+        assert_eq!(parse_tree.fold(&mut Evaluator { cfg: &cfg }), Some(0));
+        let parse_tree = parse(&cfg, &["(", "id", "+", "id", ")", "*", "id"]);
+        // (1 + 3) * 6
+        assert_eq!(parse_tree.fold(&mut Evaluator { cfg: &cfg }), Some(24));
+    }
+}
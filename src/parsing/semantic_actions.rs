@@ -0,0 +1,310 @@
+use crate::formal_language::*;
+use crate::parsing::ParserError;
+use crate::parsing::parse_tree::*;
+
+
+/// value of a symbol of the replacement given to a semantic action: the token read for a terminal, and the value
+/// computed by the action of its rule for a non-terminal
+#[derive(Debug, Clone)]
+pub enum SemanticValue<V> {
+    Token(Token),
+    Value(V),
+}
+
+impl <V> SemanticValue<V> {
+
+    pub fn token(self) -> Option<Token> {
+        match self {
+            SemanticValue::Token(token) => Some(token),
+            SemanticValue::Value(_) => None,
+        }
+    }
+
+    pub fn value(self) -> Option<V> {
+        match self {
+            SemanticValue::Token(_) => None,
+            SemanticValue::Value(value) => Some(value),
+        }
+    }
+
+    /// panics if the symbol is a non-terminal
+    pub fn unwrap_token(self) -> Token {
+        self.token().expect("the symbol is a non-terminal, it has no token")
+    }
+
+    /// panics if the symbol is a terminal
+    pub fn unwrap_value(self) -> V {
+        self.value().expect("the symbol is a terminal, it has no value")
+    }
+}
+
+// --------------------------------------------
+
+/// computes the value of the origin of a rule from the values of its replacement, when the rule is reduced
+pub trait SemanticAction<V> {
+    fn apply(&self, children: Vec<SemanticValue<V>>) -> V;
+}
+
+impl <V, F> SemanticAction<V> for F
+where
+    F: Fn(Vec<SemanticValue<V>>) -> V,
+{
+    fn apply(&self, children: Vec<SemanticValue<V>>) -> V {
+        self(children)
+    }
+}
+
+
+/// the semantic actions of the rules of a grammar, like the actions of yacc
+/// a rule without action takes the value of the first non-terminal of its replacement ($$ = $1)
+pub struct SemanticActions<V> {
+    actions: Vec<Option<Box<dyn SemanticAction<V>>>>,
+
+    // indexed by rules, whether the replacement has a non-terminal to take the value of
+    have_non_terminal: Vec<bool>,
+}
+
+impl <V> SemanticActions<V> {
+
+    /// no rule has an action yet
+    pub fn new(cfg: &Cfg) -> Self {
+        SemanticActions {
+            actions: (0..usize::from(cfg.nbr_rules())).map(|_| None).collect(),
+            have_non_terminal: cfg
+                .all_rules()
+                .map(|(_, rule)| rule.replacement.iter().any(|&symbol| cfg.is_non_terminal(symbol)))
+                .collect(),
+        }
+    }
+
+    pub fn set_action<A: SemanticAction<V> + 'static>(&mut self, rule_id: CfgRuleIdx, action: A) -> &mut Self {
+        self.actions[usize::from(rule_id)] = Some(Box::new(action));
+        self
+    }
+
+    pub fn has_action(&self, rule_id: CfgRuleIdx) -> bool {
+        self.actions[usize::from(rule_id)].is_some()
+    }
+
+    /// the rules without action and without non-terminal in their replacement, they can't get a value
+    pub fn missing_actions(&self) -> Vec<CfgRuleIdx> {
+        (0..self.actions.len())
+            .filter(|&index| self.actions[index].is_none() && !self.have_non_terminal[index])
+            .map(CfgRuleIdx::from)
+            .collect()
+    }
+
+    /// panics if the rule has no action and no non-terminal in its replacement, see missing_actions
+    pub fn apply(&self, rule_id: CfgRuleIdx, children: Vec<SemanticValue<V>>) -> V {
+        match &self.actions[usize::from(rule_id)] {
+            Some(action) => action.apply(children),
+            None => children
+                .into_iter()
+                .find_map(SemanticValue::value)
+                .unwrap_or_else(|| panic!("the rule {} has no semantic action and no non-terminal to take the value of", rule_id.0)),
+        }
+    }
+}
+
+
+/// builder computing the values of the semantic actions during the parse, instead of a parse tree
+pub struct SemanticEvaluator<'s, V> {
+    actions: &'s SemanticActions<V>,
+    value: Option<V>,
+}
+
+impl <'s, V> SemanticEvaluator<'s, V> {
+
+    /// fails if some rules can't get a value, so the evaluation can't fail during the parse
+    pub fn new(actions: &'s SemanticActions<V>) -> Result<Self, ParserError> {
+        let missing_actions: Vec<CfgRuleIdx> = actions.missing_actions();
+        if !missing_actions.is_empty() {
+            return Err(ParserError::MissingSemanticActions { rules: missing_actions });
+        }
+        Ok(SemanticEvaluator { actions, value: None })
+    }
+
+    /// the value of the rule of START, once the input is accepted
+    pub fn value(&self) -> Option<&V> {
+        self.value.as_ref()
+    }
+
+    pub fn into_value(self) -> Option<V> {
+        self.value
+    }
+}
+
+impl <'s, V> ParseBuilder for SemanticEvaluator<'s, V> {
+    type Value = SemanticValue<V>;
+
+    fn shift(&mut self, token: &Token) -> SemanticValue<V> {
+        SemanticValue::Token(token.clone())
+    }
+
+    fn reduce(&mut self, rule_id: CfgRuleIdx, _origin: Symbol, children: Vec<SemanticValue<V>>) -> SemanticValue<V> {
+        SemanticValue::Value(self.actions.apply(rule_id, children))
+    }
+
+    fn accept(&mut self, value: SemanticValue<V>) {
+        self.value = value.value();
+    }
+
+    fn clear(&mut self) {
+        self.value = None;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::test_grammars::{create_expression_grammar, create_grammar, tokens};
+    use crate::lexing::machine::MachineError;
+    use crate::parsing::LL_parsing::LL1_generator::generate_LL1_table;
+    use crate::parsing::LL_parsing::LL1_parser::LL1Parser;
+    use crate::parsing::LL_parsing::LLk_generator::{generate_LLk_table, LLkMode};
+    use crate::parsing::LL_parsing::LLk_parser::LLkParser;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_parser::LR1Parser;
+    use crate::parsing::LR_parsing::LRk_parser::{generate_LRk_table, LRkParser};
+    use crate::parsing::BuildingParser;
+
+    fn rule(cfg: &Cfg, repr: &str) -> CfgRuleIdx {
+        (0..cfg.nbr_rules().0).map(CfgRuleIdx).find(|&rule_id| cfg.repr_rule(rule_id) == repr).unwrap()
+    }
+
+    /// the ids take the lexeme of the token, the other tokens take their type
+    fn number_tokens(cfg: &Cfg, lexemes: &[&str]) -> Vec<Token> {
+        let token_types: Vec<&str> = lexemes
+            .iter()
+            .map(|lexeme| if lexeme.parse::<i64>().is_ok() { "id" } else { lexeme })
+            .collect();
+        let mut tokens: Vec<Token> = tokens(cfg, &token_types);
+        for (token, lexeme) in tokens.iter_mut().zip(lexemes) {
+            token.lexeme = String::from(*lexeme);
+        }
+        tokens
+    }
+
+    #[test]
+    fn evaluate_with_LR() {
+        let cfg = create_expression_grammar();
+        let mut actions: SemanticActions<i64> = SemanticActions::new(&cfg);
+        actions
+            .set_action(rule(&cfg, "E --> E + T"), |children: Vec<SemanticValue<i64>>| {
+                let mut children = children.into_iter();
+                let left: i64 = children.next().unwrap().unwrap_value();
+                left + children.nth(1).unwrap().unwrap_value()
+            })
+            .set_action(rule(&cfg, "T --> T * F"), |children: Vec<SemanticValue<i64>>| {
+                let mut children = children.into_iter();
+                let left: i64 = children.next().unwrap().unwrap_value();
+                left * children.nth(1).unwrap().unwrap_value()
+            })
+            .set_action(rule(&cfg, "F --> id"), |children: Vec<SemanticValue<i64>>| {
+                children.into_iter().next().unwrap().unwrap_token().lexeme.parse::<i64>().unwrap()
+            });
+        // START --> E END, E --> T, T --> F and F --> ( E ) take the value of their non-terminal
+        assert!(!actions.has_action(rule(&cfg, "F --> ( E )")));
+
+        // 2 + 3 * (4 + 5) * 2
+        let input: Vec<Token> = number_tokens(&cfg, &["2", "+", "3", "*", "(", "4", "+", "5", ")", "*", "2"]);
+
+        // F --> id has no non-terminal to take the value of, the parse doesn't start without its action
+        let table = generate_LALR1_table(&cfg);
+        let parser = LR1Parser::new(&cfg, &table);
+        let result = parser.parse_with_actions(input.clone().into_iter(), &SemanticActions::<i64>::new(&cfg));
+        let Err(MachineError::Other { other_err: err @ ParserError::MissingSemanticActions { .. } }) = result else {
+            panic!("F --> id has no action");
+        };
+        assert_eq!(err.repr(&cfg), "1 rules have no semantic action and no non-terminal to take the value of: F --> id");
+
+        assert_eq!(parser.parse_with_actions(input.clone().into_iter(), &actions).unwrap(), 56);
+
+        let table = generate_LRk_table(&cfg, 2).unwrap();
+        let parser = LRkParser::new(&cfg, &table);
+        assert_eq!(parser.parse_with_actions(input.into_iter(), &actions).unwrap(), 56);
+
+        // the values aren't computed for an invalid input
+        let table = generate_LALR1_table(&cfg);
+        let parser = LR1Parser::new(&cfg, &table);
+        assert!(parser.parse_with_actions(number_tokens(&cfg, &["2", "+", "*"]).into_iter(), &actions).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Ast {
+        Number(i64),
+        Add(Box<Ast>, Box<Ast>),
+        Mul(Box<Ast>, Box<Ast>),
+        // right operands of the tail of a sum or a product, the grammar can't be left-recursive
+        Operands(Vec<Ast>),
+    }
+
+    impl Ast {
+        fn repr(&self) -> String {
+            match self {
+                Ast::Number(number) => number.to_string(),
+                Ast::Add(left, right) => format!("({} + {})", left.repr(), right.repr()),
+                Ast::Mul(left, right) => format!("({} * {})", left.repr(), right.repr()),
+                Ast::Operands(operands) => format!("{:?}", operands),
+            }
+        }
+    }
+
+    #[test]
+    fn build_ast_with_LL() {
+        let cfg = create_grammar(
+            &["+", "*", "(", ")", "id"],
+            &["E", "E'", "T", "T'", "F"],
+            &[
+                ("START", &["E", "END"]),
+                ("E", &["T", "E'"]),
+                ("E'", &["+", "T", "E'"]),
+                ("E'", &[]),
+                ("T", &["F", "T'"]),
+                ("T'", &["*", "F", "T'"]),
+                ("T'", &[]),
+                ("F", &["(", "E", ")"]),
+                ("F", &["id"]),
+            ],
+        );
+
+        // the operands of the tail are folded to the left on the first operand
+        let fold = |operation: fn(Box<Ast>, Box<Ast>) -> Ast| move |children: Vec<SemanticValue<Ast>>| {
+            let mut children = children.into_iter();
+            let first: Ast = children.next().unwrap().unwrap_value();
+            let Ast::Operands(operands) = children.next().unwrap().unwrap_value() else { unreachable!() };
+            operands.into_iter().fold(first, |left, right| operation(Box::new(left), Box::new(right)))
+        };
+        let tail = |children: Vec<SemanticValue<Ast>>| {
+            let mut children = children.into_iter().skip(1);
+            let operand: Ast = children.next().unwrap().unwrap_value();
+            let Ast::Operands(operands) = children.next().unwrap().unwrap_value() else { unreachable!() };
+            Ast::Operands(std::iter::once(operand).chain(operands).collect())
+        };
+
+        let mut actions: SemanticActions<Ast> = SemanticActions::new(&cfg);
+        actions
+            .set_action(rule(&cfg, "E --> T E'"), fold(Ast::Add))
+            .set_action(rule(&cfg, "E' --> + T E'"), tail)
+            .set_action(rule(&cfg, "E' -->"), |_| Ast::Operands(Vec::new()))
+            .set_action(rule(&cfg, "T --> F T'"), fold(Ast::Mul))
+            .set_action(rule(&cfg, "T' --> * F T'"), tail)
+            .set_action(rule(&cfg, "T' -->"), |_| Ast::Operands(Vec::new()))
+            .set_action(rule(&cfg, "F --> id"), |children: Vec<SemanticValue<Ast>>| {
+                Ast::Number(children.into_iter().next().unwrap().unwrap_token().lexeme.parse::<i64>().unwrap())
+            });
+
+        let input: Vec<Token> = number_tokens(&cfg, &["1", "+", "2", "*", "(", "3", "+", "4", ")", "*", "5", "+", "6"]);
+        let expected: &str = "((1 + ((2 * (3 + 4)) * 5)) + 6)";
+
+        let table = generate_LL1_table(&cfg).unwrap();
+        let ast: Ast = LL1Parser::new(&cfg, &table).parse_with_actions(input.clone().into_iter(), &actions).unwrap();
+        println!("{}", ast.repr());
+        assert_eq!(ast.repr(), expected);
+
+        let table = generate_LLk_table(&cfg, 2, LLkMode::Full).unwrap();
+        let ast: Ast = LLkParser::new(&cfg, &table).parse_with_actions(input.into_iter(), &actions).unwrap();
+        assert_eq!(ast.repr(), expected);
+    }
+}
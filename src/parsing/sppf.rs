@@ -0,0 +1,653 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::formal_language::*;
+use crate::formal_language::precedence::{Associativity, PrecedenceTable};
+use crate::parsing::parse_tree::*;
+
+
+/// a single derivation taken from the forest, with its subtrees shared between the derivations
+enum Derivation {
+    Leaf{token_id: usize},
+    Inner{rule_id: CfgRuleIdx, origin: Symbol, children: Vec<Rc<Derivation>>},
+}
+
+
+/// one way of deriving the symbol of a node: the rule applied and the nodes of its replacement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SppfFamily {
+    pub rule_id: CfgRuleIdx,
+    pub children: Vec<usize>,
+}
+
+/// a symbol deriving the tokens from start (included) to end (excluded)
+/// terminal nodes have no family, non-terminal nodes with several families are ambiguous
+#[derive(Debug, Clone)]
+pub struct SppfNode {
+    pub symbol: Symbol,
+    pub start: usize,
+    pub end: usize,
+    pub families: Vec<SppfFamily>,
+}
+
+
+/// shared packed parse forest: every parse of the tokens, with a single node for each (symbol, start, end)
+/// the nodes with several families are the ambiguity nodes
+/// the nodes are stored in a vector and refer to each other by their index
+/// the forest can contain cycles when the grammar has derivations like A =>+ A
+/// the families hold the whole replacement of their rule, there are no intermediate nodes for the prefixes of the
+/// rules (as in the binarized forests of Scott), so the disambiguation filters can see the first and last children;
+/// the forest is then cubic in the number of tokens only when the rules have at most two symbols, a node of a rule
+/// with m symbols can have O(n^(m-1)) families when its span can be split between them in many ways
+#[derive(Debug, Clone, Default)]
+pub struct Sppf {
+    tokens: Vec<Token>,
+    nodes: Vec<SppfNode>,
+    node_ids: HashMap<(Symbol, usize, usize), usize>,
+    root: Option<usize>,
+}
+
+impl Sppf {
+
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Sppf {
+            tokens,
+            nodes: Vec::new(),
+            node_ids: HashMap::new(),
+            root: None,
+        }
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn nbr_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn root(&self) -> Option<usize> {
+        self.root
+    }
+
+    pub fn set_root(&mut self, node_id: usize) {
+        self.root = Some(node_id);
+    }
+
+    pub fn get_node(&self, node_id: usize) -> &SppfNode {
+        &self.nodes[node_id]
+    }
+
+    pub fn find_node(&self, symbol: Symbol, start: usize, end: usize) -> Option<usize> {
+        self.node_ids.get(&(symbol, start, end)).copied()
+    }
+
+    /// adds the token after the last one, returns its node
+    pub fn add_token(&mut self, token: Token) -> usize {
+        let position: usize = self.tokens.len();
+        let symbol: Symbol = token.token_type;
+        self.tokens.push(token);
+        self.add_node(symbol, position, position+1)
+    }
+
+    /// the node of the symbol over the span, created without family if it doesn't exist
+    pub fn add_node(&mut self, symbol: Symbol, start: usize, end: usize) -> usize {
+        *self.node_ids.entry((symbol, start, end)).or_insert_with(|| {
+            self.nodes.push(SppfNode { symbol, start, end, families: Vec::new() });
+            self.nodes.len()-1
+        })
+    }
+
+    /// adds a way of deriving the node, returns false if the node already had it
+    pub fn add_family(&mut self, node_id: usize, rule_id: CfgRuleIdx, children: Vec<usize>) -> bool {
+        let family: SppfFamily = SppfFamily { rule_id, children };
+        let families: &mut Vec<SppfFamily> = &mut self.nodes[node_id].families;
+        if families.contains(&family) {
+            return false;
+        }
+        families.push(family);
+        true
+    }
+
+    pub fn is_ambiguous(&self, node_id: usize) -> bool {
+        self.nodes[node_id].families.len() > 1
+    }
+
+    /// the nodes with several families
+    pub fn ambiguous_nodes(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nbr_nodes()).filter(|&node_id| self.is_ambiguous(node_id))
+    }
+
+    /// terminal nodes are the only ones which don't need a family to be derived
+    fn is_token_node(&self, node_id: usize) -> bool {
+        let node: &SppfNode = &self.nodes[node_id];
+        node.end == node.start+1 && self.tokens.get(node.start).is_some_and(|token| token.token_type == node.symbol)
+    }
+
+    /// a node is alive if it is a terminal node or has a family left
+    fn is_alive(&self, node_id: usize) -> bool {
+        self.is_token_node(node_id) || !self.nodes[node_id].families.is_empty()
+    }
+
+    /// the nodes reachable from the root, each one after its children (except in cycles)
+    fn post_order(&self) -> Vec<usize> {
+        let mut post_order: Vec<usize> = Vec::new();
+        let mut is_visited: Vec<bool> = vec![false; self.nbr_nodes()];
+        // (node, children already pushed)
+        let mut to_visit: Vec<(usize, bool)> = self.root.map(|root_id| (root_id, false)).into_iter().collect();
+        while let Some((node_id, are_children_pushed)) = to_visit.pop() {
+            if are_children_pushed {
+                post_order.push(node_id);
+                continue;
+            }
+            if is_visited[node_id] {
+                continue;
+            }
+            is_visited[node_id] = true;
+            to_visit.push((node_id, true));
+            for family in self.nodes[node_id].families.iter().rev() {
+                for &child_id in family.children.iter().rev() {
+                    if !is_visited[child_id] {
+                        to_visit.push((child_id, false));
+                    }
+                }
+            }
+        }
+        post_order
+    }
+
+    // -------------------------- derivations
+
+    /// number of derivations of the node, None if there are infinitely many because of a cycle
+    /// the count saturates at u128::MAX
+    pub fn count_derivations(&self, node_id: usize) -> Option<u128> {
+        // None: not visited yet, Some(None): being counted, Some(Some(count)): counted
+        let mut counts: Vec<Option<Option<u128>>> = vec![None; self.nbr_nodes()];
+        self.count_node_derivations(node_id, &mut counts)
+    }
+
+    /// number of parse trees of the tokens, 0 if they weren't accepted
+    pub fn nbr_derivations(&self) -> Option<u128> {
+        match self.root {
+            Some(root_id) => self.count_derivations(root_id),
+            None => Some(0),
+        }
+    }
+
+    fn count_node_derivations(&self, node_id: usize, counts: &mut Vec<Option<Option<u128>>>) -> Option<u128> {
+        if self.is_token_node(node_id) {
+            return Some(1);
+        }
+        match counts[node_id] {
+            Some(Some(count)) => return Some(count),
+            // the node is part of its own derivation
+            Some(None) => return None,
+            None => {}
+        }
+        counts[node_id] = Some(None);
+
+        let mut count: Option<u128> = Some(0);
+        for family in &self.nodes[node_id].families {
+            let mut family_count: Option<u128> = Some(1);
+            for &child_id in &family.children {
+                family_count = match (family_count, self.count_node_derivations(child_id, counts)) {
+                    (Some(family_count), Some(child_count)) => Some(family_count.saturating_mul(child_count)),
+                    _ => None,
+                };
+            }
+            count = match (count, family_count) {
+                (Some(count), Some(family_count)) => Some(count.saturating_add(family_count)),
+                _ => None,
+            };
+        }
+
+        if count.is_some() {
+            counts[node_id] = Some(count);
+        }
+        else {
+            // the nodes of the cycle are counted again from the other nodes, which find the cycle too
+            counts[node_id] = None;
+        }
+        count
+    }
+
+    /// at most limit parse trees of the tokens, only the derivations which don't go through the same node
+    /// twice are enumerated, so there are finitely many of them even with cycles
+    pub fn derivations(&self, limit: usize) -> Vec<ParseTree> {
+        let Some(root_id) = self.root else {
+            return vec![];
+        };
+        let mut is_on_path: Vec<bool> = vec![false; self.nbr_nodes()];
+        self.enumerate_derivations(root_id, limit, &mut is_on_path)
+            .into_iter()
+            .map(|derivation| {
+                let mut parse_tree: ParseTree = ParseTree::new();
+                let root_id: usize = self.add_to_parse_tree(&derivation, &mut parse_tree);
+                parse_tree.set_root(root_id);
+                parse_tree
+            })
+            .collect()
+    }
+
+    /// the parse tree of the first derivation, None if the tokens weren't accepted
+    /// once the forest is disambiguated, it is the only one
+    pub fn to_parse_tree(&self) -> Option<ParseTree> {
+        self.derivations(1).pop()
+    }
+
+    fn enumerate_derivations(&self, node_id: usize, limit: usize, is_on_path: &mut Vec<bool>) -> Vec<Rc<Derivation>> {
+        if self.is_token_node(node_id) {
+            return vec![Rc::new(Derivation::Leaf { token_id: self.nodes[node_id].start })];
+        }
+        if is_on_path[node_id] {
+            return vec![];
+        }
+        is_on_path[node_id] = true;
+
+        let mut derivations: Vec<Rc<Derivation>> = Vec::new();
+        for family in &self.nodes[node_id].families {
+            // every combination of the derivations of the children, cut at limit
+            let mut combinations: Vec<Vec<Rc<Derivation>>> = vec![vec![]];
+            for &child_id in &family.children {
+                let child_derivations: Vec<Rc<Derivation>> = self.enumerate_derivations(child_id, limit, is_on_path);
+                combinations = combinations
+                    .iter()
+                    .flat_map(|combination| child_derivations.iter().map(move |child_derivation| {
+                        let mut combination: Vec<Rc<Derivation>> = combination.clone();
+                        combination.push(child_derivation.clone());
+                        combination
+                    }))
+                    .take(limit)
+                    .collect();
+            }
+            derivations.extend(combinations
+                .into_iter()
+                .map(|children| Rc::new(Derivation::Inner { rule_id: family.rule_id, origin: self.nodes[node_id].symbol, children }))
+                .take(limit - derivations.len()));
+            if derivations.len() == limit {
+                break;
+            }
+        }
+
+        is_on_path[node_id] = false;
+        derivations
+    }
+
+    fn add_to_parse_tree(&self, derivation: &Derivation, parse_tree: &mut ParseTree) -> usize {
+        match derivation {
+            Derivation::Leaf { token_id } =>
+                parse_tree.add_node(ParseTreeNodeKind::Leaf { token: self.tokens[*token_id].clone() }, None),
+            Derivation::Inner { rule_id, origin, children } => {
+                let children: Vec<usize> = children.iter().map(|child| self.add_to_parse_tree(child, parse_tree)).collect();
+                parse_tree.add_parent_node(ParseTreeNodeKind::Inner { rule_id: *rule_id, origin: *origin }, children)
+            }
+        }
+    }
+
+    // -------------------------- disambiguation
+
+    /// prunes the families of the nodes reachable from the root with the filters, each node being filtered after
+    /// its children, then removes the families which lost a child and the root if it has none left
+    /// returns the nodes reachable from the root which are still ambiguous
+    pub fn disambiguate(&mut self, filters: &[&dyn DisambiguationFilter]) -> Vec<usize> {
+        for node_id in self.post_order() {
+            self.remove_dead_families(node_id);
+            for filter in filters {
+                self.nodes[node_id].families = filter.filter(self, node_id);
+            }
+        }
+
+        // cycles can make a child die after its parent was filtered
+        loop {
+            let mut has_changed: bool = false;
+            for node_id in self.post_order() {
+                has_changed |= self.remove_dead_families(node_id);
+            }
+            if !has_changed {
+                break;
+            }
+        }
+
+        if self.root.is_some_and(|root_id| !self.is_alive(root_id)) {
+            self.root = None;
+        }
+        self.post_order().into_iter().filter(|&node_id| self.is_ambiguous(node_id)).collect()
+    }
+
+    /// returns true if a family was removed
+    fn remove_dead_families(&mut self, node_id: usize) -> bool {
+        let families: Vec<SppfFamily> = std::mem::take(&mut self.nodes[node_id].families);
+        let nbr_families: usize = families.len();
+        let alive_families: Vec<SppfFamily> = families
+            .into_iter()
+            .filter(|family| family.children.iter().all(|&child_id| self.is_alive(child_id)))
+            .collect();
+        let has_changed: bool = alive_families.len() != nbr_families;
+        self.nodes[node_id].families = alive_families;
+        has_changed
+    }
+
+    pub fn repr_node(&self, cfg: &Cfg, node_id: usize) -> String {
+        let node: &SppfNode = self.get_node(node_id);
+        format!("{}({}, {})", cfg.repr_symbol(node.symbol), node.start, node.end)
+    }
+
+    /// one line per non-terminal node, followed by one line per family
+    pub fn repr(&self, cfg: &Cfg) -> String {
+        let mut s: String = String::new();
+        for (node_id, node) in self.nodes.iter().enumerate() {
+            if node.families.is_empty() && cfg.is_terminal(node.symbol) {
+                continue;
+            }
+            s.push_str(&self.repr_node(cfg, node_id));
+            if self.is_ambiguous(node_id) {
+                s.push_str(" ambiguous");
+            }
+            s.push('\n');
+            for family in &node.families {
+                let children: Vec<String> = family.children.iter().map(|&child_id| self.repr_node(cfg, child_id)).collect();
+                s.push_str(&format!("    {}: {}\n", cfg.repr_rule(family.rule_id), children.join(" ")));
+            }
+        }
+        s
+    }
+}
+
+// --------------------------------------------
+
+/// decides which families of a node of the forest are kept
+pub trait DisambiguationFilter {
+    /// the families kept among the ones of the node, its children have already been filtered
+    fn filter(&self, sppf: &Sppf, node_id: usize) -> Vec<SppfFamily>;
+}
+
+/// whether all the families of the node use one of the rules, false for terminal nodes
+fn is_only_built_with(sppf: &Sppf, node_id: usize, rules: &HashSet<CfgRuleIdx>) -> bool {
+    let families: &[SppfFamily] = &sppf.get_node(node_id).families;
+    !families.is_empty() && families.iter().all(|family| rules.contains(&family.rule_id))
+}
+
+
+/// priorities between rules, like in SDF: a rule can't have a first or last child built only with rules of lower
+/// priority (the children in between are enclosed by the terminals around them)
+/// e.g. with E -> E * E > E -> E + E, a sum can't be an operand of a product
+#[derive(Debug, Clone, Default)]
+pub struct PriorityFilter {
+    lower_rules: HashMap<CfgRuleIdx, HashSet<CfgRuleIdx>>,
+}
+
+impl PriorityFilter {
+
+    pub fn new() -> Self {
+        PriorityFilter { lower_rules: HashMap::new() }
+    }
+
+    /// the rules with a precedence have priority over the ones with a lower level
+    pub fn from_precedence_table(cfg: &Cfg, precedence_table: &PrecedenceTable) -> Self {
+        let mut priority_filter: PriorityFilter = PriorityFilter::new();
+        let levels: Vec<(CfgRuleIdx, u16)> = cfg
+            .all_rules()
+            .filter_map(|(rule_id, _)| precedence_table.get_rule_precedence(cfg, rule_id).map(|precedence| (rule_id, precedence.level)))
+            .collect();
+        for &(higher_rule_id, higher_level) in &levels {
+            for &(lower_rule_id, lower_level) in &levels {
+                if higher_level > lower_level {
+                    priority_filter.add_priority(higher_rule_id, lower_rule_id);
+                }
+            }
+        }
+        priority_filter
+    }
+
+    pub fn add_priority(&mut self, higher_rule_id: CfgRuleIdx, lower_rule_id: CfgRuleIdx) -> &mut Self {
+        self.lower_rules.entry(higher_rule_id).or_default().insert(lower_rule_id);
+        self
+    }
+}
+
+impl DisambiguationFilter for PriorityFilter {
+    fn filter(&self, sppf: &Sppf, node_id: usize) -> Vec<SppfFamily> {
+        sppf.get_node(node_id).families
+            .iter()
+            .filter(|family| {
+                let Some(lower_rules) = self.lower_rules.get(&family.rule_id) else {
+                    return true;
+                };
+                let edge_children = family.children.first().into_iter().chain(family.children.last());
+                !edge_children.into_iter().any(|&child_id| is_only_built_with(sppf, child_id, lower_rules))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+
+/// associativity of groups of rules: with a left associative group, a rule of the group can't have a last child
+/// built only with rules of the group, with a right associative group it is the first child, and with a
+/// non-associative group both
+/// e.g. with E -> E - E left associative, a - b - c is (a - b) - c
+#[derive(Debug, Clone, Default)]
+pub struct AssociativityFilter {
+    groups: Vec<(Associativity, HashSet<CfgRuleIdx>)>,
+    // index in groups
+    rule_groups: HashMap<CfgRuleIdx, usize>,
+}
+
+impl AssociativityFilter {
+
+    pub fn new() -> Self {
+        AssociativityFilter { groups: Vec::new(), rule_groups: HashMap::new() }
+    }
+
+    /// one group for each level, with the rules which have its precedence
+    pub fn from_precedence_table(cfg: &Cfg, precedence_table: &PrecedenceTable) -> Self {
+        let mut levels: Vec<(u16, Associativity, Vec<CfgRuleIdx>)> = Vec::new();
+        for (rule_id, _) in cfg.all_rules() {
+            let Some(precedence) = precedence_table.get_rule_precedence(cfg, rule_id) else {
+                continue;
+            };
+            match levels.iter_mut().find(|(level, _, _)| *level == precedence.level) {
+                Some((_, _, rules)) => rules.push(rule_id),
+                None => levels.push((precedence.level, precedence.associativity, vec![rule_id])),
+            }
+        }
+
+        let mut associativity_filter: AssociativityFilter = AssociativityFilter::new();
+        for (_, associativity, rules) in levels {
+            associativity_filter.add_group(associativity, &rules);
+        }
+        associativity_filter
+    }
+
+    pub fn add_group(&mut self, associativity: Associativity, rules: &[CfgRuleIdx]) -> &mut Self {
+        for &rule_id in rules {
+            self.rule_groups.insert(rule_id, self.groups.len());
+        }
+        self.groups.push((associativity, rules.iter().copied().collect()));
+        self
+    }
+}
+
+impl DisambiguationFilter for AssociativityFilter {
+    fn filter(&self, sppf: &Sppf, node_id: usize) -> Vec<SppfFamily> {
+        sppf.get_node(node_id).families
+            .iter()
+            .filter(|family| {
+                let Some(&group_id) = self.rule_groups.get(&family.rule_id) else {
+                    return true;
+                };
+                let (associativity, rules) = &self.groups[group_id];
+                let forbidden_children: Vec<Option<&usize>> = match associativity {
+                    Associativity::Left => vec![family.children.last()],
+                    Associativity::Right => vec![family.children.first()],
+                    Associativity::NonAssoc => vec![family.children.first(), family.children.last()],
+                };
+                !forbidden_children.into_iter().flatten().any(|&child_id| is_only_built_with(sppf, child_id, rules))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+
+/// reject rules, like in SDF: a node which can be derived with a rejected rule is removed with all its families
+/// e.g. with Name -> if rejected, the keyword if can't be used as a name
+#[derive(Debug, Clone, Default)]
+pub struct RejectFilter {
+    rejected_rules: HashSet<CfgRuleIdx>,
+}
+
+impl RejectFilter {
+
+    pub fn new(rejected_rules: &[CfgRuleIdx]) -> Self {
+        RejectFilter { rejected_rules: rejected_rules.iter().copied().collect() }
+    }
+}
+
+impl DisambiguationFilter for RejectFilter {
+    fn filter(&self, sppf: &Sppf, node_id: usize) -> Vec<SppfFamily> {
+        let families: &[SppfFamily] = &sppf.get_node(node_id).families;
+        if families.iter().any(|family| self.rejected_rules.contains(&family.rule_id)) {
+            vec![]
+        }
+        else {
+            families.to_vec()
+        }
+    }
+}
+
+
+/// longest match: keeps the families whose children end the furthest, comparing them from the first one, so the
+/// first children take as many tokens as possible
+/// e.g. the else is attached to the closest if
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreferLongestFilter;
+
+impl DisambiguationFilter for PreferLongestFilter {
+    fn filter(&self, sppf: &Sppf, node_id: usize) -> Vec<SppfFamily> {
+        let families: &[SppfFamily] = &sppf.get_node(node_id).families;
+        let children_ends = |family: &SppfFamily| -> Vec<usize> {
+            family.children.iter().map(|&child_id| sppf.get_node(child_id).end).collect()
+        };
+        let Some(longest_ends) = families.iter().map(children_ends).max() else {
+            return vec![];
+        };
+        families.iter().filter(|family| children_ends(family) == longest_ends).cloned().collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammars::math_grammar::create_math_grammar;
+    use crate::grammars::test_grammars::{create_ambiguous_sum_grammar, create_dangling_else_grammar, create_grammar, tokens};
+    use crate::parsing::general_parsing::Earley_parser::EarleyParser;
+    use crate::parsing::LR_parsing::LR1_generators::LALR1_generator::generate_LALR1_table;
+    use crate::parsing::LR_parsing::LR1_parser::LR1Parser;
+    use crate::parsing::BuildingParser;
+
+    #[test]
+    fn count_and_enumerate_derivations() {
+        let cfg = create_ambiguous_sum_grammar();
+        let sppf = EarleyParser::new(&cfg).parse_from_iter(tokens(&cfg, &["id", "+", "id", "+", "id", "+", "id"]).into_iter()).unwrap();
+
+        // the Catalan number C(3)
+        assert_eq!(sppf.nbr_derivations(), Some(5));
+        let mut reprs: Vec<String> = sppf.derivations(10).iter().map(|parse_tree| parse_tree.repr(&cfg)).collect();
+        reprs.sort();
+        reprs.dedup();
+        assert_eq!(reprs.len(), 5);
+        assert_eq!(sppf.derivations(3).len(), 3);
+    }
+
+    #[test]
+    fn cycle() {
+        // A =>+ A, so there are infinitely many derivations
+        let cfg = create_grammar(
+            &["a"],
+            &["A"],
+            &[
+                ("START", &["A", "END"]),
+                ("A", &["A"]),
+                ("A", &["a"]),
+            ],
+        );
+        let sppf = EarleyParser::new(&cfg).parse_from_iter(tokens(&cfg, &["a"]).into_iter()).unwrap();
+        println!("{}", sppf.repr(&cfg));
+
+        assert_eq!(sppf.nbr_derivations(), None);
+        assert_eq!(sppf.derivations(10).len(), 1);
+    }
+
+    #[test]
+    fn priority_and_associativity() {
+        let cfg = create_math_grammar();
+        let input: Vec<Token> = tokens(&cfg, &["-", "number", "-", "number", "*", "number", "^", "number", "^", "number", "+", "number"]);
+        let mut sppf = EarleyParser::new(&cfg).parse_from_iter(input.clone().into_iter()).unwrap();
+        assert!(sppf.nbr_derivations().unwrap() > 1);
+
+        // unwrap is safe because the math grammar has a precedence table
+        let precedence_table: &PrecedenceTable = cfg.get_precedence_table().unwrap();
+        let priority_filter = PriorityFilter::from_precedence_table(&cfg, precedence_table);
+        let associativity_filter = AssociativityFilter::from_precedence_table(&cfg, precedence_table);
+        let ambiguous_nodes: Vec<usize> = sppf.disambiguate(&[&priority_filter, &associativity_filter]);
+        assert!(ambiguous_nodes.is_empty());
+        assert_eq!(sppf.nbr_derivations(), Some(1));
+
+        // same tree as the LR parser whose conflicts are resolved with the precedence table
+        let table = generate_LALR1_table(&cfg);
+        let expected: String = LR1Parser::new(&cfg, &table).parse_from_iter(input.into_iter()).unwrap().repr(&cfg);
+        assert_eq!(sppf.to_parse_tree().unwrap().repr(&cfg), expected);
+    }
+
+    #[test]
+    fn reject() {
+        // if x is either a keyword followed by a name, or a name declared with the type if
+        let cfg = create_grammar(
+            &["if", "id"],
+            &["S", "Keyword", "Name"],
+            &[
+                ("START", &["S", "END"]),
+                ("S", &["Keyword", "id"]),
+                ("S", &["Name", "id"]),
+                ("S", &["Name", "id", "id"]),
+                ("Keyword", &["if"]),
+                ("Name", &["id"]),
+                ("Name", &["if"]),
+            ],
+        );
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let (rejected_rule_id, _) = cfg.get_rules_by_origin(S("Name")).find(|(_, rule)| rule.replacement == [S("if")]).unwrap();
+
+        let mut sppf = EarleyParser::new(&cfg).parse_from_iter(tokens(&cfg, &["if", "id"]).into_iter()).unwrap();
+        assert_eq!(sppf.nbr_derivations(), Some(2));
+        assert!(sppf.disambiguate(&[&RejectFilter::new(&[rejected_rule_id])]).is_empty());
+        assert_eq!(sppf.nbr_derivations(), Some(1));
+        let name_node: usize = sppf.find_node(S("Name"), 0, 1).unwrap();
+        assert!(sppf.get_node(name_node).families.is_empty());
+
+        // the only parse is rejected
+        let mut sppf = EarleyParser::new(&cfg).parse_from_iter(tokens(&cfg, &["if", "id", "id"]).into_iter()).unwrap();
+        assert!(sppf.disambiguate(&[&RejectFilter::new(&[rejected_rule_id])]).is_empty());
+        assert!(sppf.root().is_none());
+        assert_eq!(sppf.nbr_derivations(), Some(0));
+    }
+
+    #[test]
+    fn dangling_else() {
+        let cfg = create_dangling_else_grammar();
+        let S = |s: &str| cfg.symbol_set().get_symbol_by_representation(s);
+        let mut sppf = EarleyParser::new(&cfg)
+            .parse_from_iter(tokens(&cfg, &["if", "cond", "then", "if", "cond", "then", "instr", "else", "instr"]).into_iter())
+            .unwrap();
+        assert_eq!(sppf.nbr_derivations(), Some(2));
+
+        assert!(sppf.disambiguate(&[&PreferLongestFilter]).is_empty());
+        // the else belongs to the second if, which spans until the end
+        let outer_if: usize = sppf.find_node(S("S"), 0, 9).unwrap();
+        assert_eq!(sppf.get_node(outer_if).families.len(), 1);
+        assert_eq!(cfg.repr_rule(sppf.get_node(outer_if).families[0].rule_id), "S --> if cond then S");
+        assert!(sppf.find_node(S("S"), 3, 9).is_some_and(|inner_if| sppf.get_node(inner_if).families.len() == 1));
+    }
+}